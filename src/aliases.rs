@@ -0,0 +1,76 @@
+use crate::exit;
+use crate::redirects::parse_qid;
+use crate::{write_batch, DataPoint};
+use arrow::array::{ArrayRef, StringArray, UInt32Array};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::record_batch::RecordBatch;
+use hashbrown::HashMap;
+use parquet::file::metadata::KeyValue;
+use std::io::{BufRead, BufReader};
+use std::sync::Arc;
+
+/// Adds how many times every surface form is used for every QID in `split`, which may be a part
+/// of a split, to `counts`.
+pub fn count_aliases(counts: &mut HashMap<(String, u32), u32>, split: &[DataPoint]) {
+    for example in split {
+        let chars = example.text.chars().collect::<Vec<_>>();
+
+        for entity in &example.entities {
+            let Some(qid) = entity.qid else {
+                continue;
+            };
+            let alias = chars[entity.start as usize..entity.end as usize]
+                .iter()
+                .collect::<String>();
+            *counts.entry((alias, qid)).or_insert(0) += 1;
+        }
+    }
+}
+
+/// Adds the counts from a TSV file of Wikipedia anchors with one
+/// `anchor<TAB>qid<TAB>count` triple per line to `counts`.
+pub fn add_anchor_counts(counts: &mut HashMap<(String, u32), u32>, path: &str) {
+    let reader = BufReader::new(exit::open(path));
+
+    for line in reader.lines().map(|x| x.unwrap()) {
+        let mut fields = line.split('\t');
+        let (Some(anchor), Some(qid), Some(count)) = (fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+        let (Some(qid), Ok(count)) = (parse_qid(qid), count.trim().parse::<u32>()) else {
+            continue;
+        };
+
+        *counts.entry((anchor.to_owned(), qid)).or_insert(0) += count;
+    }
+}
+
+/// Writes the alias table, sorted by the alias and then by the descending count, into a Parquet file.
+pub fn write_aliases(counts: HashMap<(String, u32), u32>, path: &str, metadata: &[KeyValue]) {
+    let mut counts = counts.into_iter().collect::<Vec<_>>();
+    counts.sort_unstable_by(|((a, x), m), ((b, y), n)| a.cmp(b).then(n.cmp(m)).then(x.cmp(y)));
+
+    let aliases = counts
+        .iter()
+        .map(|((alias, _), _)| Some(alias.as_str()))
+        .collect::<StringArray>();
+    let qids = counts
+        .iter()
+        .map(|((_, qid), _)| *qid)
+        .collect::<UInt32Array>();
+    let counts = counts
+        .iter()
+        .map(|(_, count)| *count)
+        .collect::<UInt32Array>();
+
+    let schema = Schema::new(vec![
+        Field::new("alias", DataType::Utf8, false),
+        Field::new("qid", DataType::UInt32, false),
+        Field::new("count", DataType::UInt32, false),
+    ]);
+    let arrays: Vec<ArrayRef> = vec![Arc::new(aliases), Arc::new(qids), Arc::new(counts)];
+
+    let batch = RecordBatch::try_new(Arc::new(schema), arrays).unwrap();
+    write_batch(batch, path, metadata);
+}
@@ -0,0 +1,89 @@
+use crate::normalize::TitleNormalizer;
+use crate::{DataPoint, TokenSpan};
+use hashbrown::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// How far past the end of the previous token a token is looked for in the raw text, which
+/// skips the markup and boilerplate left out of the tokens.
+const MAX_SKIP: usize = 200;
+
+/// Returns the offsets of the `tokens` in `raw`, in characters, or `None` if any of them
+/// can't be found in order.
+fn align(raw: &[char], tokens: &[Vec<char>]) -> Option<Vec<TokenSpan>> {
+    let mut spans = Vec::with_capacity(tokens.len());
+    let mut cursor = 0;
+
+    for token in tokens {
+        let last = raw
+            .len()
+            .min(cursor + MAX_SKIP + token.len())
+            .checked_sub(token.len())?;
+        let start = (cursor..=last).find(|&i| raw[i..].starts_with(token))?;
+
+        cursor = start + token.len();
+        spans.push(TokenSpan {
+            start: start as u64,
+            end: cursor as u64,
+        });
+    }
+
+    Some(spans)
+}
+
+/// Replaces the text of every document with a raw text in `dir`, named by the document ID
+/// (e.g. `946.txt`) and normalized like the tokens, with the offsets of the entities and
+/// tokens aligned with it.
+///
+/// The documents without a raw text, and the ones whose tokens can't be found in their raw
+/// text in order, keep the text joined from the tokens. Returns the IDs of the latter.
+pub fn align_raw_texts(
+    splits: &mut [&mut Vec<DataPoint>],
+    dir: &str,
+    normalizer: &TitleNormalizer,
+) -> Vec<u32> {
+    let mut misaligned = vec![];
+
+    for document in splits.iter_mut().flat_map(|x| x.iter_mut()) {
+        let path = Path::new(dir).join(format!("{}.txt", document.document_id));
+        let Ok(raw) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let raw = normalizer.normalize_text(&raw).chars().collect::<Vec<_>>();
+
+        let text = document.text.chars().collect::<Vec<_>>();
+        let tokens = document
+            .token_spans
+            .iter()
+            .map(|x| text[x.start as usize..x.end as usize].to_vec())
+            .collect::<Vec<_>>();
+
+        let Some(spans) = align(&raw, &tokens) else {
+            misaligned.push(document.document_id);
+            continue;
+        };
+
+        // The entities start and end on the boundaries of the tokens.
+        let starts = document
+            .token_spans
+            .iter()
+            .zip(&spans)
+            .map(|(x, y)| (x.start, y.start))
+            .collect::<HashMap<_, _>>();
+        let ends = document
+            .token_spans
+            .iter()
+            .zip(&spans)
+            .map(|(x, y)| (x.end, y.end))
+            .collect::<HashMap<_, _>>();
+        for entity in &mut document.entities {
+            entity.start = starts[&entity.start];
+            entity.end = ends[&entity.end];
+        }
+
+        document.text = raw.into_iter().collect();
+        document.token_spans = spans;
+    }
+
+    misaligned
+}
@@ -0,0 +1,364 @@
+use apache_avro::types::Value;
+use apache_avro::{from_avro_datum, Codec, Schema};
+use hashbrown::HashMap;
+use std::io::Read;
+use std::str::FromStr;
+
+/// The title, the pageid, and the QID of a record.
+pub type MappingFields<'a> = (&'a str, u32, Option<u32>);
+
+/// How a field of the records is read.
+enum FieldKind {
+    Title,
+    Pageid,
+    /// The QID, with the variants of its union.
+    Qid(Vec<Schema>),
+    /// A field that isn't needed, which is skipped without being decoded.
+    Skipped(Schema),
+}
+
+/// Reads the title, the pageid, and the QID of the records of an Apache Avro file of mappings
+/// straight from its decompressed blocks, skipping every other field without decoding it, and
+/// borrows the titles from the blocks instead of allocating them.
+pub struct MappingScanner<R> {
+    reader: R,
+    codec: Codec,
+    marker: [u8; 16],
+    fields: Vec<FieldKind>,
+    /// The named types of the schema, which the skipped fields may refer to.
+    names: HashMap<String, Schema>,
+    block: Vec<u8>,
+    offset: usize,
+    remaining: usize,
+}
+
+impl<R: Read> MappingScanner<R> {
+    /// Reads the header of the file from `reader`, and returns the scanner of its records and
+    /// the schema they were written with, which is expected to pass
+    /// [`crate::mapping::check_schema`] before any record is read.
+    pub fn new(mut reader: R) -> Result<(Self, Schema), String> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic).map_err(|x| x.to_string())?;
+        if magic != *b"Obj\x01" {
+            return Err("wrong magic in the header".to_owned());
+        }
+
+        let metadata = Schema::Map(Box::new(Schema::Bytes));
+        let Value::Map(metadata) =
+            from_avro_datum(&metadata, &mut reader, None).map_err(|x| x.to_string())?
+        else {
+            return Err("missing the metadata in the header".to_owned());
+        };
+        let entry = |key: &str| match metadata.get(key) {
+            Some(Value::Bytes(x)) => std::str::from_utf8(x).ok(),
+            _ => None,
+        };
+        let schema = entry("avro.schema")
+            .ok_or_else(|| "missing the schema in the header".to_owned())
+            .and_then(|x| Schema::parse_str(x).map_err(|x| x.to_string()))?;
+        let codec = entry("avro.codec").unwrap_or("null");
+        let codec = Codec::from_str(codec).map_err(|_| format!("unsupported codec {codec}"))?;
+
+        let mut marker = [0; 16];
+        reader.read_exact(&mut marker).map_err(|x| x.to_string())?;
+
+        let mut names = HashMap::new();
+        collect_names(&schema, &mut names);
+        let fields = match &schema {
+            Schema::Record { fields, .. } => fields
+                .iter()
+                .map(|field| match (field.name.as_str(), &field.schema) {
+                    ("title", _) => FieldKind::Title,
+                    ("pageid", _) => FieldKind::Pageid,
+                    ("qid", Schema::Union(union)) => FieldKind::Qid(union.variants().to_vec()),
+                    (_, schema) => FieldKind::Skipped(schema.clone()),
+                })
+                .collect(),
+            _ => vec![],
+        };
+
+        let scanner = Self {
+            reader,
+            codec,
+            marker,
+            fields,
+            names,
+            block: vec![],
+            offset: 0,
+            remaining: 0,
+        };
+        Ok((scanner, schema))
+    }
+
+    /// Returns the title, the pageid, and the QID of the next record, or `None` after the last one.
+    pub fn next_record(&mut self) -> Result<Option<MappingFields<'_>>, String> {
+        while self.remaining == 0 {
+            if !self.read_block()? {
+                return Ok(None);
+            }
+        }
+        self.remaining -= 1;
+
+        let mut data = &self.block[self.offset..];
+        let (mut title, mut pageid, mut qid) = (None, None, None);
+        for field in &self.fields {
+            match field {
+                FieldKind::Title => title = Some(read_str(&mut data)?),
+                FieldKind::Pageid => pageid = Some(read_u32(&mut data, "pageid")?),
+                FieldKind::Qid(variants) => {
+                    let index = read_long(&mut data)?;
+                    qid = match usize::try_from(index).ok().and_then(|x| variants.get(x)) {
+                        Some(Schema::Null) => None,
+                        Some(_) => Some(read_u32(&mut data, "qid")?),
+                        None => return Err(format!("invalid variant {index} of the qid")),
+                    };
+                }
+                FieldKind::Skipped(schema) => skip(schema, &mut data, &self.names)?,
+            }
+        }
+        self.offset = self.block.len() - data.len();
+
+        match (title, pageid) {
+            (Some(title), Some(pageid)) => Ok(Some((title, pageid, qid))),
+            _ => Err("missing the title or the pageid".to_owned()),
+        }
+    }
+
+    /// Reads the next block and decompresses it, returning `false` at the end of the file.
+    fn read_block(&mut self) -> Result<bool, String> {
+        let mut first = [0];
+        if self.reader.read(&mut first).map_err(|x| x.to_string())? == 0 {
+            return Ok(false);
+        }
+        let count = read_long(&mut (&first[..]).chain(&mut self.reader))?;
+        let size = read_long(&mut self.reader)?;
+        let (Ok(count), Ok(size)) = (usize::try_from(count), usize::try_from(size)) else {
+            return Err("invalid block header".to_owned());
+        };
+
+        self.block.resize(size, 0);
+        self.reader
+            .read_exact(&mut self.block)
+            .map_err(|x| x.to_string())?;
+        let mut marker = [0; 16];
+        self.reader
+            .read_exact(&mut marker)
+            .map_err(|x| x.to_string())?;
+        if marker != self.marker {
+            return Err("wrong sync marker after a block".to_owned());
+        }
+        self.codec
+            .decompress(&mut self.block)
+            .map_err(|x| x.to_string())?;
+
+        self.offset = 0;
+        self.remaining = count;
+        Ok(true)
+    }
+}
+
+/// Collects the named types of `schema` by their full names.
+fn collect_names(schema: &Schema, names: &mut HashMap<String, Schema>) {
+    match schema {
+        Schema::Record { name, fields, .. } => {
+            names.insert(name.fullname(None), schema.clone());
+            for field in fields {
+                collect_names(&field.schema, names);
+            }
+        }
+        Schema::Enum { name, .. } | Schema::Fixed { name, .. } => {
+            names.insert(name.fullname(None), schema.clone());
+        }
+        Schema::Array(item) | Schema::Map(item) => collect_names(item, names),
+        Schema::Union(union) => {
+            for variant in union.variants() {
+                collect_names(variant, names);
+            }
+        }
+        Schema::Decimal { inner, .. } => collect_names(inner, names),
+        _ => {}
+    }
+}
+
+/// Reads a zigzag encoded variable-length long.
+fn read_long(reader: &mut impl Read) -> Result<i64, String> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0];
+        reader
+            .read_exact(&mut byte)
+            .map_err(|_| "truncated integer".to_owned())?;
+        value |= u64::from(byte[0] & 0x7f) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok((value >> 1) as i64 ^ -((value & 1) as i64));
+        }
+    }
+    Err("integer longer than 64 bits".to_owned())
+}
+
+fn read_u32(data: &mut &[u8], name: &str) -> Result<u32, String> {
+    let value = read_long(data)?;
+    u32::try_from(value).map_err(|_| format!("invalid {name} {value}"))
+}
+
+/// Reads the bytes of a string or of a byte array.
+fn read_bytes<'a>(data: &mut &'a [u8]) -> Result<&'a [u8], String> {
+    let length = usize::try_from(read_long(data)?)
+        .ok()
+        .filter(|x| *x <= data.len())
+        .ok_or_else(|| "truncated string".to_owned())?;
+    let (bytes, rest) = data.split_at(length);
+    *data = rest;
+    Ok(bytes)
+}
+
+fn read_str<'a>(data: &mut &'a [u8]) -> Result<&'a str, String> {
+    std::str::from_utf8(read_bytes(data)?).map_err(|_| "the title isn't UTF-8".to_owned())
+}
+
+fn skip_bytes(data: &mut &[u8], length: usize) -> Result<(), String> {
+    if length > data.len() {
+        return Err("truncated record".to_owned());
+    }
+    *data = &data[length..];
+    Ok(())
+}
+
+/// Skips a value of `schema`, whose named types are looked up in `names`.
+fn skip(schema: &Schema, data: &mut &[u8], names: &HashMap<String, Schema>) -> Result<(), String> {
+    match schema {
+        Schema::Null => Ok(()),
+        Schema::Boolean => skip_bytes(data, 1),
+        Schema::Int
+        | Schema::Long
+        | Schema::Enum { .. }
+        | Schema::Date
+        | Schema::TimeMillis
+        | Schema::TimeMicros
+        | Schema::TimestampMillis
+        | Schema::TimestampMicros => read_long(data).map(|_| ()),
+        Schema::Float => skip_bytes(data, 4),
+        Schema::Double => skip_bytes(data, 8),
+        Schema::Bytes | Schema::String | Schema::Uuid => read_bytes(data).map(|_| ()),
+        Schema::Fixed { size, .. } => skip_bytes(data, *size),
+        Schema::Duration => skip_bytes(data, 12),
+        Schema::Decimal { inner, .. } => skip(inner, data, names),
+        Schema::Array(_) | Schema::Map(_) => loop {
+            let count = read_long(data)?;
+            if count == 0 {
+                return Ok(());
+            }
+            // A negative count is followed by the size of the block, so it's skipped at once.
+            if count < 0 {
+                let size = usize::try_from(read_long(data)?).map_err(|x| x.to_string())?;
+                skip_bytes(data, size)?;
+                continue;
+            }
+            for _ in 0..count {
+                match schema {
+                    Schema::Array(item) => skip(item, data, names)?,
+                    Schema::Map(value) => {
+                        read_bytes(data)?;
+                        skip(value, data, names)?;
+                    }
+                    _ => unreachable!(),
+                }
+            }
+        },
+        Schema::Union(union) => {
+            let index = read_long(data)?;
+            match usize::try_from(index)
+                .ok()
+                .and_then(|x| union.variants().get(x))
+            {
+                Some(variant) => skip(variant, data, names),
+                None => Err(format!("invalid union variant {index}")),
+            }
+        }
+        Schema::Record { fields, .. } => {
+            for field in fields {
+                skip(&field.schema, data, names)?;
+            }
+            Ok(())
+        }
+        Schema::Ref { name } => match names.get(&name.fullname(None)) {
+            Some(schema) => skip(schema, data, names),
+            None => Err(format!("unknown type {}", name.fullname(None))),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use apache_avro::Writer;
+
+    #[test]
+    fn only_the_fields_of_the_mappings_are_read() {
+        let schema = Schema::parse_str(
+            r#"{
+                "type": "record",
+                "name": "Page",
+                "fields": [
+                    {"name": "aliases", "type": {"type": "array", "items": "string"}},
+                    {"name": "title", "type": "string"},
+                    {"name": "views", "type": {"type": "map", "values": "long"}},
+                    {"name": "pageid", "type": "long"},
+                    {"name": "meta", "type": {
+                        "type": "record",
+                        "name": "Meta",
+                        "fields": [{"name": "score", "type": ["null", "double"]}]
+                    }},
+                    {"name": "qid", "type": ["null", "int"]},
+                    {"name": "previous", "type": ["null", "Meta"]}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let mut writer = Writer::with_codec(&schema, vec![], Codec::Deflate);
+        for (title, pageid, qid) in [("Zürich", 34_283, Some(72)), ("Mercury", 19_694, None)] {
+            let meta = Value::Record(vec![(
+                "score".to_owned(),
+                Value::Union(1, Box::new(Value::Double(0.5))),
+            )]);
+            writer
+                .append(Value::Record(vec![
+                    (
+                        "aliases".to_owned(),
+                        Value::Array(vec![Value::String("alias".to_owned())]),
+                    ),
+                    ("title".to_owned(), Value::String(title.to_owned())),
+                    (
+                        "views".to_owned(),
+                        Value::Map([("2020".to_owned(), Value::Long(7))].into()),
+                    ),
+                    ("pageid".to_owned(), Value::Long(pageid)),
+                    ("meta".to_owned(), meta.clone()),
+                    (
+                        "qid".to_owned(),
+                        match qid {
+                            Some(qid) => Value::Union(1, Box::new(Value::Int(qid))),
+                            None => Value::Union(0, Box::new(Value::Null)),
+                        },
+                    ),
+                    ("previous".to_owned(), Value::Union(1, Box::new(meta))),
+                ]))
+                .unwrap();
+        }
+        let bytes = writer.into_inner().unwrap();
+
+        let (mut scanner, _) = MappingScanner::new(bytes.as_slice()).unwrap();
+        let mut records = vec![];
+        while let Some((title, pageid, qid)) = scanner.next_record().unwrap() {
+            records.push((title.to_owned(), pageid, qid));
+        }
+        assert_eq!(
+            records,
+            [
+                ("Zürich".to_owned(), 34_283, Some(72)),
+                ("Mercury".to_owned(), 19_694, None),
+            ]
+        );
+    }
+}
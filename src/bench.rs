@@ -0,0 +1,177 @@
+use crate::exit::{self, fail, Failure};
+use serde_json::Value;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::process::Command;
+
+/// Runs the conversion once with `args` and returns the seconds spent in every stage,
+/// as recorded in its report.
+fn time_conversion(args: &[String], report: &Path) -> Vec<(String, f64)> {
+    let status = Command::new(std::env::current_exe().unwrap())
+        .arg("convert")
+        .args(args)
+        .args(["--overwrite", "--quiet", "--report-json"])
+        .arg(report)
+        .status()
+        .unwrap();
+    if !status.success() {
+        fail(
+            Failure::Other,
+            format!(
+                "The conversion failed with the exit code {}.",
+                status.code().unwrap_or(1)
+            ),
+        );
+    }
+
+    let report: Value = serde_json::from_reader(exit::open(report)).unwrap();
+    report["timings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|x| {
+            (
+                x["stage"].as_str().unwrap().to_owned(),
+                x["seconds"].as_f64().unwrap(),
+            )
+        })
+        .collect()
+}
+
+/// Times every stage of the conversion with `args` over `runs` runs, appends the mean
+/// timings under `label` to the TSV file at `history`, and prints the timings of every
+/// benchmark in it, with the change of the total time relative to the previous one.
+pub fn bench(args: &[String], runs: usize, label: Option<&str>, history: &str) {
+    let report = std::env::temp_dir().join(format!("aida-bench-{}.json", std::process::id()));
+
+    let mut stages = Vec::<String>::new();
+    let mut totals = Vec::<f64>::new();
+    for run in 0..runs {
+        let timings = time_conversion(args, &report);
+        eprintln!(
+            "Run {}/{runs}: {:.3}s",
+            run + 1,
+            timings.iter().map(|x| x.1).sum::<f64>()
+        );
+
+        if stages.is_empty() {
+            stages = timings.iter().map(|x| x.0.clone()).collect();
+            totals = vec![0.0; stages.len()];
+        }
+        for (total, (_, seconds)) in totals.iter_mut().zip(timings) {
+            *total += seconds;
+        }
+    }
+    fs::remove_file(&report).ok();
+
+    let means = totals.iter().map(|x| x / runs as f64).collect::<Vec<_>>();
+
+    let mut rows = read_history(history);
+    let header = match rows.first() {
+        Some(header) => header.clone(),
+        None => {
+            let header = ["label".to_owned()]
+                .into_iter()
+                .chain(stages.iter().cloned())
+                .chain(["total".to_owned()])
+                .collect::<Vec<_>>();
+            rows.push(header.clone());
+            header
+        }
+    };
+
+    // The stages are matched with the columns by their names, so old histories stay readable.
+    let label = label.map_or_else(|| format!("run {}", rows.len()), |x| x.to_owned());
+    let row = header
+        .iter()
+        .map(|column| match column.as_str() {
+            "label" => label.clone(),
+            "total" => format!("{:.3}", means.iter().sum::<f64>()),
+            stage => stages
+                .iter()
+                .position(|x| x == stage)
+                .map(|i| format!("{:.3}", means[i]))
+                .unwrap_or_default(),
+        })
+        .collect::<Vec<_>>();
+
+    let mut writer = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(history)
+        .unwrap_or_else(|error| fail(Failure::Write, format!("Couldn't open {history}: {error}.")));
+    if rows.len() == 1 {
+        writeln!(writer, "{}", header.join("\t")).unwrap();
+    }
+    writeln!(writer, "{}", row.join("\t")).unwrap();
+    rows.push(row);
+
+    print_table(&rows);
+}
+
+fn read_history(path: &str) -> Vec<Vec<String>> {
+    if !Path::new(path).exists() {
+        return vec![];
+    }
+
+    BufReader::new(exit::open(path))
+        .lines()
+        .map(|line| line.unwrap().split('\t').map(|x| x.to_owned()).collect())
+        .collect()
+}
+
+/// Prints the benchmarks as an aligned table, with a column for the change of the total
+/// time relative to the previous benchmark.
+fn print_table(rows: &[Vec<String>]) {
+    let total = rows[0].iter().position(|x| x == "total");
+    let mut previous = None;
+
+    let rows = rows
+        .iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let change = if i == 0 {
+                "change".to_owned()
+            } else {
+                let current = total.and_then(|x| row.get(x)?.parse::<f64>().ok());
+                let change = match (previous, current) {
+                    (Some(previous), Some(current)) if previous > 0.0 => {
+                        format!("{:+.1}%", (current - previous) / previous * 100.0)
+                    }
+                    _ => String::new(),
+                };
+                previous = current;
+                change
+            };
+            row.iter().cloned().chain([change]).collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let columns = rows.iter().map(|x| x.len()).max().unwrap_or_default();
+    let widths = (0..columns)
+        .map(|i| {
+            rows.iter()
+                .filter_map(|x| x.get(i))
+                .map(|x| x.chars().count())
+                .max()
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>();
+
+    for row in rows {
+        let cells = row
+            .iter()
+            .zip(&widths)
+            .enumerate()
+            .map(|(i, (cell, width))| {
+                if i == 0 {
+                    format!("{cell:<width$}")
+                } else {
+                    format!("{cell:>width$}")
+                }
+            })
+            .collect::<Vec<_>>();
+        println!("{}", cells.join("  "));
+    }
+}
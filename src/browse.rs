@@ -0,0 +1,270 @@
+use crate::dataset::{read_dataset, Document, StoredSplit};
+use crate::exit::{fail, Failure};
+use crate::redirects::parse_qid;
+use lazy_regex::regex_is_match;
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{Block, Paragraph, Wrap},
+    DefaultTerminal, Frame,
+};
+
+/// Whether the keys browse the documents or edit the filter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Browse,
+    Filter,
+}
+
+struct Browser {
+    splits: Vec<StoredSplit>,
+    split: usize,
+    /// The positions of the documents of the current split that match the filter.
+    matches: Vec<usize>,
+    /// The position of the current document in `matches`.
+    position: usize,
+    scroll: u16,
+    filter: String,
+    mode: Mode,
+}
+
+impl Browser {
+    fn new(splits: Vec<StoredSplit>) -> Self {
+        let mut browser = Self {
+            splits,
+            split: 0,
+            matches: vec![],
+            position: 0,
+            scroll: 0,
+            filter: String::new(),
+            mode: Mode::Browse,
+        };
+        browser.apply_filter();
+        browser
+    }
+
+    /// Returns whether `document` has a mention matching the filter, which is either a
+    /// QID or a part of the surface form.
+    fn matches(&self, document: &Document) -> bool {
+        if self.filter.is_empty() {
+            return true;
+        }
+
+        let qid = regex_is_match!(r"^[Qq]\d+$", &self.filter)
+            .then(|| parse_qid(&self.filter[1..]))
+            .flatten();
+        document.entities.iter().any(|mention| match qid {
+            Some(qid) => mention.qid == Some(qid),
+            None => document.surface(mention).contains(&self.filter),
+        })
+    }
+
+    fn apply_filter(&mut self) {
+        self.matches = (0..self.splits[self.split].documents.len())
+            .filter(|i| self.matches(&self.splits[self.split].documents[*i]))
+            .collect();
+        self.position = 0;
+        self.scroll = 0;
+    }
+
+    fn document(&self) -> Option<&Document> {
+        let index = *self.matches.get(self.position)?;
+        Some(&self.splits[self.split].documents[index])
+    }
+
+    fn move_by(&mut self, offset: isize) {
+        let last = self.matches.len().saturating_sub(1) as isize;
+        self.position = (self.position as isize + offset).clamp(0, last) as usize;
+        self.scroll = 0;
+    }
+
+    fn switch_split(&mut self) {
+        self.split = (self.split + 1) % self.splits.len();
+        self.apply_filter();
+    }
+
+    /// Handles a key press, and returns whether to quit.
+    fn handle(&mut self, key: KeyCode) -> bool {
+        match (self.mode, key) {
+            (Mode::Browse, KeyCode::Char('q') | KeyCode::Esc) => return true,
+            (Mode::Browse, KeyCode::Right | KeyCode::Char('n')) => self.move_by(1),
+            (Mode::Browse, KeyCode::Left | KeyCode::Char('p')) => self.move_by(-1),
+            (Mode::Browse, KeyCode::PageDown) => self.move_by(10),
+            (Mode::Browse, KeyCode::PageUp) => self.move_by(-10),
+            (Mode::Browse, KeyCode::Home) => self.move_by(isize::MIN / 2),
+            (Mode::Browse, KeyCode::End) => self.move_by(isize::MAX / 2),
+            (Mode::Browse, KeyCode::Down | KeyCode::Char('j')) => {
+                self.scroll = self.scroll.saturating_add(1)
+            }
+            (Mode::Browse, KeyCode::Up | KeyCode::Char('k')) => {
+                self.scroll = self.scroll.saturating_sub(1)
+            }
+            (Mode::Browse, KeyCode::Tab) => self.switch_split(),
+            (Mode::Browse, KeyCode::Char('/')) => {
+                self.mode = Mode::Filter;
+                self.filter.clear();
+            }
+            (Mode::Filter, KeyCode::Enter) => {
+                self.mode = Mode::Browse;
+                self.apply_filter();
+            }
+            (Mode::Filter, KeyCode::Esc) => {
+                self.mode = Mode::Browse;
+                self.filter.clear();
+                self.apply_filter();
+            }
+            (Mode::Filter, KeyCode::Backspace) => {
+                self.filter.pop();
+            }
+            (Mode::Filter, KeyCode::Char(c)) => self.filter.push(c),
+            _ => {}
+        }
+
+        false
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [header, body, mentions, footer] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(3),
+            Constraint::Percentage(40),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let split = &self.splits[self.split];
+        let title = match self.document() {
+            Some(document) => format!(
+                "{} | document {} | {}/{}",
+                split.name,
+                document.document_id,
+                self.position + 1,
+                self.matches.len()
+            ),
+            None => format!("{} | no matching documents", split.name),
+        };
+        frame.render_widget(
+            Paragraph::new(title).style(Style::new().add_modifier(Modifier::BOLD)),
+            header,
+        );
+
+        let footer_text = match self.mode {
+            Mode::Filter => format!("filter (QID or surface form): {}_", self.filter),
+            Mode::Browse if self.filter.is_empty() => {
+                "←/→ documents  ↑/↓ scroll  tab split  / filter  q quit".to_owned()
+            }
+            Mode::Browse => format!(
+                "filter: {}  ←/→ documents  ↑/↓ scroll  tab split  / filter  q quit",
+                self.filter
+            ),
+        };
+        frame.render_widget(
+            Paragraph::new(footer_text).style(Style::new().fg(Color::DarkGray)),
+            footer,
+        );
+
+        let Some(document) = self.document() else {
+            return;
+        };
+
+        frame.render_widget(
+            Paragraph::new(highlight(document))
+                .block(Block::bordered().title("text"))
+                .wrap(Wrap { trim: false })
+                .scroll((self.scroll, 0)),
+            body,
+        );
+
+        let lines = sorted_mentions(document)
+            .into_iter()
+            .map(|i| {
+                let mention = &document.entities[i];
+                let target = match (&mention.title, mention.qid) {
+                    (Some(title), Some(qid)) => format!("{title} (Q{qid})"),
+                    (Some(title), None) => format!("{title} (no QID)"),
+                    (None, _) => "NIL".to_owned(),
+                };
+                Line::from(vec![
+                    Span::styled(format!("{i:>3}  "), mention_style(document, i)),
+                    Span::raw(format!("{}  ", document.surface(mention))),
+                    Span::raw(target),
+                ])
+            })
+            .collect::<Vec<_>>();
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title("mentions")),
+            mentions,
+        );
+    }
+}
+
+fn sorted_mentions(document: &Document) -> Vec<usize> {
+    let mut mentions = (0..document.entities.len()).collect::<Vec<_>>();
+    mentions.sort_by_key(|i| document.entities[*i].start);
+    mentions
+}
+
+/// Colors the linked mentions green and the NIL ones yellow, like `inspect` does.
+fn mention_style(document: &Document, index: usize) -> Style {
+    let color = match document.entities[index].title {
+        Some(_) => Color::Green,
+        None => Color::Yellow,
+    };
+    Style::new().fg(color).add_modifier(Modifier::BOLD)
+}
+
+fn highlight(document: &Document) -> Text<'static> {
+    let chars = document.text.chars().collect::<Vec<_>>();
+    let mut spans = vec![];
+    let mut position = 0;
+
+    for i in sorted_mentions(document) {
+        let mention = &document.entities[i];
+        let (start, end) = (mention.start as usize, mention.end as usize);
+        if start < position || end > chars.len() {
+            continue;
+        }
+
+        spans.push(Span::raw(chars[position..start].iter().collect::<String>()));
+        spans.push(Span::styled(
+            format!("[{}]{i}", chars[start..end].iter().collect::<String>()),
+            mention_style(document, i),
+        ));
+        position = end;
+    }
+    spans.push(Span::raw(chars[position..].iter().collect::<String>()));
+
+    Text::from(Line::from(spans))
+}
+
+fn run(terminal: &mut DefaultTerminal, mut browser: Browser) -> std::io::Result<()> {
+    loop {
+        terminal.draw(|frame| browser.draw(frame))?;
+
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press && browser.handle(key.code) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Opens a terminal browser over the generated dataset in `input`, for paging through
+/// the documents of every split with their mentions highlighted, and filtering them by
+/// the QIDs or surface forms of their mentions.
+pub fn browse(input: &str) {
+    let splits = read_dataset(input);
+    if splits.is_empty() {
+        fail(Failure::InputNotFound, format!("No splits in {input}."));
+    }
+
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, Browser::new(splits));
+    ratatui::restore();
+
+    if let Err(error) = result {
+        fail(Failure::Other, format!("The browser failed: {error}."));
+    }
+}
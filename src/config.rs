@@ -0,0 +1,107 @@
+use crate::exit::{fail, Failure};
+use clap::Command;
+use std::ffi::OsString;
+use std::fs;
+use toml::Value;
+
+/// Returns the name of the long flag in `arg` (e.g. `input-aida` for `--input-aida=x`).
+fn flag_name(arg: &OsString) -> Option<String> {
+    let arg = arg.to_str()?.strip_prefix("--")?;
+    Some(arg.split('=').next().unwrap_or(arg).to_owned())
+}
+
+fn config_error(path: &str, message: &str) -> ! {
+    fail(
+        Failure::Usage,
+        format!("Invalid configuration {path}: {message}."),
+    )
+}
+
+/// Converts the value of an option in the configuration into the arguments on the command line.
+fn value_args(path: &str, flag: &str, value: &Value) -> Vec<OsString> {
+    let scalar = |value: &Value| match value {
+        Value::String(x) => x.clone(),
+        Value::Integer(x) => x.to_string(),
+        Value::Float(x) => x.to_string(),
+        _ => config_error(path, &format!("unsupported value of {flag}")),
+    };
+
+    match value {
+        Value::Boolean(true) => vec![format!("--{flag}").into()],
+        Value::Boolean(false) => vec![],
+        Value::Array(values) => values
+            .iter()
+            .flat_map(|x| [format!("--{flag}").into(), scalar(x).into()])
+            .collect(),
+        value => vec![format!("--{flag}").into(), scalar(value).into()],
+    }
+}
+
+/// Expands `--config <path>` of the `convert` subcommand into the options in the TOML file
+/// at `path`, so the options can be given declaratively, e.g. `input-aida = "data/aida.tsv"`
+/// or `title_normalization = ["nfc", "underscores"]`.
+///
+/// The options given on the command line or in their environment variables take precedence
+/// over the ones in the file; an option given in both is taken from there only. Since the resolvers are
+/// configured with a `resolvers` array like in `--resolver-config`, a configuration with
+/// one is also used as the resolver configuration.
+pub fn expand_config(command: &Command, args: Vec<OsString>) -> Vec<OsString> {
+    let Some(convert) = args.iter().position(|x| x == "convert") else {
+        return args;
+    };
+
+    let rest = &args[convert + 1..];
+    let path = rest
+        .iter()
+        .enumerate()
+        .find_map(|(i, arg)| match arg.to_str()? {
+            "--config" => rest.get(i + 1).cloned(),
+            x => x.strip_prefix("--config=").map(OsString::from),
+        });
+    let path = path.or_else(|| std::env::var_os("AIDA_CONFIG"));
+
+    let Some(path) = path else {
+        return args;
+    };
+    let path = path.to_string_lossy().into_owned();
+
+    let source =
+        fs::read_to_string(&path).unwrap_or_else(|error| config_error(&path, &error.to_string()));
+    let table = match source.parse::<toml::Table>() {
+        Ok(table) => table,
+        Err(error) => config_error(&path, &error.to_string()),
+    };
+
+    let mut given = rest.iter().filter_map(flag_name).collect::<Vec<_>>();
+    if let Some(convert) = command.find_subcommand("convert") {
+        given.extend(
+            convert
+                .get_arguments()
+                .filter(|x| x.get_env().is_some_and(|x| std::env::var_os(x).is_some()))
+                .filter_map(|x| x.get_long().map(|x| x.to_owned())),
+        );
+    }
+    let mut expanded = args.clone();
+
+    for (key, value) in &table {
+        let flag = match key.as_str() {
+            "resolvers" => {
+                let configured = given
+                    .iter()
+                    .chain(table.keys())
+                    .any(|x| x.replace('_', "-") == "resolver-config");
+                if !configured {
+                    expanded.extend(["--resolver-config".into(), path.clone().into()]);
+                }
+                continue;
+            }
+            key => key.replace('_', "-"),
+        };
+
+        if !given.contains(&flag) {
+            expanded.extend(value_args(&path, &flag, value));
+        }
+    }
+
+    expanded
+}
@@ -0,0 +1,80 @@
+use crate::DataPoint;
+use hashbrown::HashSet;
+
+/// Returns the problems with the offsets of `document`: an empty text, spans of entities or
+/// tokens that are empty or out of the bounds of its text, entities that don't start and end on
+/// the boundaries of its tokens, and tokens that differ from the text they span.
+fn check_document(document: &DataPoint) -> Vec<String> {
+    let text = document.text.chars().collect::<Vec<_>>();
+    let length = text.len() as u64;
+    let mut problems = vec![];
+    if length == 0 {
+        problems.push("the text is empty".to_owned());
+    }
+
+    for (i, span) in document.token_spans.iter().enumerate() {
+        if span.start >= span.end || span.end > length {
+            problems.push(format!(
+                "token {i} spans {}..{} of a text of {length} characters",
+                span.start, span.end
+            ));
+            continue;
+        }
+
+        let slice = text[span.start as usize..span.end as usize]
+            .iter()
+            .collect::<String>();
+        if let Some(token) = document.tokens.get(i) {
+            if *slice != **token {
+                problems.push(format!("token {i} is {token:?} but spans {slice:?}"));
+            }
+        }
+    }
+
+    let starts = document
+        .token_spans
+        .iter()
+        .map(|x| x.start)
+        .collect::<HashSet<_>>();
+    let ends = document
+        .token_spans
+        .iter()
+        .map(|x| x.end)
+        .collect::<HashSet<_>>();
+
+    for (i, entity) in document.entities.iter().enumerate() {
+        if entity.start >= entity.end || entity.end > length {
+            problems.push(format!(
+                "entity {i} spans {}..{} of a text of {length} characters",
+                entity.start, entity.end
+            ));
+        } else if !starts.is_empty()
+            && (!starts.contains(&entity.start) || !ends.contains(&entity.end))
+        {
+            problems.push(format!(
+                "entity {i} spans {}..{}, which aren't the boundaries of tokens",
+                entity.start, entity.end
+            ));
+        }
+    }
+
+    problems
+}
+
+/// Checks the offsets of every data point of the `splits` before they're written, and
+/// returns the problems, which mean the parsing or the alignment is broken.
+pub fn check_offsets(splits: &[(&str, &[DataPoint])]) -> Vec<String> {
+    splits
+        .iter()
+        .flat_map(|(name, split)| {
+            split.iter().flat_map(move |document| {
+                check_document(document).into_iter().map(move |problem| {
+                    format!(
+                        "{name}: document {} ({}): {problem}",
+                        document.document_id, document.uuid
+                    )
+                })
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,152 @@
+use crate::exit::{self, fail, Failure};
+use crate::mapping::{Resolution, Resolver};
+use crate::normalize::TitleNormalizer;
+use hashbrown::HashMap;
+use serde::Deserialize;
+
+/// The corrections bundled with the program.
+const DEFAULT_CORRECTIONS: &str = include_str!("../data/corrections.toml");
+
+#[derive(Debug, Deserialize)]
+struct CorrectionsFile {
+    #[serde(default)]
+    correction: Vec<Correction>,
+}
+
+/// A correction of the resolution of a title.
+#[derive(Debug, Deserialize)]
+pub struct Correction {
+    pub title: String,
+    pub pageid: u32,
+    pub qid: Option<u32>,
+    /// Why the title needs to be corrected.
+    #[serde(default)]
+    pub reason: Option<String>,
+    /// Where the pageid and QID come from, e.g. the URL of the Wikidata item.
+    #[serde(default)]
+    pub source: Option<String>,
+}
+
+/// A correction of the registry, with the file it comes from.
+#[derive(Debug)]
+pub struct Entry {
+    pub correction: Correction,
+    /// `bundled`, or the path of the file given with `--corrections`.
+    pub origin: String,
+}
+
+fn parse_corrections(source: &str, name: &str) -> Vec<Correction> {
+    match toml::from_str::<CorrectionsFile>(source) {
+        Ok(file) => file.correction,
+        Err(error) => fail(
+            Failure::Parse,
+            format!("Invalid corrections file {name}: {error}."),
+        ),
+    }
+}
+
+/// Loads the registry of the corrections: the bundled ones followed by the ones in `path` if
+/// given, so the later entries for a title override the earlier ones.
+pub fn load_registry(path: Option<&str>) -> Vec<Entry> {
+    let mut registry = parse_corrections(DEFAULT_CORRECTIONS, "(bundled)")
+        .into_iter()
+        .map(|correction| Entry {
+            correction,
+            origin: "bundled".to_owned(),
+        })
+        .collect::<Vec<_>>();
+
+    if let Some(path) = path {
+        let source = exit::read_to_string(path);
+        registry.extend(
+            parse_corrections(&source, path)
+                .into_iter()
+                .map(|correction| Entry {
+                    correction,
+                    origin: path.to_owned(),
+                }),
+        );
+    }
+
+    registry
+}
+
+/// Loads the bundled corrections, extended and overridden by the ones in `path` if given.
+pub fn load_corrections(
+    path: Option<&str>,
+    normalizer: &TitleNormalizer,
+) -> HashMap<String, Resolution> {
+    load_registry(path)
+        .into_iter()
+        .map(|entry| {
+            let correction = entry.correction;
+            (
+                normalizer.normalize(&correction.title),
+                Resolution {
+                    pageid: correction.pageid,
+                    qid: correction.qid,
+                    resolver: Resolver::Correction,
+                },
+            )
+        })
+        .collect()
+}
+
+/// Prints the registry of the corrections as TSV, with the title, the pageid, the QID, the
+/// file every correction comes from, its reason and source, and whether it's overridden by a
+/// later one for the same title.
+pub fn list_corrections(path: Option<&str>, normalizer: &TitleNormalizer) {
+    let registry = load_registry(path);
+    let mut last = HashMap::new();
+    for (i, entry) in registry.iter().enumerate() {
+        last.insert(normalizer.normalize(&entry.correction.title), i);
+    }
+
+    println!("title\tpageid\tqid\torigin\treason\tsource\tstatus");
+    for (i, entry) in registry.iter().enumerate() {
+        let correction = &entry.correction;
+        let status = match last[&normalizer.normalize(&correction.title)] {
+            j if j == i => "applied".to_owned(),
+            j => format!("overridden by {}", registry[j].origin),
+        };
+        println!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{status}",
+            correction.title,
+            correction.pageid,
+            correction.qid.map_or_else(String::new, |x| format!("Q{x}")),
+            entry.origin,
+            correction.reason.as_deref().unwrap_or_default(),
+            correction.source.as_deref().unwrap_or_default(),
+        );
+    }
+}
+
+/// A correction proposed for a title that resolved to a page without a QID.
+#[derive(Debug)]
+pub struct Suggestion {
+    pub title: String,
+    pub pageid: u32,
+    pub qid: u32,
+    /// Where the QID was found, written as the source of the correction for the reviewer.
+    pub source: String,
+}
+
+/// Writes the suggestions into a TOML file in the format accepted by `--corrections`.
+pub fn write_suggestions(suggestions: &[Suggestion], path: &str) {
+    let mut source = String::from(
+        "# Suggested corrections for titles that resolved to a page without a QID.\n\
+         # Review them and pass this file back with `--corrections`.\n",
+    );
+
+    for suggestion in suggestions {
+        source.push_str(&format!(
+            "\n[[correction]]\ntitle = {}\npageid = {}\nqid = {}\nreason = \"The title resolves to a page without a QID.\"\nsource = {}\n",
+            toml::Value::String(suggestion.title.clone()),
+            suggestion.pageid,
+            suggestion.qid,
+            toml::Value::String(suggestion.source.clone())
+        ));
+    }
+
+    exit::write(path, source);
+}
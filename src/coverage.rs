@@ -0,0 +1,108 @@
+use crate::exit;
+use crate::mapping::Resolution;
+use crate::{EntityType, TokenRecord};
+use hashbrown::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{BufWriter, Write};
+
+#[derive(Debug, Default)]
+pub struct Unmapped {
+    pub mentions: usize,
+    pub documents: BTreeSet<u32>,
+}
+
+#[derive(Debug, Default)]
+pub struct Coverage {
+    /// The number of mentions annotated with a title.
+    pub mentions: usize,
+    /// The titles that couldn't be resolved, together with their mentions.
+    pub unmapped: BTreeMap<String, Unmapped>,
+}
+
+impl Coverage {
+    pub fn unmapped_mentions(&self) -> usize {
+        self.unmapped.values().map(|x| x.mentions).sum()
+    }
+
+    pub fn unmapped_percentage(&self) -> f64 {
+        if self.mentions == 0 {
+            0.0
+        } else {
+            100.0 * self.unmapped_mentions() as f64 / self.mentions as f64
+        }
+    }
+}
+
+/// The mentions annotated with every title, counted split by split, so the splits don't have to be
+/// in memory together.
+#[derive(Debug, Default)]
+pub struct Mentions(HashMap<String, Unmapped>);
+
+impl Mentions {
+    /// Counts the mentions of `split`.
+    pub fn count(&mut self, split: &[TokenRecord]) {
+        let mut previous: Option<&TokenRecord> = None;
+
+        for record in split {
+            let continues = !record.begins
+                && previous.is_some_and(|x| {
+                    x.document_id == record.document_id && x.entity == record.entity
+                });
+            previous = Some(record);
+
+            let EntityType::InDistribution(title, _) = &record.entity else {
+                continue;
+            };
+            if continues {
+                continue;
+            }
+
+            let mentions = self.0.entry_ref(&**title).or_default();
+            mentions.mentions += 1;
+            mentions.documents.insert(record.document_id);
+        }
+    }
+}
+
+/// Counts the mentions annotated with a title and collects the ones whose title isn't in `mapping`.
+/// The mentions of the titles in `dropped` are left out altogether.
+pub fn compute_coverage(
+    mentions: &Mentions,
+    mapping: &HashMap<String, Resolution>,
+    dropped: &HashSet<String>,
+) -> Coverage {
+    let mut coverage = Coverage::default();
+
+    for (title, mentions) in &mentions.0 {
+        if dropped.contains(title) {
+            continue;
+        }
+
+        coverage.mentions += mentions.mentions;
+        if !mapping.contains_key(title) {
+            let unmapped = Unmapped {
+                mentions: mentions.mentions,
+                documents: mentions.documents.clone(),
+            };
+            coverage.unmapped.insert(title.clone(), unmapped);
+        }
+    }
+
+    coverage
+}
+
+/// Writes a TSV report with every unmapped title, the number of its mentions, and the documents they're in.
+pub fn write_coverage_report(coverage: &Coverage, path: &str) {
+    let mut writer = BufWriter::new(exit::create(path));
+    writeln!(writer, "title\tmentions\tdocuments").unwrap();
+
+    for (title, unmapped) in &coverage.unmapped {
+        let documents = unmapped
+            .documents
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        writeln!(writer, "{title}\t{}\t{documents}", unmapped.mentions).unwrap();
+    }
+}
@@ -0,0 +1,279 @@
+use crate::exit::{self, fail, Failure};
+use crate::redirects::parse_qid;
+use arrow::array::{Array, AsArray, StructArray};
+use arrow::datatypes::{UInt32Type, UInt64Type};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::ProjectionMask;
+use parquet::file::metadata::{ParquetMetaData, ParquetMetaDataReader};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+/// The names of the splits, in the order they're written in.
+pub const SPLITS: [&str; 3] = ["train", "validation", "test"];
+
+/// A mention read back from a generated Parquet file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mention {
+    pub start: u64,
+    pub end: u64,
+    pub tag: String,
+    pub pageid: Option<u32>,
+    pub qid: Option<u32>,
+    pub title: Option<String>,
+    /// Whether the mention overlaps a longer one, as kept with `--overlaps nested`.
+    pub nested: bool,
+}
+
+/// A document read back from a generated Parquet file.
+#[derive(Debug, Clone)]
+pub struct Document {
+    pub document_id: u32,
+    /// The index of the sentence in its document, if the files have one per row.
+    pub sentence: Option<u32>,
+    pub uuid: Option<String>,
+    pub text: String,
+    pub entities: Vec<Mention>,
+}
+
+impl Document {
+    /// Returns the text of `mention`, whose offsets are in characters.
+    pub fn surface(&self, mention: &Mention) -> String {
+        self.text
+            .chars()
+            .skip(mention.start as usize)
+            .take(mention.end.saturating_sub(mention.start) as usize)
+            .collect()
+    }
+
+    /// Returns `mention` as a JSON object, including its surface form.
+    pub fn mention_json(&self, mention: &Mention) -> Value {
+        json!({
+            "start": mention.start,
+            "end": mention.end,
+            "surface": self.surface(mention),
+            "tag": mention.tag,
+            "pageid": mention.pageid,
+            "qid": mention.qid.map(|x| format!("Q{x}")),
+            "title": mention.title,
+        })
+    }
+
+    /// Returns the document as a JSON object, with its sentence and UUID only if the files
+    /// have them.
+    pub fn to_json(&self) -> Value {
+        let mut value = json!({
+            "document_id": self.document_id,
+            "text": self.text,
+            "entities": self
+                .entities
+                .iter()
+                .map(|x| self.mention_json(x))
+                .collect::<Vec<_>>(),
+        });
+        if let Some(sentence) = self.sentence {
+            value["sentence"] = json!(sentence);
+        }
+        if let Some(uuid) = &self.uuid {
+            value["uuid"] = json!(uuid);
+        }
+        value
+    }
+}
+
+/// A split read back from a generated Parquet file, with its key-value metadata.
+#[derive(Debug)]
+pub struct StoredSplit {
+    pub name: String,
+    pub metadata: BTreeMap<String, String>,
+    pub documents: Vec<Document>,
+}
+
+fn list_parts(array: &dyn Array) -> (Vec<usize>, &StructArray) {
+    if let Some(list) = array.as_list_opt::<i32>() {
+        let offsets = list.offsets().iter().map(|x| *x as usize).collect();
+        (offsets, list.values().as_struct())
+    } else {
+        let list = array.as_list::<i64>();
+        let offsets = list.offsets().iter().map(|x| *x as usize).collect();
+        (offsets, list.values().as_struct())
+    }
+}
+
+fn utf8_values(array: &dyn Array) -> Vec<Option<String>> {
+    if let Some(array) = array.as_string_opt::<i32>() {
+        array.iter().map(|x| x.map(|x| x.to_owned())).collect()
+    } else {
+        let array = array.as_string::<i64>();
+        array.iter().map(|x| x.map(|x| x.to_owned())).collect()
+    }
+}
+
+fn u32_values(array: &dyn Array) -> Vec<Option<u32>> {
+    match array.as_primitive_opt::<UInt32Type>() {
+        Some(array) => array.iter().collect(),
+        None => utf8_values(array)
+            .into_iter()
+            .map(|x| parse_qid(&x?))
+            .collect(),
+    }
+}
+
+/// Returns the offsets in `array`, which are 64-bit in the files converted with `--wide`.
+fn offset_values(array: &dyn Array) -> Vec<Option<u64>> {
+    match array.as_primitive_opt::<UInt64Type>() {
+        Some(array) => array.iter().collect(),
+        None => u32_values(array)
+            .into_iter()
+            .map(|x| x.map(u64::from))
+            .collect(),
+    }
+}
+
+fn struct_field<'a>(array: &'a StructArray, name: &str) -> Option<&'a dyn Array> {
+    Some(array.column_by_name(name)?.as_ref())
+}
+
+/// Opens the Parquet file at `path` for reading its batches, or exits if it isn't one.
+pub fn open_parquet(path: &Path) -> ParquetRecordBatchReaderBuilder<File> {
+    ParquetRecordBatchReaderBuilder::try_new(exit::open(path)).unwrap_or_else(|error| {
+        fail(
+            Failure::Parse,
+            format!("{} isn't a valid Parquet file: {error}.", path.display()),
+        )
+    })
+}
+
+/// Returns the key-value metadata of a Parquet file, without the Arrow schema.
+pub fn key_values(metadata: &ParquetMetaData) -> BTreeMap<String, String> {
+    metadata
+        .file_metadata()
+        .key_value_metadata()
+        .iter()
+        .copied()
+        .flatten()
+        .filter(|x| !x.key.starts_with("ARROW:"))
+        .filter_map(|x| Some((x.key.clone(), x.value.clone()?)))
+        .collect()
+}
+
+/// Reads a split of the generated dataset from a Parquet file.
+pub fn read_split(path: &Path) -> StoredSplit {
+    let builder = open_parquet(path);
+    let key_values = key_values(builder.metadata());
+
+    let columns = ["document_id", "sentence", "uuid", "text", "entities"];
+    let indices = builder
+        .schema()
+        .fields()
+        .iter()
+        .enumerate()
+        .filter(|(_, field)| columns.contains(&field.name().as_str()))
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    let projection = ProjectionMask::roots(builder.parquet_schema(), indices);
+
+    let mut documents = vec![];
+    let reader = builder.with_projection(projection).build().unwrap();
+
+    for batch in reader {
+        let batch = batch.unwrap();
+        let column = |name: &str| batch.column_by_name(name).map(|x| x.as_ref());
+
+        let document_ids = u32_values(column("document_id").unwrap());
+        let sentences = column("sentence").map(u32_values);
+        let uuids = column("uuid").map(utf8_values);
+        let texts = utf8_values(column("text").unwrap());
+
+        let (offsets, entities) = list_parts(column("entities").unwrap());
+        let field = |name: &str| struct_field(entities, name);
+        let starts = offset_values(field("start").unwrap());
+        let ends = offset_values(field("end").unwrap());
+        let tags = utf8_values(field("tag").unwrap());
+        let pageids = field("pageid").map_or_else(|| vec![None; starts.len()], u32_values);
+        let qids = field("qid").map_or_else(|| vec![None; starts.len()], u32_values);
+        let titles = field("title").map_or_else(|| vec![None; starts.len()], utf8_values);
+        let nested = field("nested").map_or_else(
+            || vec![false; starts.len()],
+            |x| x.as_boolean().values().iter().collect(),
+        );
+
+        for (row, text) in texts.into_iter().enumerate() {
+            let entities = (offsets[row]..offsets[row + 1])
+                .map(|i| Mention {
+                    start: starts[i].unwrap_or_default(),
+                    end: ends[i].unwrap_or_default(),
+                    tag: tags[i].clone().unwrap_or_default(),
+                    pageid: pageids[i],
+                    qid: qids[i],
+                    title: titles[i].clone(),
+                    nested: nested[i],
+                })
+                .collect();
+
+            documents.push(Document {
+                document_id: document_ids[row].unwrap_or_default(),
+                sentence: sentences.as_ref().and_then(|x| x[row]),
+                uuid: uuids.as_ref().and_then(|x| x[row].clone()),
+                text: text.unwrap_or_default(),
+                entities,
+            });
+        }
+    }
+
+    StoredSplit {
+        name: key_values
+            .get("split")
+            .cloned()
+            .unwrap_or_else(|| path.file_stem().unwrap().to_string_lossy().into_owned()),
+        metadata: key_values,
+        documents,
+    }
+}
+
+/// Returns the split recorded in the metadata of the Parquet file at `path`, if any.
+fn split_name(path: &Path) -> Option<String> {
+    let metadata = ParquetMetaDataReader::new()
+        .parse_and_finish(&File::open(path).ok()?)
+        .ok()?;
+    key_values(&metadata).remove("split")
+}
+
+/// Returns the path of the file of `split` in the output directory `dir` of the conversion.
+///
+/// Since the files can be renamed with `--train-name` and the like, they're found by the
+/// split in their metadata, falling back to `{split}.parquet` for datasets without one.
+pub fn split_path(dir: &Path, split: &str) -> Option<PathBuf> {
+    let mut files = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|x| Some(x.ok()?.path()))
+        .filter(|x| x.extension().is_some_and(|x| x == "parquet"))
+        .collect::<Vec<_>>();
+    files.sort_unstable();
+
+    files
+        .into_iter()
+        .find(|x| split_name(x).as_deref() == Some(split))
+        .or_else(|| {
+            let path = dir.join(format!("{split}.parquet"));
+            (path.exists() && split_name(&path).is_none()).then_some(path)
+        })
+}
+
+/// Reads the splits of the generated dataset from either the output directory of
+/// the conversion or a single Parquet file.
+pub fn read_dataset(input: &str) -> Vec<StoredSplit> {
+    let path = Path::new(input);
+
+    if path.is_dir() {
+        SPLITS
+            .into_iter()
+            .filter_map(|name| split_path(path, name))
+            .map(|file| read_split(&file))
+            .collect()
+    } else {
+        vec![read_split(path)]
+    }
+}
@@ -0,0 +1,52 @@
+/// Tokens that attach to the previous token, e.g. punctuation, clitics, and closing quotes.
+const ATTACH_LEFT: [&str; 18] = [
+    ".", ",", ";", ":", "!", "?", "%", ")", "]", "}", "''", "'s", "'S", "n't", "'re", "'ve", "'ll",
+    "'d",
+];
+/// Tokens that attach to the next token, e.g. opening brackets and quotes.
+const ATTACH_RIGHT: [&str; 5] = ["(", "[", "{", "$", "``"];
+
+/// Decides where the spaces go when joining the tokens of a document into text.
+///
+/// By default, the tokens are separated by single spaces. With `--detokenize`, there's no
+/// space before punctuation and clitics or after opening brackets, and straight double quotes
+/// alternate between opening and closing ones.
+pub struct Detokenizer {
+    detokenize: bool,
+    previous: Option<String>,
+    /// Whether a straight double quote was opened and not closed yet.
+    quoted: bool,
+}
+
+impl Detokenizer {
+    pub fn new(detokenize: bool) -> Self {
+        Self {
+            detokenize,
+            previous: None,
+            quoted: false,
+        }
+    }
+
+    /// Returns whether a space goes between the previous token and `token`.
+    pub fn space_before(&mut self, token: &str) -> bool {
+        let previous = self.previous.replace(token.to_owned());
+        let opening_quote = previous.as_deref() == Some("\"") && self.quoted;
+        if token == "\"" {
+            self.quoted = !self.quoted;
+        }
+
+        let Some(previous) = previous else {
+            return false;
+        };
+        if !self.detokenize {
+            return true;
+        }
+
+        let attaches_left = if token == "\"" {
+            !self.quoted
+        } else {
+            ATTACH_LEFT.contains(&token)
+        };
+        !(attaches_left || opening_quote || ATTACH_RIGHT.contains(&previous.as_str()))
+    }
+}
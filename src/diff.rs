@@ -0,0 +1,144 @@
+use crate::dataset::{read_split, split_path, Document, Mention, SPLITS};
+use hashbrown::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Default)]
+struct SplitDiff {
+    added: usize,
+    removed: usize,
+    text: usize,
+    spans: usize,
+    qids: usize,
+}
+
+impl SplitDiff {
+    fn total(&self) -> usize {
+        self.added + self.removed + self.text + self.spans + self.qids
+    }
+}
+
+fn format_qid(qid: Option<u32>) -> String {
+    qid.map_or_else(|| "NIL".to_owned(), |x| format!("Q{x}"))
+}
+
+/// Returns the entities of `document` sorted by their spans.
+fn sorted_spans(document: &Document) -> Vec<((u64, u64), &Mention)> {
+    let mut spans = document
+        .entities
+        .iter()
+        .map(|x| ((x.start, x.end), x))
+        .collect::<Vec<_>>();
+    spans.sort_by_key(|(span, _)| *span);
+    spans
+}
+
+/// Compares the same document in both datasets and prints the differences, counting
+/// the document towards the kind of the most fundamental difference.
+fn diff_document(split: &str, a: &Document, b: &Document, diff: &mut SplitDiff) {
+    let id = a.document_id;
+
+    if a.text != b.text {
+        println!("{split}\t{id}\ttext differs");
+        diff.text += 1;
+        return;
+    }
+
+    let (spans_a, spans_b) = (sorted_spans(a), sorted_spans(b));
+
+    if spans_a.len() != spans_b.len() || spans_a.iter().zip(&spans_b).any(|(x, y)| x.0 != y.0) {
+        let format = |spans: &[((u64, u64), &Mention)]| {
+            spans
+                .iter()
+                .map(|((start, end), _)| format!("{start}-{end}"))
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        println!(
+            "{split}\t{id}\tspans differ\t{}\t{}",
+            format(&spans_a),
+            format(&spans_b)
+        );
+        diff.spans += 1;
+        return;
+    }
+
+    let mut changed = false;
+    for ((span, x), (_, y)) in spans_a.iter().zip(&spans_b) {
+        if x.qid != y.qid || x.title != y.title {
+            println!(
+                "{split}\t{id}\t{}-{} ({})\t{} {}\t{} {}",
+                span.0,
+                span.1,
+                a.surface(x),
+                x.title.as_deref().unwrap_or("--NME--"),
+                format_qid(x.qid),
+                y.title.as_deref().unwrap_or("--NME--"),
+                format_qid(y.qid),
+            );
+            changed = true;
+        }
+    }
+    diff.qids += changed as usize;
+}
+
+/// Prints the documents whose text, entity spans, or entities differ between the
+/// generated datasets in `dir_a` and `dir_b`, followed by a summary of the changes
+/// in every split.
+///
+/// Returns whether the datasets are the same.
+pub fn diff_datasets(dir_a: &str, dir_b: &str) -> bool {
+    let mut diffs = vec![];
+
+    for name in SPLITS {
+        let (path_a, path_b) = (
+            split_path(Path::new(dir_a), name),
+            split_path(Path::new(dir_b), name),
+        );
+        if path_a.is_none() && path_b.is_none() {
+            continue;
+        }
+
+        let documents =
+            |path: Option<PathBuf>| path.map_or_else(Vec::new, |x| read_split(&x).documents);
+        let (documents_a, documents_b) = (documents(path_a), documents(path_b));
+        let by_id = documents_b
+            .iter()
+            .map(|x| (x.document_id, x))
+            .collect::<HashMap<_, _>>();
+
+        let mut diff = SplitDiff::default();
+        for a in &documents_a {
+            match by_id.get(&a.document_id) {
+                Some(b) => diff_document(name, a, b, &mut diff),
+                None => {
+                    println!("{name}\t{}\tonly in {dir_a}", a.document_id);
+                    diff.removed += 1;
+                }
+            }
+        }
+
+        let ids_a = documents_a
+            .iter()
+            .map(|x| x.document_id)
+            .collect::<HashSet<_>>();
+        for b in documents_b
+            .iter()
+            .filter(|x| !ids_a.contains(&x.document_id))
+        {
+            println!("{name}\t{}\tonly in {dir_b}", b.document_id);
+            diff.added += 1;
+        }
+
+        diffs.push((name, diff));
+    }
+
+    println!();
+    for (name, diff) in &diffs {
+        println!(
+            "{name}: {} only in {dir_a}, {} only in {dir_b}, {} with different text, {} with different spans, {} with different entities",
+            diff.removed, diff.added, diff.text, diff.spans, diff.qids
+        );
+    }
+
+    diffs.iter().all(|(_, diff)| diff.total() == 0)
+}
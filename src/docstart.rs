@@ -0,0 +1,77 @@
+/// The line that starts a document in the AIDA CoNLL-YAGO dataset, e.g. `-DOCSTART- (946testa CRICKET)`.
+#[derive(Debug, Clone, Copy)]
+pub struct Docstart {
+    pub document_id: u32,
+    /// The index of the split in [`crate::dataset::SPLITS`], given by the suffix of the ID:
+    /// none for the training split, `testa` for the validation split, and `testb` for the test split.
+    pub split: usize,
+}
+
+/// Parses `line` if it starts a document, or returns `None` if it doesn't.
+///
+/// The name after the ID is free-form, so parentheses or backslashes in it don't matter,
+/// and it can be missing too, like in the files written by `export`. Only the ID in front of
+/// it has to be valid, as a document whose ID can't be read would be merged into the previous one.
+pub fn parse_docstart(line: &str) -> Option<Result<Docstart, String>> {
+    let rest = line.strip_prefix("-DOCSTART-")?.trim();
+    Some(parse_rest(rest))
+}
+
+fn parse_rest(rest: &str) -> Result<Docstart, String> {
+    let inner = rest
+        .strip_prefix('(')
+        .and_then(|x| x.strip_suffix(')'))
+        .ok_or("expected the document ID and name in parentheses")?;
+    let id = inner.split_whitespace().next().unwrap_or_default();
+
+    let digits = id.len() - id.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let (number, suffix) = id.split_at(digits);
+    if number.is_empty() {
+        return Err(format!("expected a numeric document ID, found {id:?}"));
+    }
+
+    let split = match suffix {
+        "" => 0,
+        "testa" => 1,
+        "testb" => 2,
+        _ => return Err(format!("unknown split {suffix:?} after the document ID")),
+    };
+    let document_id = number
+        .parse::<u32>()
+        .map_err(|_| format!("document ID {number} is out of range"))?;
+
+    Ok(Docstart { document_id, split })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Result<(u32, usize), String> {
+        parse_docstart(line)
+            .unwrap()
+            .map(|x| (x.document_id, x.split))
+    }
+
+    #[test]
+    fn ids_are_parsed_with_their_split() {
+        assert_eq!(parse("-DOCSTART- (1 EU)"), Ok((1, 0)));
+        assert_eq!(parse("-DOCSTART- (947testa CRICKET)"), Ok((947, 1)));
+        assert_eq!(parse("-DOCSTART- (1163testb SOCCER)"), Ok((1163, 2)));
+        assert!(parse_docstart("EU\tB\tEU").is_none());
+    }
+
+    #[test]
+    fn names_are_free_form() {
+        assert_eq!(parse("-DOCSTART- (12 (\\ODD) NAME)"), Ok((12, 0)));
+        assert_eq!(parse("-DOCSTART- (12)"), Ok((12, 0)));
+    }
+
+    #[test]
+    fn invalid_ids_are_reported() {
+        assert!(parse("-DOCSTART- 12 EU").is_err());
+        assert!(parse("-DOCSTART- (testa EU)").is_err());
+        assert!(parse("-DOCSTART- (12testc EU)").is_err());
+        assert!(parse("-DOCSTART- (99999999999 EU)").is_err());
+    }
+}
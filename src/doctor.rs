@@ -0,0 +1,180 @@
+use crate::layout::{Fields, TsvLayout};
+use crate::mapping::check_schema;
+use apache_avro::Reader;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// The number of documents in the full AIDA CoNLL-YAGO dataset.
+const AIDA_DOCUMENTS: usize = 1393;
+
+/// The checks run by `doctor`, which print a fix for every failed check.
+struct Doctor {
+    problems: usize,
+}
+
+impl Doctor {
+    fn ok(&self, message: String) {
+        println!("ok\t{message}");
+    }
+
+    fn warn(&self, message: String, fix: &str) {
+        println!("warn\t{message}\n\t-> {fix}");
+    }
+
+    fn fail(&mut self, message: String, fix: &str) {
+        println!("FAIL\t{message}\n\t-> {fix}");
+        self.problems += 1;
+    }
+
+    /// Opens an input, failing the check if it doesn't exist or isn't readable.
+    fn open(&mut self, name: &str, path: &str) -> Option<File> {
+        match File::open(path) {
+            Ok(file) => Some(file),
+            Err(error) => {
+                self.fail(
+                    format!("{name} {path} can't be read: {error}"),
+                    &format!(
+                        "check the path given with --{name}, or run `fetch` to download the inputs"
+                    ),
+                );
+                None
+            }
+        }
+    }
+
+    /// Checks that the dataset looks like the AIDA CoNLL-YAGO TSV file, and returns its number of lines.
+    fn check_aida(&mut self, path: &str) -> Option<usize> {
+        let file = self.open("input-aida", path)?;
+
+        let mut lines = 0;
+        let mut documents = 0;
+        let mut annotated = 0;
+        let layout = TsvLayout::default();
+        for line in BufReader::new(file).lines() {
+            let Ok(line) = line else {
+                self.fail(
+                    format!("input-aida {path} isn't valid UTF-8 at line {}", lines + 1),
+                    "pass the AIDA-YAGO2-dataset.tsv file from the AIDA CoNLL-YAGO archive",
+                );
+                return None;
+            };
+            lines += 1;
+            documents += line.starts_with("-DOCSTART-") as usize;
+            let fields = Fields::new(&line, b'\t');
+            annotated += layout.url(&fields).is_some() as usize;
+        }
+
+        if documents == 0 || annotated == 0 {
+            self.fail(
+                format!("input-aida {path} has {documents} -DOCSTART- lines and {annotated} annotated tokens"),
+                "pass the AIDA-YAGO2-dataset.tsv file from the AIDA CoNLL-YAGO archive, not the CoNLL 2003 one",
+            );
+        } else if documents != AIDA_DOCUMENTS {
+            self.warn(
+                format!("input-aida {path} has {documents} documents, but the full dataset has {AIDA_DOCUMENTS}"),
+                "ignore this if the dataset is intentionally a subset, otherwise download it again",
+            );
+        } else {
+            self.ok(format!("input-aida {path} has {documents} documents"));
+        }
+
+        Some(lines)
+    }
+
+    fn check_conll(&mut self, path: &str, aida_lines: Option<usize>) {
+        let Some(file) = self.open("input-conll", path) else {
+            return;
+        };
+
+        let lines = BufReader::new(file).lines().count();
+        match aida_lines {
+            Some(aida_lines) if aida_lines != lines => self.fail(
+                format!("input-conll {path} has {lines} lines, but input-aida has {aida_lines}"),
+                "pass the CoNLL 2003 files the AIDA CoNLL-YAGO dataset was built from, concatenated in the same order",
+            ),
+            _ => self.ok(format!("input-conll {path} has {lines} lines")),
+        }
+    }
+
+    fn check_mapping(&mut self, path: &str) {
+        let Some(file) = self.open("input-wiki2qid", path) else {
+            return;
+        };
+
+        let reader = match Reader::new(file) {
+            Ok(reader) => reader,
+            Err(error) => {
+                self.fail(
+                    format!("input-wiki2qid {path} isn't an Apache Avro file: {error}"),
+                    "generate the mappings with wiki2qid, or build them with `build-mapping --from-api`",
+                );
+                return;
+            }
+        };
+
+        let problems = check_schema(reader.writer_schema());
+        if problems.is_empty() {
+            self.ok(format!("input-wiki2qid {path} has the expected schema"));
+        } else {
+            self.fail(
+                format!(
+                    "input-wiki2qid {path} has an unexpected schema: {}",
+                    problems.join("; ")
+                ),
+                "regenerate the mappings with a current version of wiki2qid",
+            );
+        }
+    }
+
+    fn check_output_dir(&mut self, path: &str) {
+        let dir = Path::new(path);
+        if !dir.is_dir() {
+            self.fail(
+                format!("output-dir {path} doesn't exist"),
+                &format!("create it with `mkdir -p {path}`"),
+            );
+            return;
+        }
+
+        let probe = dir.join(format!(".doctor-{}", std::process::id()));
+        match File::create(&probe) {
+            Ok(_) => {
+                fs::remove_file(&probe).ok();
+                self.ok(format!("output-dir {path} is writable"));
+            }
+            Err(error) => self.fail(
+                format!("output-dir {path} isn't writable: {error}"),
+                "check the permissions of the directory, or pass another one with --output-dir",
+            ),
+        }
+    }
+}
+
+/// Checks that the inputs exist and look like what the conversion expects, and that the
+/// output directory is writable, printing a fix for every problem.
+///
+/// Returns whether every check passed.
+pub fn doctor(
+    input_aida: &str,
+    input_conll: &str,
+    input_wiki2qid: &[String],
+    output_dir: &str,
+) -> bool {
+    let mut doctor = Doctor { problems: 0 };
+
+    let aida_lines = doctor.check_aida(input_aida);
+    doctor.check_conll(input_conll, aida_lines);
+    for path in input_wiki2qid {
+        doctor.check_mapping(path);
+    }
+    doctor.check_output_dir(output_dir);
+
+    if doctor.problems == 0 {
+        println!("Everything looks fine.");
+    } else {
+        println!("Found {} problems.", doctor.problems);
+    }
+
+    doctor.problems == 0
+}
@@ -0,0 +1,65 @@
+use crate::TokenRecord;
+use hashbrown::HashMap;
+use itertools::Itertools;
+use sha2::{Digest, Sha256};
+
+/// The documents that are duplicated within or between the splits.
+#[derive(Debug, Default)]
+pub struct Duplicates {
+    /// The IDs of the documents that appear more than once within a split, with their split.
+    pub ids: Vec<(u32, String)>,
+    /// The IDs of the documents that appear in more than one split, with the splits.
+    pub shared: Vec<(u32, Vec<String>)>,
+    /// The IDs of the pairs of (distinct) documents with identical texts.
+    pub texts: Vec<(u32, u32)>,
+}
+
+/// Finds the documents with the same ID, or with the same text, as another one, in the splits
+/// given one at a time.
+///
+/// The texts are only kept as their hashes, so the splits don't have to be in memory together.
+#[derive(Debug, Default)]
+pub struct DuplicateFinder {
+    duplicates: Duplicates,
+    splits_by_id: HashMap<u32, Vec<String>>,
+    ids_by_text: HashMap<[u8; 32], u32>,
+}
+
+impl DuplicateFinder {
+    pub fn add(&mut self, name: &str, split: &[TokenRecord]) {
+        for ((document_id, _), group) in &split.iter().group_by(|x| (x.document_id, x.document)) {
+            let names = self.splits_by_id.entry(document_id).or_default();
+            if names.iter().any(|x| x == name) {
+                self.duplicates.ids.push((document_id, name.to_owned()));
+            } else {
+                names.push(name.to_owned());
+            }
+
+            let text = group.map(|x| &*x.token).join(" ");
+            let hash = Sha256::digest(text).into();
+            match self.ids_by_text.get(&hash) {
+                Some(&other) if other != document_id => {
+                    self.duplicates.texts.push((other, document_id))
+                }
+                Some(_) => {}
+                None => {
+                    self.ids_by_text.insert(hash, document_id);
+                }
+            }
+        }
+    }
+
+    pub fn finish(self) -> Duplicates {
+        let mut duplicates = self.duplicates;
+        duplicates.ids.sort_unstable();
+        duplicates.ids.dedup();
+        duplicates.shared = self
+            .splits_by_id
+            .into_iter()
+            .filter(|(_, names)| names.len() > 1)
+            .sorted()
+            .collect();
+
+        duplicates
+    }
+}
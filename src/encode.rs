@@ -0,0 +1,57 @@
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::arrow_writer::{
+    compute_leaves, ArrowColumnChunk, ArrowColumnWriter, ArrowRowGroupWriterFactory,
+};
+use parquet::errors::ParquetError;
+use parquet::schema::types::SchemaDescriptor;
+use std::thread;
+
+/// Encodes and compresses the columns of `batch`, whose leaves are described by
+/// `parquet_schema`, into the column chunks of the row group `index`.
+///
+/// Every column is encoded and compressed on its own thread, since compressing them one after
+/// another on a single core dominates the time spent writing.
+pub fn encode_row_group(
+    batch: &RecordBatch,
+    parquet_schema: &SchemaDescriptor,
+    factory: &ArrowRowGroupWriterFactory,
+    index: usize,
+) -> Result<Vec<ArrowColumnChunk>, ParquetError> {
+    // The writers are of the leaves, so they're grouped by the columns they belong to.
+    let mut writers = (0..batch.num_columns())
+        .map(|_| vec![])
+        .collect::<Vec<Vec<ArrowColumnWriter>>>();
+    for (leaf, writer) in factory
+        .create_column_writers(index)?
+        .into_iter()
+        .enumerate()
+    {
+        writers[parquet_schema.get_column_root_idx(leaf)].push(writer);
+    }
+
+    let columns = thread::scope(|scope| {
+        batch
+            .columns()
+            .iter()
+            .zip(batch.schema_ref().fields())
+            .zip(writers)
+            .map(|((array, field), writers)| {
+                scope.spawn(move || {
+                    compute_leaves(field, array)?
+                        .iter()
+                        .zip(writers)
+                        .map(|(leaf, mut writer)| {
+                            writer.write(leaf)?;
+                            writer.close()
+                        })
+                        .collect::<Result<Vec<_>, ParquetError>>()
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|x| x.join().unwrap())
+            .collect::<Result<Vec<_>, ParquetError>>()
+    })?;
+
+    Ok(columns.into_iter().flatten().collect())
+}
@@ -0,0 +1,82 @@
+use std::fmt::Display;
+use std::fs::{self, File};
+use std::path::Path;
+
+/// The kinds of failures, with their exit codes, so scripts can tell them apart.
+///
+/// Panics on internal errors exit with the code 101 of the Rust runtime instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    /// A failure without a code of its own, e.g. an unreachable server.
+    /// `grep` and `diff` also exit with it when nothing matches or the datasets differ.
+    Other = 1,
+    /// Invalid arguments or configuration; also the exit code of clap.
+    Usage = 2,
+    /// An input file or directory doesn't exist or can't be opened.
+    InputNotFound = 3,
+    /// An input is malformed.
+    Parse = 4,
+    /// Too many mentions couldn't be mapped to the knowledge base.
+    Coverage = 5,
+    /// A dataset or mapping failed its validation, or a download its checksum.
+    Validation = 6,
+    /// An output couldn't be written, or would've replaced an existing one.
+    Write = 7,
+    /// Data-quality warnings were turned into errors with `-W`.
+    Warnings = 8,
+}
+
+/// Exits with the exit code of `failure`.
+pub fn exit(failure: Failure) -> ! {
+    std::process::exit(failure as i32)
+}
+
+/// Prints `message` and exits with the exit code of `failure`.
+pub fn fail(failure: Failure, message: impl Display) -> ! {
+    eprintln!("{message}");
+    exit(failure)
+}
+
+/// Opens an input file, exiting with [`Failure::InputNotFound`] if it can't be opened.
+pub fn open(path: impl AsRef<Path>) -> File {
+    let path = path.as_ref();
+    File::open(path).unwrap_or_else(|error| {
+        fail(
+            Failure::InputNotFound,
+            format!("Couldn't open {}: {error}.", path.display()),
+        )
+    })
+}
+
+/// Creates an output file, exiting with [`Failure::Write`] if it can't be created.
+pub fn create(path: impl AsRef<Path>) -> File {
+    let path = path.as_ref();
+    File::create(path).unwrap_or_else(|error| {
+        fail(
+            Failure::Write,
+            format!("Couldn't create {}: {error}.", path.display()),
+        )
+    })
+}
+
+/// Reads an input file, exiting with [`Failure::InputNotFound`] if it can't be read.
+pub fn read_to_string(path: impl AsRef<Path>) -> String {
+    let path = path.as_ref();
+    fs::read_to_string(path).unwrap_or_else(|error| {
+        fail(
+            Failure::InputNotFound,
+            format!("Couldn't read {}: {error}.", path.display()),
+        )
+    })
+}
+
+/// Writes an output file, exiting with [`Failure::Write`] if it can't be written.
+pub fn write(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) {
+    let path = path.as_ref();
+    fs::write(path, contents).unwrap_or_else(|error| {
+        fail(
+            Failure::Write,
+            format!("Couldn't write {}: {error}.", path.display()),
+        )
+    })
+}
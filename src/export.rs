@@ -0,0 +1,413 @@
+use crate::dataset::{read_dataset, Document, Mention, StoredSplit, SPLITS};
+use crate::exit::{self, fail, Failure};
+use crate::redirects::parse_qid;
+use crate::repair::fix_offsets;
+use clap::ValueEnum;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// The base of the IRIs of the documents in the NIF export.
+const NIF_BASE: &str = "https://huggingface.co/datasets/cyanic-selkie/aida-conll-yago-wikidata";
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// The Parquet files generated by the conversion.
+    Parquet,
+    /// brat standoff annotations, with a `.txt` and an `.ann` file per document in `{split}/`,
+    /// like the ones written by `--to brat` or by other tools.
+    Brat,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// One JSON object per document and line, in `{split}.jsonl`.
+    Jsonl,
+    /// One token per line in the format of the original TSV, with QIDs instead of URLs, in `{split}.tsv`.
+    Conll,
+    /// NLP Interchange Format in Turtle, linking the mentions to Wikidata's items, in `{split}.ttl`.
+    Nif,
+    /// brat standoff annotations, with a `.txt` and an `.ann` file per document in `{split}/`.
+    Brat,
+    /// The original AIDA CoNLL-YAGO TSV in `aida.tsv` and the CoNLL 2003 TSV with the tags in
+    /// `conll.tsv`, with all the splits, which can be converted again.
+    Aida,
+}
+
+/// The prefix of the Wikipedia URLs in the AIDA CoNLL-YAGO dataset.
+const WIKIPEDIA_URL: &str = "http://en.wikipedia.org/wiki/";
+
+fn format_qid(qid: Option<u32>) -> Option<String> {
+    qid.map(|x| format!("Q{x}"))
+}
+
+fn write_jsonl(split: &StoredSplit, writer: &mut dyn Write) {
+    for document in &split.documents {
+        writeln!(writer, "{}", document.to_json()).unwrap();
+    }
+}
+
+fn write_conll(split: &StoredSplit, writer: &mut dyn Write) {
+    for document in &split.documents {
+        writeln!(writer, "-DOCSTART- ({})", document.document_id).unwrap();
+
+        let mut start = 0;
+        for token in document.text.split(' ') {
+            let end = start + token.chars().count() as u64;
+            let mention = document
+                .entities
+                .iter()
+                .find(|x| x.start <= start && end <= x.end);
+
+            match mention {
+                Some(mention) => {
+                    let prefix = if mention.start == start { "B" } else { "I" };
+                    let title = mention.title.as_deref().unwrap_or("--NME--");
+                    let qid = format_qid(mention.qid).unwrap_or_default();
+                    writeln!(
+                        writer,
+                        "{token}\t{prefix}\t{}\t{title}\t{qid}",
+                        document.surface(mention)
+                    )
+                    .unwrap();
+                }
+                None => writeln!(writer, "{token}").unwrap(),
+            }
+
+            start = end + 1;
+        }
+    }
+}
+
+/// Returns the suffix of the document IDs of `split` in the AIDA CoNLL-YAGO dataset.
+fn split_suffix(split: &str) -> &'static str {
+    match split {
+        "validation" => "testa",
+        "test" => "testb",
+        _ => "",
+    }
+}
+
+fn write_aida(splits: &[StoredSplit], aida: &mut dyn Write, conll: &mut dyn Write) {
+    for split in splits {
+        for document in &split.documents {
+            let suffix = split_suffix(&split.name);
+            writeln!(aida, "-DOCSTART- ({}{suffix})", document.document_id).unwrap();
+            writeln!(conll, "-DOCSTART- -X- -X- O").unwrap();
+
+            let mut start = 0;
+            for token in document.text.split(' ') {
+                let end = start + token.chars().count() as u64;
+                let mention = document
+                    .entities
+                    .iter()
+                    .find(|x| x.start <= start && end <= x.end);
+
+                match mention {
+                    Some(mention) => {
+                        let prefix = if mention.start == start { "B" } else { "I" };
+                        let surface = document.surface(mention);
+                        match &mention.title {
+                            Some(title) => {
+                                let pageid =
+                                    mention.pageid.map(|x| format!("\t{x}")).unwrap_or_default();
+                                writeln!(
+                                    aida,
+                                    "{token}\t{prefix}\t{surface}\t{title}\t{WIKIPEDIA_URL}{title}{pageid}"
+                                )
+                                .unwrap();
+                            }
+                            None => {
+                                writeln!(aida, "{token}\t{prefix}\t{surface}\t--NME--").unwrap()
+                            }
+                        }
+
+                        let tag = if mention.tag.is_empty() {
+                            "O".to_owned()
+                        } else {
+                            format!("{prefix}-{}", mention.tag)
+                        };
+                        writeln!(conll, "{token} -X- -X- {tag}").unwrap();
+                    }
+                    None => {
+                        writeln!(aida, "{token}").unwrap();
+                        writeln!(conll, "{token} -X- -X- O").unwrap();
+                    }
+                }
+
+                start = end + 1;
+            }
+        }
+    }
+}
+
+fn escape_turtle(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+fn write_nif(split: &StoredSplit, writer: &mut dyn Write) {
+    writeln!(
+        writer,
+        "@prefix nif: <http://persistence.uni-leipzig.org/nlp2rdf/ontologies/nif-core#> ."
+    )
+    .unwrap();
+    writeln!(
+        writer,
+        "@prefix itsrdf: <http://www.w3.org/2005/11/its/rdf#> ."
+    )
+    .unwrap();
+    writeln!(writer, "@prefix xsd: <http://www.w3.org/2001/XMLSchema#> .").unwrap();
+    writeln!(writer, "@prefix wd: <http://www.wikidata.org/entity/> .").unwrap();
+
+    for document in &split.documents {
+        let base = format!("{NIF_BASE}/{}/{}", split.name, document.document_id);
+        let length = document.text.chars().count();
+        let context = format!("<{base}#char=0,{length}>");
+
+        writeln!(writer).unwrap();
+        writeln!(
+            writer,
+            "{context} a nif:String, nif:Context, nif:RFC5147String ;\n    nif:isString \"{}\" ;\n    nif:beginIndex \"0\"^^xsd:nonNegativeInteger ;\n    nif:endIndex \"{length}\"^^xsd:nonNegativeInteger .",
+            escape_turtle(&document.text)
+        )
+        .unwrap();
+
+        for mention in &document.entities {
+            write!(
+                writer,
+                "<{base}#char={},{}> a nif:String, nif:Phrase, nif:RFC5147String ;\n    nif:referenceContext {context} ;\n    nif:anchorOf \"{}\" ;\n    nif:beginIndex \"{}\"^^xsd:nonNegativeInteger ;\n    nif:endIndex \"{}\"^^xsd:nonNegativeInteger",
+                mention.start,
+                mention.end,
+                escape_turtle(&document.surface(mention)),
+                mention.start,
+                mention.end,
+            )
+            .unwrap();
+            if let Some(qid) = mention.qid {
+                write!(writer, " ;\n    itsrdf:taIdentRef wd:Q{qid}").unwrap();
+            }
+            writeln!(writer, " .").unwrap();
+        }
+    }
+}
+
+fn write_brat_document(document: &Document, dir: &Path) {
+    let name = document.document_id.to_string();
+    exit::write(dir.join(format!("{name}.txt")), &document.text);
+
+    let mut writer = BufWriter::new(exit::create(dir.join(format!("{name}.ann"))));
+    let mut references = 0;
+    for (i, mention) in document.entities.iter().enumerate() {
+        let surface = document.surface(mention);
+        let tag = if mention.tag.is_empty() {
+            "Entity"
+        } else {
+            &mention.tag
+        };
+        writeln!(
+            writer,
+            "T{}\t{tag} {} {}\t{surface}",
+            i + 1,
+            mention.start,
+            mention.end
+        )
+        .unwrap();
+
+        if let Some(qid) = mention.qid {
+            references += 1;
+            writeln!(
+                writer,
+                "N{references}\tReference T{} Wikidata:Q{qid}\t{}",
+                i + 1,
+                mention.title.as_deref().unwrap_or(&surface)
+            )
+            .unwrap();
+        }
+    }
+}
+
+/// Parses the brat annotations `source` of the document with the text `text`, keeping the
+/// text-bound annotations as mentions and the normalizations to Wikidata as their QIDs.
+fn parse_brat_annotations(source: &str, text: &str, path: &Path) -> Vec<Mention> {
+    let mut mentions = vec![];
+    let mut ids = vec![];
+
+    for (i, line) in source.lines().enumerate() {
+        let fields = line.split('\t').collect::<Vec<_>>();
+        let invalid = |problem: &str| -> ! {
+            fail(
+                Failure::Parse,
+                format!("{}:{}: {problem}: {line:?}.", path.display(), i + 1),
+            )
+        };
+
+        match fields[0].chars().next() {
+            Some('T') => {
+                let Some(annotation) = fields.get(1) else {
+                    invalid("expected the type and span of the text-bound annotation");
+                };
+                let mut parts = annotation.splitn(2, ' ');
+                let tag = parts.next().unwrap_or_default();
+                // A discontinuous span, `start end;start end`, is taken as a whole.
+                let offsets = parts
+                    .next()
+                    .unwrap_or_default()
+                    .split([' ', ';'])
+                    .map(|x| x.parse::<u64>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .unwrap_or_default();
+                let (Some(&start), Some(&end)) = (offsets.first(), offsets.last()) else {
+                    invalid("expected the character offsets of the text-bound annotation");
+                };
+                let length = text.chars().count() as u64;
+
+                ids.push(fields[0]);
+                mentions.push(Mention {
+                    start: start.min(length),
+                    end: end.min(length),
+                    tag: if tag == "Entity" { "" } else { tag }.to_owned(),
+                    pageid: None,
+                    qid: None,
+                    title: None,
+                    nested: false,
+                });
+            }
+            Some('N') => {
+                let reference = fields.get(1).unwrap_or(&"").split(' ').collect::<Vec<_>>();
+                let [_, target, identifier] = reference[..] else {
+                    invalid("expected the target and identifier of the normalization");
+                };
+                let Some(qid) = identifier.strip_prefix("Wikidata:").and_then(parse_qid) else {
+                    continue;
+                };
+                let Some(mention) = ids.iter().position(|x| *x == target) else {
+                    invalid("the normalization refers to an unknown annotation");
+                };
+                mentions[mention].qid = Some(qid);
+                mentions[mention].title = fields.get(2).map(|x| x.to_string());
+            }
+            // The other annotations (relations, events, attributes, and notes) aren't kept.
+            _ => {}
+        }
+    }
+
+    mentions
+}
+
+/// Reads the splits of the brat annotations in the `{split}/` directories of `input`, with
+/// the documents named by their IDs.
+fn read_brat(input: &str) -> Vec<StoredSplit> {
+    let mut splits = vec![];
+
+    for name in SPLITS {
+        let dir = Path::new(input).join(name);
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut paths = entries
+            .filter_map(|x| Some(x.ok()?.path()))
+            .filter(|x| x.extension().is_some_and(|x| x == "txt"))
+            .collect::<Vec<_>>();
+        paths.sort_unstable();
+
+        let mut documents = vec![];
+        for (i, path) in paths.iter().enumerate() {
+            let text = fs::read_to_string(path).unwrap_or_else(|error| {
+                fail(
+                    Failure::Parse,
+                    format!("Can't read {}: {error}.", path.display()),
+                )
+            });
+            let annotations = path.with_extension("ann");
+            let entities = match fs::read_to_string(&annotations) {
+                Ok(source) => parse_brat_annotations(&source, &text, &annotations),
+                Err(_) => vec![],
+            };
+
+            documents.push(Document {
+                document_id: path
+                    .file_stem()
+                    .and_then(|x| x.to_str()?.parse().ok())
+                    .unwrap_or(i as u32),
+                sentence: None,
+                uuid: None,
+                text,
+                entities,
+            });
+        }
+        documents.sort_by_key(|x| x.document_id);
+
+        splits.push(StoredSplit {
+            name: name.to_owned(),
+            metadata: Default::default(),
+            documents,
+        });
+    }
+
+    if splits.is_empty() {
+        fail(
+            Failure::InputNotFound,
+            format!("{input} has no train, validation, or test directory with brat annotations."),
+        );
+    }
+    splits
+}
+
+/// Converts the dataset in `input`, in the format `from`, into another format, writing one file
+/// (or directory, for brat) per split into `output_dir`, or both TSV files for `aida`.
+///
+/// With `repair`, the offsets of the entities are snapped to the boundaries of the tokens first,
+/// printing every adjustment.
+pub fn export(
+    input: &str,
+    from: ImportFormat,
+    format: ExportFormat,
+    output_dir: &str,
+    repair: bool,
+) {
+    let mut splits = match from {
+        ImportFormat::Parquet => read_dataset(input),
+        ImportFormat::Brat => read_brat(input),
+    };
+    if repair {
+        let adjustments = fix_offsets(&mut splits);
+        for adjustment in &adjustments {
+            eprintln!("{adjustment}");
+        }
+        eprintln!("Adjusted the offsets of {} entities.", adjustments.len());
+    }
+
+    fs::create_dir_all(output_dir).unwrap();
+    let output_dir = Path::new(output_dir);
+
+    if format == ExportFormat::Aida {
+        let mut aida = BufWriter::new(exit::create(output_dir.join("aida.tsv")));
+        let mut conll = BufWriter::new(exit::create(output_dir.join("conll.tsv")));
+        write_aida(&splits, &mut aida, &mut conll);
+        return;
+    }
+
+    for split in splits {
+        let (extension, write): (_, fn(&StoredSplit, &mut dyn Write)) = match format {
+            ExportFormat::Jsonl => ("jsonl", write_jsonl),
+            ExportFormat::Conll => ("tsv", write_conll),
+            ExportFormat::Nif => ("ttl", write_nif),
+            ExportFormat::Aida => unreachable!(),
+            ExportFormat::Brat => {
+                let dir = output_dir.join(&split.name);
+                fs::create_dir_all(&dir).unwrap();
+                for document in &split.documents {
+                    write_brat_document(document, &dir);
+                }
+                continue;
+            }
+        };
+
+        let path = output_dir.join(format!("{}.{extension}", split.name));
+        let mut writer = BufWriter::new(exit::create(path));
+        write(&split, &mut writer);
+    }
+}
@@ -0,0 +1,121 @@
+use crate::exit::{self, exit, fail, Failure};
+use crate::http::{download, file_checksum};
+use std::fs;
+use std::path::{Path, PathBuf};
+use zip::read::root_dir_common_filter;
+use zip::ZipArchive;
+
+/// The archive with the AIDA CoNLL-YAGO annotations published by the Max Planck Institute for Informatics.
+pub const AIDA_URL: &str =
+    "https://resources.mpi-inf.mpg.de/yago-naga/aida/download/aida-yago2-dataset.zip";
+
+/// An input to download, with the checksum it has to match.
+#[derive(Debug, Clone)]
+pub struct Download {
+    pub url: String,
+    pub sha256: Option<String>,
+}
+
+/// Downloads `input` into `dir`, unless it has already been downloaded, and verifies
+/// its checksum. Returns the path of the download.
+///
+/// Without a checksum to match, the checksum of the download is printed so it can
+/// be pinned in later runs, and the fetch fails unless `verify` is `false`. The download
+/// is kept, so the next run with the checksum doesn't download it again.
+fn fetch_one(input: &Download, dir: &Path, verify: bool) -> PathBuf {
+    let name = input
+        .url
+        .rsplit('/')
+        .next()
+        .filter(|x| !x.is_empty())
+        .unwrap_or("download");
+    let path = dir.join(name);
+
+    let checksum = if path.exists() {
+        eprintln!("{} is already downloaded.", path.display());
+        file_checksum(&path)
+    } else {
+        eprintln!("Downloading {} into {}.", input.url, path.display());
+        download(&input.url, &path)
+    };
+
+    match &input.sha256 {
+        Some(expected) if !expected.eq_ignore_ascii_case(&checksum) => {
+            fs::remove_file(&path).unwrap();
+            eprintln!(
+                "The checksum of {} is {checksum}, but {expected} was expected; the download was removed.",
+                input.url
+            );
+            exit(Failure::Validation);
+        }
+        Some(_) => {}
+        None if verify => {
+            eprintln!(
+                "There's no checksum to verify {} against; its checksum is {checksum}. Pass it with --aida-sha256 or --mapping-sha256 once you've checked it, or skip the verification with --no-verify.",
+                input.url
+            );
+            exit(Failure::Validation);
+        }
+        None => eprintln!("Not verifying {}, whose checksum is {checksum}.", input.url),
+    }
+
+    path
+}
+
+/// Extracts the ZIP archive at `path` into the directory next to it without the `.zip`
+/// extension, unless it has already been extracted, and returns the path of the directory.
+///
+/// The archive is first extracted next to the directory and only renamed once it's complete,
+/// so an interrupted extraction is started over by the next run.
+fn extract(path: &Path) -> PathBuf {
+    let directory = path.with_extension("");
+    if directory.exists() {
+        eprintln!("{} is already extracted.", path.display());
+        return directory;
+    }
+
+    eprintln!(
+        "Extracting {} into {}.",
+        path.display(),
+        directory.display()
+    );
+    let partial = path.with_extension("partial");
+    let _ = fs::remove_dir_all(&partial);
+    let result = ZipArchive::new(exit::open(path)).and_then(|mut archive| {
+        // The files of the archive are in a single directory, which is left out.
+        archive.extract_unwrapped_root_dir(&partial, root_dir_common_filter)
+    });
+    if let Err(error) = result
+        .map_err(|x| x.to_string())
+        .and_then(|_| fs::rename(&partial, &directory).map_err(|x| x.to_string()))
+    {
+        let _ = fs::remove_dir_all(&partial);
+        fail(
+            Failure::Write,
+            format!("Couldn't extract {}: {error}.", path.display()),
+        );
+    }
+
+    directory
+}
+
+/// Downloads the AIDA CoNLL-YAGO annotations and, if given, a hosted wiki2qid mapping
+/// into `{cache_dir}/downloads`, extracts the archive of the annotations, and prints the paths
+/// of the annotations and of the mapping.
+pub fn fetch(cache_dir: &str, aida: &Download, mapping: Option<&Download>, verify: bool) {
+    let dir = Path::new(cache_dir).join("downloads");
+    fs::create_dir_all(&dir).unwrap();
+
+    let archive = fetch_one(aida, &dir, verify);
+    let mapping = mapping.map(|x| fetch_one(x, &dir, verify));
+
+    // A mirror may host the annotations without the archive.
+    let aida = match archive.extension() {
+        Some(extension) if extension == "zip" => extract(&archive),
+        _ => archive,
+    };
+    println!("{}", aida.display());
+    if let Some(mapping) = mapping {
+        println!("{}", mapping.display());
+    }
+}
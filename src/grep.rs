@@ -0,0 +1,62 @@
+use crate::dataset::{read_dataset, Mention};
+use crate::redirects::parse_qid;
+
+/// The conditions a mention has to meet to match, all of which have to hold.
+#[derive(Debug)]
+pub struct Query {
+    pub qid: Option<String>,
+    pub title: Option<String>,
+    pub surface: Option<String>,
+}
+
+impl Query {
+    fn matches(&self, mention: &Mention, surface: &str) -> bool {
+        self.qid
+            .as_deref()
+            .is_none_or(|qid| parse_qid(qid).is_some_and(|qid| mention.qid == Some(qid)))
+            && self.title.as_deref().is_none_or(|title| {
+                mention.title.as_deref() == Some(title.replace(' ', "_").as_str())
+            })
+            && self.surface.as_deref().is_none_or(|x| surface.contains(x))
+    }
+}
+
+/// Prints every mention of the generated dataset matching `query`, one per line, with
+/// its split, document, span, surface form, title, and QID.
+///
+/// Returns whether any mention matched.
+pub fn grep(input: &str, query: &Query) -> bool {
+    let mut matches = 0;
+    let mut documents = 0;
+
+    for split in read_dataset(input) {
+        for document in &split.documents {
+            let mut matched = false;
+
+            for mention in &document.entities {
+                let surface = document.surface(mention);
+                if !query.matches(mention, &surface) {
+                    continue;
+                }
+
+                println!(
+                    "{}\t{}\t{}-{}\t{surface}\t{}\t{}",
+                    split.name,
+                    document.document_id,
+                    mention.start,
+                    mention.end,
+                    mention.title.as_deref().unwrap_or("--NME--"),
+                    mention.qid.map(|x| format!("Q{x}")).unwrap_or_default(),
+                );
+                matches += 1;
+                matched = true;
+            }
+
+            documents += matched as usize;
+        }
+    }
+
+    eprintln!("Found {matches} matching mentions in {documents} documents.");
+
+    matches > 0
+}
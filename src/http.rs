@@ -0,0 +1,232 @@
+use crate::exit::{self, fail, Failure};
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const USER_AGENT: &str = concat!(
+    env!("CARGO_PKG_NAME"),
+    "/",
+    env!("CARGO_PKG_VERSION"),
+    " (https://github.com/cyanic-selkie/aida-conll-yago-wikidata)"
+);
+/// The pause before the first retry, doubled after every failed attempt.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// An HTTP client for all the online lookups, which caches every response on disk,
+/// retries failed requests with an exponential backoff, and caps the request rate.
+///
+/// Since the responses are cached by their URL and query, a repeated run makes no
+/// requests at all and can be reproduced offline.
+pub struct HttpClient {
+    cache_dir: PathBuf,
+    interval: Duration,
+    max_retries: u32,
+    last_request: Option<Instant>,
+    read_only: bool,
+}
+
+impl HttpClient {
+    pub fn new(cache_dir: &str, requests_per_second: f64, max_retries: u32) -> Self {
+        Self {
+            cache_dir: PathBuf::from(cache_dir).join("http"),
+            interval: Duration::from_secs_f64(1.0 / requests_per_second.max(f64::EPSILON)),
+            max_retries,
+            last_request: None,
+            read_only: false,
+        }
+    }
+
+    /// Keeps the cache as it is, e.g. in a dry run: the cached responses are still used, but
+    /// new ones aren't written into it.
+    pub fn read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    fn cache_path(&self, url: &str, query: &[(&str, &str)]) -> PathBuf {
+        let mut hasher = Sha256::new();
+        hasher.update(url.as_bytes());
+        for (key, value) in query {
+            hasher.update(b"\0");
+            hasher.update(key.as_bytes());
+            hasher.update(b"=");
+            hasher.update(value.as_bytes());
+        }
+
+        let hash = to_hex(&hasher.finalize());
+
+        self.cache_dir.join(format!("{hash}.json"))
+    }
+
+    fn wait(&mut self) {
+        if let Some(last_request) = self.last_request {
+            let elapsed = last_request.elapsed();
+            if elapsed < self.interval {
+                thread::sleep(self.interval - elapsed);
+            }
+        }
+        self.last_request = Some(Instant::now());
+    }
+
+    /// Sends a GET request with the given query, or returns the cached response, and parses it as JSON.
+    ///
+    /// A cached response that can't be parsed, e.g. one truncated by a full disk, is requested
+    /// again, while a response that can't be parsed fails with [`Failure::Parse`] and isn't cached.
+    pub fn get_json<T: DeserializeOwned>(&mut self, url: &str, query: &[(&str, &str)]) -> T {
+        let path = self.cache_path(url, query);
+
+        if let Some(value) = fs::read_to_string(&path)
+            .ok()
+            .and_then(|body| serde_json::from_str(&body).ok())
+        {
+            return value;
+        }
+
+        let body = self.fetch(url, query);
+        let value = serde_json::from_str(&body).unwrap_or_else(|error| {
+            fail(
+                Failure::Parse,
+                format!("Couldn't parse the response of {url}: {error}."),
+            )
+        });
+        if !self.read_only {
+            if let Err(error) = fs::create_dir_all(&self.cache_dir) {
+                fail(
+                    Failure::Write,
+                    format!("Couldn't create {}: {error}.", self.cache_dir.display()),
+                );
+            }
+            write_atomically(&path, body.as_bytes());
+        }
+
+        value
+    }
+
+    fn fetch(&mut self, url: &str, query: &[(&str, &str)]) -> String {
+        let mut backoff = INITIAL_BACKOFF;
+        let mut attempt = 0;
+
+        loop {
+            self.wait();
+
+            let mut request = ureq::get(url).set("User-Agent", USER_AGENT);
+            for (key, value) in query {
+                request = request.query(key, value);
+            }
+
+            let retry_after = match request.call() {
+                Ok(response) => {
+                    return response.into_string().unwrap_or_else(|error| {
+                        fail(
+                            Failure::Other,
+                            format!("Couldn't read the response of {url}: {error}."),
+                        )
+                    })
+                }
+                Err(ureq::Error::Status(status, response))
+                    if (status == 429 || status >= 500) && attempt < self.max_retries =>
+                {
+                    response
+                        .header("Retry-After")
+                        .and_then(|x| x.parse::<u64>().ok())
+                        .map(Duration::from_secs)
+                }
+                Err(ureq::Error::Transport(_)) if attempt < self.max_retries => None,
+                Err(error) => fail(Failure::Other, format!("Request to {url} failed: {error}.")),
+            };
+
+            let pause = retry_after.unwrap_or(backoff).max(backoff);
+            eprintln!(
+                "Request to {url} failed; retrying in {}s ({}/{}).",
+                pause.as_secs_f64(),
+                attempt + 1,
+                self.max_retries
+            );
+            thread::sleep(pause);
+            backoff *= 2;
+            attempt += 1;
+        }
+    }
+}
+
+/// Downloads `url` into `path` and returns the SHA-256 checksum of the download as a
+/// hexadecimal string.
+///
+/// The file is first written next to `path` and only renamed once it's complete, so
+/// an interrupted download never leaves a partial file behind at `path`.
+pub fn download(url: &str, path: &Path) -> String {
+    let response = match ureq::get(url).set("User-Agent", USER_AGENT).call() {
+        Ok(response) => response,
+        Err(error) => fail(Failure::Other, format!("Request to {url} failed: {error}.")),
+    };
+
+    let partial = partial_path(path);
+    let mut writer = BufWriter::new(exit::create(&partial));
+    let mut reader = response.into_reader();
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 1 << 16];
+
+    loop {
+        let length = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(length) => length,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => fail(
+                Failure::Other,
+                format!("Download of {url} failed: {error}."),
+            ),
+        };
+        hasher.update(&buffer[..length]);
+        writer.write_all(&buffer[..length]).unwrap();
+    }
+
+    writer.flush().unwrap();
+    drop(writer);
+    fs::rename(&partial, path).unwrap();
+
+    to_hex(&hasher.finalize())
+}
+
+/// Writes `contents` into the file at `path`, first writing them next to it and only renaming
+/// the file once it's complete, so an interrupted run never leaves a partial file at `path`.
+pub fn write_atomically(path: &Path, contents: &[u8]) {
+    let partial = partial_path(path);
+    exit::write(&partial, contents);
+    if let Err(error) = fs::rename(&partial, path) {
+        fail(
+            Failure::Write,
+            format!(
+                "Couldn't move {} to {}: {error}.",
+                partial.display(),
+                path.display()
+            ),
+        );
+    }
+}
+
+fn partial_path(path: &Path) -> PathBuf {
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".part");
+    PathBuf::from(partial)
+}
+
+/// Returns the SHA-256 checksum of the file at `path` as a hexadecimal string.
+pub fn file_checksum(path: &Path) -> String {
+    let mut reader = exit::open(path);
+    let mut hasher = Sha256::new();
+    io::copy(&mut reader, &mut hasher).unwrap();
+
+    to_hex(&hasher.finalize())
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|x| format!("{x:02x}")).collect()
+}
@@ -0,0 +1,94 @@
+use crate::dataset::{read_dataset, Document};
+use crate::exit::{fail, Failure};
+use std::io::IsTerminal;
+
+const RESET: &str = "\x1b[0m";
+/// The color of the mentions that are linked to an entity.
+const LINKED: &str = "\x1b[1;32m";
+/// The color of the NIL mentions.
+const NIL: &str = "\x1b[1;33m";
+
+/// Returns whether to color the output, which is only done on a terminal and
+/// unless disabled with the `NO_COLOR` environment variable.
+fn use_colors() -> bool {
+    std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+}
+
+fn print_document(split: &str, document: &Document, colors: bool) {
+    let paint = |text: &str, color: &str| {
+        if colors {
+            format!("{color}{text}{RESET}")
+        } else {
+            text.to_owned()
+        }
+    };
+    let color = |index: usize| match document.entities[index].title {
+        Some(_) => LINKED,
+        None => NIL,
+    };
+
+    println!("document {} ({split})", document.document_id);
+    if let Some(uuid) = &document.uuid {
+        println!("uuid {uuid}");
+    }
+    println!();
+
+    let mut entities = (0..document.entities.len()).collect::<Vec<_>>();
+    entities.sort_by_key(|i| document.entities[*i].start);
+
+    let chars = document.text.chars().collect::<Vec<_>>();
+    let mut text = String::new();
+    let mut position = 0;
+    for &i in &entities {
+        let mention = &document.entities[i];
+        let (start, end) = (mention.start as usize, mention.end as usize);
+        if start < position || end > chars.len() {
+            continue;
+        }
+
+        text.extend(&chars[position..start]);
+        let surface = chars[start..end].iter().collect::<String>();
+        text.push_str(&paint(&format!("[{surface}]"), color(i)));
+        text.push_str(&paint(&i.to_string(), color(i)));
+        position = end;
+    }
+    text.extend(&chars[position..]);
+    println!("{text}");
+    println!();
+
+    for i in entities {
+        let mention = &document.entities[i];
+        let target = match (&mention.title, mention.qid) {
+            (Some(title), Some(qid)) => format!("{title} (Q{qid})"),
+            (Some(title), None) => format!("{title} (no QID)"),
+            (None, _) => "NIL".to_owned(),
+        };
+        println!(
+            "{:>3}  {}  {target}",
+            paint(&i.to_string(), color(i)),
+            document.surface(mention)
+        );
+    }
+}
+
+/// Pretty-prints the document with the given ID from the generated dataset, with
+/// its mentions highlighted and listed together with their titles and QIDs.
+pub fn inspect(input: &str, document_id: u32) {
+    let colors = use_colors();
+
+    for split in read_dataset(input) {
+        if let Some(document) = split
+            .documents
+            .iter()
+            .find(|x| x.document_id == document_id)
+        {
+            print_document(&split.name, document, colors);
+            return;
+        }
+    }
+
+    fail(
+        Failure::Other,
+        format!("No document with the ID {document_id} in {input}."),
+    );
+}
@@ -0,0 +1,26 @@
+use hashbrown::HashSet;
+use std::sync::Arc;
+
+/// Shares a single allocation among the equal strings it's given, e.g. the titles of the
+/// thousands of tokens of the mentions of the same entity.
+#[derive(Debug, Default)]
+pub struct Interner {
+    strings: HashSet<Arc<str>>,
+}
+
+impl Interner {
+    /// Returns the shared copy of `string`, which is allocated the first time it's given.
+    pub fn intern(&mut self, string: &str) -> Arc<str> {
+        if let Some(interned) = self.strings.get(string) {
+            return interned.clone();
+        }
+        let interned = Arc::<str>::from(string);
+        self.strings.insert(interned.clone());
+        interned
+    }
+
+    /// Returns the distinct strings given so far.
+    pub fn strings(&self) -> impl Iterator<Item = &str> {
+        self.strings.iter().map(|x| x.as_ref())
+    }
+}
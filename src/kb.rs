@@ -0,0 +1,202 @@
+use crate::exit;
+use crate::redirects::parse_qid;
+use crate::write_batch;
+use arrow::array::{ArrayRef, ListArray, StringArray, StructArray, UInt32Array};
+use arrow::buffer::OffsetBuffer;
+use arrow::datatypes::{DataType, Field, FieldRef, Schema};
+use arrow::record_batch::RecordBatch;
+use hashbrown::HashSet;
+use lazy_regex::regex_captures;
+use serde_json::Value;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::sync::Arc;
+
+/// A single entity of the knowledge base.
+#[derive(Debug, Default)]
+struct KbEntity {
+    qid: u32,
+    label: Option<String>,
+    description: Option<String>,
+    aliases: Vec<String>,
+    claims: Vec<(String, String)>,
+}
+
+/// Loads the QIDs from the `qid2pageid.tsv` file written next to the dataset.
+fn load_dataset_qids(path: &str) -> HashSet<u32> {
+    let reader = BufReader::new(exit::open(path));
+
+    reader
+        .lines()
+        .skip(1)
+        .map(|x| x.unwrap())
+        .filter_map(|line| parse_qid(line.split('\t').next()?))
+        .collect()
+}
+
+/// Formats the value of a claim's main snak as a string, e.g., "Q5" for items
+/// or the plain string for external identifiers.
+fn format_value(snak: &Value) -> Option<String> {
+    let value = &snak["datavalue"]["value"];
+
+    match snak["datatype"].as_str()? {
+        "wikibase-item" | "wikibase-property" => Some(value["id"].as_str()?.to_owned()),
+        "time" => Some(value["time"].as_str()?.to_owned()),
+        "quantity" => Some(value["amount"].as_str()?.to_owned()),
+        "monolingualtext" => Some(value["text"].as_str()?.to_owned()),
+        "globe-coordinate" => Some(format!(
+            "{},{}",
+            value["latitude"].as_f64()?,
+            value["longitude"].as_f64()?
+        )),
+        _ => Some(value.as_str()?.to_owned()),
+    }
+}
+
+fn parse_entity(entity: &Value, qid: u32, properties: &[String]) -> KbEntity {
+    let english = |key: &str| entity[key]["en"]["value"].as_str().map(|x| x.to_owned());
+
+    let aliases = entity["aliases"]["en"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|x| Some(x["value"].as_str()?.to_owned()))
+        .collect();
+
+    let mut claims = vec![];
+    for property in properties {
+        for claim in entity["claims"][property].as_array().into_iter().flatten() {
+            if let Some(value) = format_value(&claim["mainsnak"]) {
+                claims.push((property.clone(), value));
+            }
+        }
+    }
+
+    KbEntity {
+        qid,
+        label: english("labels"),
+        description: english("descriptions"),
+        aliases,
+        claims,
+    }
+}
+
+/// Scans a Wikidata JSON dump, with one entity per line, and keeps the English labels,
+/// descriptions, and aliases, together with the claims for `properties`, of the entities in `qids`.
+fn extract_entities(path: &str, qids: &HashSet<u32>, properties: &[String]) -> Vec<KbEntity> {
+    let reader = BufReader::new(exit::open(path));
+    let mut entities = vec![];
+
+    for line in reader.lines().map(|x| x.unwrap()) {
+        let line = line.trim_end().trim_end_matches(',');
+
+        // Parsing the whole JSON is by far the slowest part, so the ID is checked first.
+        let head = line.get(..256).unwrap_or(line);
+        let Some((_, id)) = regex_captures!(r#""id"\s*:\s*"Q(\d+)""#, head) else {
+            continue;
+        };
+        let Ok(qid) = id.parse::<u32>() else {
+            continue;
+        };
+        if !qids.contains(&qid) {
+            continue;
+        }
+
+        let entity = serde_json::from_str::<Value>(line).unwrap();
+        entities.push(parse_entity(&entity, qid, properties));
+    }
+
+    entities.sort_unstable_by_key(|x| x.qid);
+    entities
+}
+
+/// Extracts the knowledge base for the QIDs in the dataset from a Wikidata dump into a Parquet file.
+pub fn extract_kb(input_dir: &str, input_wikidata: &str, output: &str, properties: &[String]) {
+    let index = Path::new(input_dir).join("qid2pageid.tsv");
+    let qids = load_dataset_qids(index.to_str().unwrap());
+
+    let entities = extract_entities(input_wikidata, &qids, properties);
+    eprintln!(
+        "Found {} of the {} entities in the dataset.",
+        entities.len(),
+        qids.len()
+    );
+
+    let string_field = FieldRef::new(Field::new("", DataType::Utf8, false));
+    let claim_fields = vec![
+        Field::new("property", DataType::Utf8, false),
+        Field::new("value", DataType::Utf8, false),
+    ];
+    let claim_field = FieldRef::new(Field::new(
+        "",
+        DataType::Struct(claim_fields.clone().into()),
+        false,
+    ));
+
+    let qid_array = entities.iter().map(|x| x.qid).collect::<UInt32Array>();
+    let labels = entities
+        .iter()
+        .map(|x| x.label.as_deref())
+        .collect::<StringArray>();
+    let descriptions = entities
+        .iter()
+        .map(|x| x.description.as_deref())
+        .collect::<StringArray>();
+
+    let aliases = ListArray::new(
+        string_field.clone(),
+        OffsetBuffer::from_lengths(entities.iter().map(|x| x.aliases.len())),
+        Arc::new(
+            entities
+                .iter()
+                .flat_map(|x| &x.aliases)
+                .map(Some)
+                .collect::<StringArray>(),
+        ),
+        None,
+    );
+
+    let claims = entities.iter().flat_map(|x| &x.claims).collect::<Vec<_>>();
+    let claim_values = StructArray::new(
+        claim_fields.into(),
+        vec![
+            Arc::new(
+                claims
+                    .iter()
+                    .map(|(x, _)| Some(x.as_str()))
+                    .collect::<StringArray>(),
+            ),
+            Arc::new(
+                claims
+                    .iter()
+                    .map(|(_, x)| Some(x.as_str()))
+                    .collect::<StringArray>(),
+            ),
+        ],
+        None,
+    );
+    let claims = ListArray::new(
+        claim_field.clone(),
+        OffsetBuffer::from_lengths(entities.iter().map(|x| x.claims.len())),
+        Arc::new(claim_values),
+        None,
+    );
+
+    let schema = Schema::new(vec![
+        Field::new("qid", DataType::UInt32, false),
+        Field::new("label", DataType::Utf8, true),
+        Field::new("description", DataType::Utf8, true),
+        Field::new("aliases", DataType::List(string_field), false),
+        Field::new("claims", DataType::List(claim_field), false),
+    ]);
+    let arrays: Vec<ArrayRef> = vec![
+        Arc::new(qid_array),
+        Arc::new(labels),
+        Arc::new(descriptions),
+        Arc::new(aliases),
+        Arc::new(claims),
+    ];
+
+    let batch = RecordBatch::try_new(Arc::new(schema), arrays).unwrap();
+    write_batch(batch, output, &[]);
+}
@@ -0,0 +1,306 @@
+use crate::sentinels::{is_nil, NIL_SENTINELS};
+use clap::ValueEnum;
+use memchr::{memchr_iter, memrchr};
+use std::iter;
+
+/// The fields of a line separated by `separator`, which are found as they're accessed instead
+/// of collected, so splitting the hundreds of thousands of lines of the dataset doesn't allocate.
+#[derive(Debug, Clone, Copy)]
+pub struct Fields<'a> {
+    line: &'a str,
+    separator: u8,
+    count: usize,
+}
+
+impl<'a> Fields<'a> {
+    /// Splits `line` on the ASCII `separator`.
+    pub fn new(line: &'a str, separator: u8) -> Self {
+        Self {
+            line,
+            separator,
+            count: memchr_iter(separator, line.as_bytes()).count() + 1,
+        }
+    }
+
+    /// Returns the number of fields.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Returns the field `index`, if the line has as many.
+    pub fn get(&self, index: usize) -> Option<&'a str> {
+        let ends =
+            memchr_iter(self.separator, self.line.as_bytes()).chain(iter::once(self.line.len()));
+        let mut start = 0;
+        for (i, end) in ends.enumerate() {
+            if i == index {
+                return Some(&self.line[start..end]);
+            }
+            start = end + 1;
+        }
+        None
+    }
+
+    /// Returns the last field.
+    pub fn last(&self) -> &'a str {
+        let start = memrchr(self.separator, self.line.as_bytes()).map_or(0, |x| x + 1);
+        &self.line[start..]
+    }
+}
+
+/// The layout of the columns of a release of the AIDA CoNLL-YAGO dataset in the TSV format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TsvProfile {
+    /// The token, `B` or `I`, the mention, the YAGO2 title or a NIL sentinel, the Wikipedia
+    /// URL, and the Wikipedia pageid, without the Freebase MID.
+    AidaYago2,
+    /// The columns of `aida-yago2` followed by the Freebase MID, as in `AIDA-YAGO2-dataset.tsv`.
+    AidaYago2PlusFreebase,
+    /// The columns given with `--tsv-columns`.
+    Custom,
+}
+
+/// The zero-based indices of the columns of the lines of the tokens of mentions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TsvColumns {
+    pub token: usize,
+    pub prefix: usize,
+    pub title: usize,
+    pub url: usize,
+    pub pageid: Option<usize>,
+    /// The most columns a line can have.
+    pub columns: usize,
+}
+
+impl TsvColumns {
+    /// Parses the columns from a comma-separated list of `name=index`, e.g.
+    /// `token=0,prefix=1,title=3,url=4,pageid=5,columns=7`, where `token`, `prefix`, `title`,
+    /// and `url` are required, and `columns` defaults to one past the last index.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let (mut token, mut prefix, mut title, mut url, mut pageid, mut columns) =
+            (None, None, None, None, None, None);
+
+        for part in spec.split(',').filter(|x| !x.trim().is_empty()) {
+            let (name, index) = part
+                .split_once('=')
+                .ok_or_else(|| format!("expected name=index, found {:?}", part.trim()))?;
+            let index = index
+                .trim()
+                .parse::<usize>()
+                .map_err(|_| format!("invalid index {:?} of {}", index.trim(), name.trim()))?;
+            let column = match name.trim() {
+                "token" => &mut token,
+                "prefix" => &mut prefix,
+                "title" => &mut title,
+                "url" => &mut url,
+                "pageid" => &mut pageid,
+                "columns" => &mut columns,
+                name => return Err(format!("unknown column {name:?}")),
+            };
+            *column = Some(index);
+        }
+
+        let required = |index: Option<usize>, name: &str| {
+            index.ok_or_else(|| format!("missing the index of the {name} column"))
+        };
+        let (token, prefix, title, url) = (
+            required(token, "token")?,
+            required(prefix, "prefix")?,
+            required(title, "title")?,
+            required(url, "url")?,
+        );
+        let last = [token, prefix, title, url, pageid.unwrap_or_default()]
+            .into_iter()
+            .max()
+            .unwrap();
+        let columns = columns.unwrap_or(last + 1);
+        if columns <= last {
+            return Err(format!(
+                "{columns} columns can't hold the column with the index {last}"
+            ));
+        }
+
+        Ok(Self {
+            token,
+            prefix,
+            title,
+            url,
+            pageid,
+            columns,
+        })
+    }
+}
+
+/// How the lines of the AIDA CoNLL-YAGO dataset are read: the columns of the tokens of
+/// mentions, and the strings that mark a mention as NIL.
+#[derive(Debug, Clone)]
+pub struct TsvLayout {
+    columns: TsvColumns,
+    nil_sentinels: Vec<String>,
+}
+
+impl Default for TsvLayout {
+    fn default() -> Self {
+        Self::new(
+            TsvProfile::AidaYago2PlusFreebase,
+            None,
+            NIL_SENTINELS.map(String::from).to_vec(),
+        )
+    }
+}
+
+impl TsvLayout {
+    /// Creates the layout of `profile`, or of the `custom` columns for [`TsvProfile::Custom`].
+    pub fn new(
+        profile: TsvProfile,
+        custom: Option<TsvColumns>,
+        nil_sentinels: Vec<String>,
+    ) -> Self {
+        let aida = TsvColumns {
+            token: 0,
+            prefix: 1,
+            title: 3,
+            url: 4,
+            pageid: Some(5),
+            columns: 6,
+        };
+        let columns = match profile {
+            TsvProfile::AidaYago2 => aida,
+            TsvProfile::AidaYago2PlusFreebase => TsvColumns { columns: 7, ..aida },
+            TsvProfile::Custom => custom.expect("the custom profile requires --tsv-columns"),
+        };
+
+        Self {
+            columns,
+            nil_sentinels,
+        }
+    }
+
+    /// Checks the number of `fields` of a line, and that the tokens of mentions start with
+    /// `B` or `I`.
+    ///
+    /// A line is either a single token outside of mentions, or the token of a mention, which has
+    /// at least the columns up to the title and at most the columns of the layout. The columns
+    /// after the title are missing from the NIL mentions of some releases.
+    pub fn check(&self, fields: &Fields) -> Result<(), String> {
+        let columns = &self.columns;
+        let required = columns.token.max(columns.prefix).max(columns.title) + 1;
+        if fields.count() != 1 && !(required..=columns.columns).contains(&fields.count()) {
+            return Err(format!(
+                "expected 1 or {required} to {} tab-separated fields, found {}",
+                columns.columns,
+                fields.count()
+            ));
+        }
+        if fields.count() > 1 && !matches!(fields.get(columns.prefix), Some("B" | "I")) {
+            return Err(format!("expected B or I in field {}", columns.prefix + 1));
+        }
+        Ok(())
+    }
+
+    /// Returns the token of the checked `fields`.
+    pub fn token<'a>(&self, fields: &Fields<'a>) -> &'a str {
+        if fields.count() == 1 {
+            fields.line
+        } else {
+            fields.get(self.columns.token).unwrap()
+        }
+    }
+
+    /// Returns whether the checked `fields` are of a token of a mention, and whether it begins
+    /// the mention.
+    pub fn mention(&self, fields: &Fields) -> Option<bool> {
+        (fields.count() > 1).then(|| fields.get(self.columns.prefix) == Some("B"))
+    }
+
+    /// Returns the title in the title column of the checked `fields` of a mention, or `None`
+    /// if it's NIL.
+    pub fn title<'a>(&self, fields: &Fields<'a>) -> Option<&'a str> {
+        fields
+            .get(self.columns.title)
+            .filter(|x| fields.count() > 1 && !is_nil(x, &self.nil_sentinels))
+    }
+
+    /// Returns the Wikipedia URL of the mention in the checked `fields` if it's linked, i.e.
+    /// neither its title nor its URL is NIL.
+    ///
+    /// Some releases write NIL mentions with a URL cell, empty or with a sentinel, so these
+    /// aren't mistaken for linked ones with an empty title.
+    pub fn url<'a>(&self, fields: &Fields<'a>) -> Option<&'a str> {
+        self.title(fields)?;
+        fields
+            .get(self.columns.url)
+            .filter(|x| !is_nil(x, &self.nil_sentinels))
+    }
+
+    /// Returns the Wikipedia pageid of the mention in the checked `fields`, if any.
+    pub fn pageid(&self, fields: &Fields) -> Option<u32> {
+        fields.get(self.columns.pageid?)?.trim().parse().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fields_are_split_on_the_separator() {
+        let fields = Fields::new("Germany\tB\tGermany\tGermany\thttp://x\t11867", b'\t');
+        assert_eq!(fields.count(), 6);
+        assert_eq!(fields.get(0), Some("Germany"));
+        assert_eq!(fields.get(5), Some("11867"));
+        assert_eq!(fields.get(6), None);
+        assert_eq!(fields.last(), "11867");
+    }
+
+    #[test]
+    fn empty_fields_are_kept() {
+        let fields = Fields::new("\tB\t\t", b'\t');
+        assert_eq!(fields.count(), 4);
+        assert_eq!(fields.get(0), Some(""));
+        assert_eq!(fields.get(2), Some(""));
+        assert_eq!(fields.last(), "");
+
+        let fields = Fields::new("EU", b'\t');
+        assert_eq!(fields.count(), 1);
+        assert_eq!(fields.get(0), Some("EU"));
+        assert_eq!(fields.last(), "EU");
+    }
+
+    #[test]
+    fn custom_columns_are_parsed() {
+        let columns = TsvColumns::parse("token=0,prefix=1,title=2,url=3").unwrap();
+        assert_eq!(
+            (columns.title, columns.pageid, columns.columns),
+            (2, None, 4)
+        );
+
+        assert!(TsvColumns::parse("token=0,prefix=1,title=2").is_err());
+        assert!(TsvColumns::parse("token=0,prefix=1,title=2,url=3,columns=3").is_err());
+        assert!(TsvColumns::parse("token=0,prefix=1,title=2,url=x").is_err());
+    }
+
+    #[test]
+    fn the_columns_of_nil_mentions_after_the_title_are_optional() {
+        let layout = TsvLayout::default();
+        let nil = Fields::new("Blackburn\tI\tPeter Blackburn\t--NME--", b'\t');
+        assert!(layout.check(&nil).is_ok());
+        assert_eq!(layout.mention(&nil), Some(false));
+        assert_eq!(layout.url(&nil), None);
+
+        let linked = Fields::new("EU\tB\tEU\tEuropean_Union\thttp://x\t9317\t/m/x", b'\t');
+        assert!(layout.check(&linked).is_ok());
+        assert_eq!(layout.url(&linked), Some("http://x"));
+        assert_eq!(layout.pageid(&linked), Some(9317));
+
+        let short = TsvLayout::new(
+            TsvProfile::AidaYago2,
+            None,
+            NIL_SENTINELS.map(String::from).to_vec(),
+        );
+        assert!(short.check(&linked).is_err());
+        assert!(layout
+            .check(&Fields::new("EU\tX\tEU\tEuropean_Union", b'\t'))
+            .is_err());
+    }
+}
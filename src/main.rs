@@ -1,356 +1,3193 @@
-use apache_avro::{from_value, Reader};
-use arrow2::{
-    array::Array,
-    chunk::Chunk,
-    datatypes::*,
-    io::parquet::write::{
-        transverse, CompressionOptions, Encoding, FileWriter, RowGroupIterator, Version,
-        WriteOptions,
-    },
-};
-use arrow2_convert::{
-    serialize::{FlattenChunk, TryIntoArrow},
-    ArrowDeserialize, ArrowField, ArrowSerialize,
+mod aliases;
+mod align;
+mod avro;
+mod bench;
+mod browse;
+mod config;
+mod consistency;
+mod corrections;
+mod coverage;
+mod dataset;
+mod detokenize;
+mod diff;
+mod docstart;
+mod doctor;
+mod duplicates;
+mod encode;
+mod exit;
+mod export;
+mod fetch;
+mod grep;
+mod http;
+mod inspect;
+mod intern;
+mod kb;
+mod layout;
+mod manifest;
+mod mapping;
+mod merge;
+mod normalize;
+mod official;
+mod online;
+mod overlaps;
+mod parse_cache;
+mod priors;
+mod progress;
+mod provenance;
+mod random;
+mod records;
+mod redirects;
+mod repair;
+mod report;
+mod resolve;
+mod roundtrip;
+mod run_report;
+mod sample;
+mod sanitize;
+mod selection;
+mod sentinels;
+mod serve;
+mod spill;
+mod stats;
+mod store;
+mod validate;
+mod verify;
+mod watch;
+
+use aliases::{add_anchor_counts, count_aliases, write_aliases};
+use align::align_raw_texts;
+use arrow::array::{
+    ArrayRef, BooleanArray, Float32Array, LargeListArray, LargeStringArray, ListArray, StringArray,
+    StructArray, UInt32Array, UInt64Array,
 };
-use clap::Parser;
+use arrow::buffer::OffsetBuffer;
+use arrow::datatypes::{self, DataType, Field, FieldRef, Schema, SchemaRef};
+use arrow::record_batch::RecordBatch;
+use bench::bench;
+use browse::browse;
+use clap::builder::ArgPredicate;
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use config::expand_config;
+use consistency::check_offsets;
+use corrections::{list_corrections, write_suggestions};
+use coverage::{compute_coverage, write_coverage_report, Mentions};
+use dataset::SPLITS;
+use detokenize::Detokenizer;
+use diff::diff_datasets;
+use docstart::parse_docstart;
+use doctor::doctor;
+use duplicates::DuplicateFinder;
+use encode::encode_row_group;
+use exit::{exit, fail, Failure};
+use export::{export, ExportFormat, ImportFormat};
+use fetch::{fetch, Download, AIDA_URL};
+use grep::{grep, Query};
 use hashbrown::{HashMap, HashSet};
+use http::HttpClient;
+use indicatif::ProgressBar;
+use inspect::inspect;
+use intern::Interner;
 use itertools::Itertools;
-use lazy_regex::regex_captures;
-use serde::Deserialize;
-use std::fs::File;
+use kb::extract_kb;
+use layout::{Fields, TsvColumns, TsvLayout, TsvProfile};
+use manifest::{manifest_entries, write_manifest, ManifestEntry, CHECKSUMS_FILE, MANIFEST_FILE};
+use mapping::{
+    check_mapping_schemas, load_suspicious_targets, validate_mapping, write_mapping, MappingCache,
+    MappingEntry, Resolution,
+};
+use merge::{merge_datasets, Source};
+use normalize::{TitleNormalization, TitleNormalizer, UnicodeForm};
+use official::OfficialCounts;
+use online::OnlineResolver;
+use overlaps::{resolve_overlaps, OverlapPolicy};
+use parquet::arrow::arrow_writer::ArrowRowGroupWriterFactory;
+use parquet::arrow::{add_encoded_arrow_schema_to_metadata, ArrowSchemaConverter};
+use parquet::basic::{Compression, Encoding, ZstdLevel};
+use parquet::errors::ParquetError;
+use parquet::file::metadata::KeyValue;
+use parquet::file::properties::{WriterProperties, WriterVersion};
+use parquet::file::writer::SerializedFileWriter;
+use parse_cache::{parse_cache_path, read_parse_cache, write_parse_cache, Parsed};
+use priors::{mention_surfaces, Priors};
+use provenance::{provenance_metadata, snapshot_date, write_staleness_report};
+use rand::rngs::StdRng;
+use report::write_report;
+use resolve::{
+    load_resolver_chain, resolve_titles, suggest_corrections, write_qid_index,
+    write_resolution_report,
+};
+use roundtrip::verify_roundtrip;
+use run_report::{RunReport, Warning, WarningsAsErrors};
+use sample::print_sample;
+use sanitize::check_tokens;
+use selection::DocumentSelection;
+use sentinels::NIL_SENTINELS;
+use serve::serve;
+use spill::SplitRecords;
+use stats::print_stats;
+use std::fs;
 use std::io::{BufRead, BufReader};
-use std::path::Path;
-use unicode_normalization::UnicodeNormalization;
+use std::iter;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread::{self, ScopedJoinHandle};
+use store::OutputStore;
+use validate::validate_dataset;
+use verify::{check_sitelinks, verify_qids, write_pageid_report};
+use watch::watch;
 
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, arg_required_else_help = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+    /// Don't show progress bars.
+    #[arg(long, global = true)]
+    quiet: bool,
+    /// Seed of the random number generator used for the UUIDs of the documents and
+    /// for sampling, so runs with the same seed are reproducible.
+    #[arg(long, global = true, env = "AIDA_SEED")]
+    seed: Option<u64>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Convert the AIDA CoNLL-YAGO dataset to use Wikidata QIDs.
+    Convert(Box<Args>),
+    /// Print the number of documents, mentions, NIL mentions, and unique entities, and the histograms of
+    /// the mention and document lengths of every split.
+    Stats {
+        /// Path to the AIDA CoNLL-YAGO dataset in the TSV format, or to the output directory or a Parquet file of the conversion.
+        input: String,
+    },
+    /// Print a document of the generated dataset with its mentions highlighted, together with their titles and QIDs.
+    Inspect {
+        /// Path to the output directory or a Parquet file of the conversion.
+        input: String,
+        /// ID of the document to print.
+        #[arg(long)]
+        document_id: u32,
+    },
+    /// Print random documents or mentions of the generated dataset as JSON, one per line.
+    Sample {
+        /// Path to the output directory or a Parquet file of the conversion.
+        input: String,
+        /// Number of documents or mentions to print.
+        #[arg(short, default_value_t = 10)]
+        n: usize,
+        /// Only sample from this split (e.g. `train`, `validation`, or `test`).
+        #[arg(long)]
+        split: Option<String>,
+        /// Sample mentions instead of documents.
+        #[arg(long)]
+        mentions: bool,
+    },
+    /// List the mentions of the generated dataset with the given QID, title, or surface form, and the
+    /// documents they're in.
+    #[command(group(clap::ArgGroup::new("query").required(true).multiple(true)))]
+    Grep {
+        /// Path to the output directory or a Parquet file of the conversion.
+        input: String,
+        /// Only match mentions linked to this QID (with or without the "Q" prefix).
+        #[arg(long, group = "query")]
+        qid: Option<String>,
+        /// Only match mentions linked to this Wikipedia title.
+        #[arg(long, group = "query")]
+        title: Option<String>,
+        /// Only match mentions whose surface form contains this string.
+        #[arg(long, group = "query")]
+        surface: Option<String>,
+    },
+    /// Compare two generated datasets and report the documents whose text, entity spans, or entities
+    /// differ, together with a summary of the changes in every split.
+    Diff {
+        /// Path to the output directory of the first conversion.
+        dir_a: String,
+        /// Path to the output directory of the second conversion.
+        dir_b: String,
+    },
+    /// Concatenate several generated datasets into one, with renumbered document IDs and the name
+    /// of the dataset every document comes from in a `source` column.
+    Merge {
+        /// Paths to the output directories of the conversions, optionally prefixed with the name
+        /// of the dataset as `NAME=DIR` (defaults to the name of the directory).
+        #[arg(required = true)]
+        inputs: Vec<String>,
+        /// Path to the output directory.
+        #[arg(long)]
+        output_dir: String,
+    },
+    /// Convert a generated dataset into another format, without rerunning the conversion.
+    Export {
+        /// Path to the output directory or a Parquet file of the conversion.
+        input: String,
+        /// Format of the input.
+        #[arg(long, value_enum, default_value_t = ImportFormat::Parquet)]
+        from: ImportFormat,
+        /// Format to export the dataset to.
+        #[arg(long, value_enum)]
+        to: ExportFormat,
+        /// Path to the directory to write the exported splits into.
+        #[arg(long)]
+        output_dir: String,
+        /// Snap the offsets of the entities to the nearest boundaries of the tokens, printing
+        /// every adjustment, for inputs whose spans are slightly off.
+        #[arg(long)]
+        fix_offsets: bool,
+    },
+    /// Download the AIDA CoNLL-YAGO annotations and a hosted wiki2qid mapping into the cache directory,
+    /// verifying their checksums.
+    Fetch {
+        /// Path to the directory the inputs are downloaded into (under `downloads/`).
+        #[arg(long, default_value = ".cache")]
+        cache_dir: String,
+        /// URL of the archive with the AIDA CoNLL-YAGO annotations.
+        #[arg(long, default_value = AIDA_URL)]
+        aida_url: String,
+        /// Expected SHA-256 checksum of the AIDA CoNLL-YAGO archive.
+        #[arg(long)]
+        aida_sha256: Option<String>,
+        /// URL of a hosted wiki2qid mapping in the Apache Avro format.
+        #[arg(long)]
+        mapping_url: Option<String>,
+        /// Expected SHA-256 checksum of the mapping.
+        #[arg(long, requires = "mapping_url")]
+        mapping_sha256: Option<String>,
+        /// Keep the downloads without a checksum to verify them against, instead of failing.
+        #[arg(long)]
+        no_verify: bool,
+    },
+    /// Serve the generated dataset over HTTP, with JSON endpoints for documents (`/documents/{id}`),
+    /// the mentions of an entity (`/entities/{qid}/mentions`), and statistics (`/stats`).
+    Serve {
+        /// Path to the output directory or a Parquet file of the conversion.
+        input: String,
+        /// Address to listen on.
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        address: String,
+    },
+    /// Page through the documents of the generated dataset in a terminal browser, with the mentions
+    /// highlighted and filterable by QID or surface form.
+    Browse {
+        /// Path to the output directory or a Parquet file of the conversion.
+        input: String,
+    },
+    /// Write a static HTML page with the statistics of the generated dataset and its documents rendered
+    /// with highlighted mentions, for sharing the results of the conversion.
+    Report {
+        /// Path to the output directory or a Parquet file of the conversion.
+        input: String,
+        /// Path to the HTML file.
+        #[arg(long, default_value = "report.html")]
+        output: String,
+        /// The number of documents rendered per split, or 0 to render all of them.
+        #[arg(long, default_value_t = 20)]
+        max_documents: usize,
+    },
+    /// Check the generated Parquet files for an unsupported schema version, invalid or overlapping
+    /// entity spans, and duplicated documents within and between the splits.
+    Validate {
+        /// Path to the output directory of the conversion.
+        input_dir: String,
+    },
+    /// Check the mappings for duplicate titles, missing QIDs, pageid collisions, and schema problems.
+    ValidateMapping {
+        /// Path to the mappings between Wikipedia's titles and Wikidata's QIDs in the Apache Avro format.
+        #[arg(long)]
+        input_wiki2qid: String,
+    },
+    /// Build a minimal mapping file with only the titles annotated in the dataset.
+    BuildMapping {
+        /// Path to the AIDA ConLL-YAGO dataset in the TSV format.
+        #[arg(long)]
+        input_aida: String,
+        /// Fetch the titles from the Wikipedia API.
+        #[arg(long)]
+        from_api: bool,
+        /// Path to the output mappings in the Apache Avro format.
+        #[arg(long)]
+        output: String,
+        /// Path to the directory where the responses from the API are cached.
+        #[arg(long, default_value = ".cache")]
+        cache_dir: String,
+        /// The maximum number of requests per second sent to the Wikipedia and Wikidata APIs.
+        #[arg(long, default_value_t = 2.0)]
+        requests_per_second: f64,
+        /// The number of times a failed request is retried, with an exponential backoff.
+        #[arg(long, default_value_t = 5)]
+        max_retries: u32,
+        /// Comma-separated normalization steps applied to every title.
+        #[arg(long, value_enum, value_delimiter = ',')]
+        title_normalization: Vec<TitleNormalization>,
+        /// The Unicode normalization form of the titles.
+        #[arg(long, value_enum, default_value_t = UnicodeForm::Nfc)]
+        normalize: UnicodeForm,
+        /// The layout of the columns of the AIDA CoNLL-YAGO dataset.
+        #[arg(long, value_enum, default_value_t = TsvProfile::AidaYago2PlusFreebase, default_value_if("tsv_columns", ArgPredicate::IsPresent, "custom"))]
+        tsv_profile: TsvProfile,
+        /// Comma-separated zero-based indices of the columns for `--tsv-profile custom`, e.g.
+        /// `token=0,prefix=1,title=3,url=4,pageid=5,columns=7`.
+        #[arg(long, value_parser = TsvColumns::parse, required_if_eq("tsv_profile", "custom"))]
+        tsv_columns: Option<TsvColumns>,
+        /// Comma-separated strings that mark a mention as NIL in the title or URL cells, besides an empty cell.
+        #[arg(long, value_delimiter = ',', allow_hyphen_values = true, default_values_t = NIL_SENTINELS.map(String::from))]
+        nil_sentinels: Vec<String>,
+    },
+    /// Check that the inputs of the conversion exist and look as expected, and that the output
+    /// directory is writable, and print how to fix any problems.
+    Doctor {
+        /// Path to the AIDA ConLL-YAGO dataset in the TSV format.
+        #[arg(long, env = "AIDA_INPUT_AIDA")]
+        input_aida: String,
+        /// Path to the ConLL2003 dataset in the TSV format.
+        #[arg(long, env = "AIDA_INPUT_CONLL")]
+        input_conll: String,
+        /// Path to the mappings between Wikipedia's titles and Wikidata's QIDs in the Apache Avro format.
+        #[arg(long, env = "AIDA_WIKI2QID", required = true)]
+        input_wiki2qid: Vec<String>,
+        /// Path to the output directory.
+        #[arg(long, env = "AIDA_OUTPUT_DIR")]
+        output_dir: String,
+    },
+    /// Time every stage of the conversion over several runs, and print the timings next to the
+    /// ones of the previous benchmarks.
+    Bench {
+        /// The number of times the conversion is run.
+        #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(u32).range(1..))]
+        runs: u32,
+        /// Name of the benchmark in the history (e.g. the tuning flags or the commit).
+        #[arg(long)]
+        label: Option<String>,
+        /// Path to the TSV file the mean timings of every benchmark are appended to.
+        #[arg(long, default_value = "bench.tsv")]
+        history: String,
+        /// The options of the conversion, after `--`.
+        #[arg(last = true, required = true)]
+        args: Vec<String>,
+    },
+    /// Convert the dataset into Parquet, export it back into the TSV format, convert that again,
+    /// and print the documents that differ between the two datasets.
+    VerifyRoundtrip {
+        /// Path to the AIDA ConLL-YAGO dataset in the TSV format.
+        #[arg(long, env = "AIDA_INPUT_AIDA")]
+        input_aida: String,
+        /// Path to the ConLL2003 dataset in the TSV format.
+        #[arg(long, env = "AIDA_INPUT_CONLL")]
+        input_conll: String,
+        /// The other options of the conversion (e.g. `--input-wiki2qid`), after `--`.
+        #[arg(last = true)]
+        args: Vec<String>,
+    },
+    /// Print the completions for a shell.
+    #[command(hide = true)]
+    Completions { shell: clap_complete::Shell },
+    /// Print the man page in the roff format.
+    #[command(hide = true)]
+    Manpage,
+    /// Extract the labels, descriptions, aliases, and claims of the entities in the dataset from a Wikidata dump.
+    ExtractKb {
+        /// Path to the output directory of the conversion, with the `qid2pageid.tsv` file.
+        #[arg(long)]
+        input_dir: String,
+        /// Path to the Wikidata JSON dump, decompressed, with one entity per line.
+        #[arg(long)]
+        input_wikidata: String,
+        /// Path to the output knowledge base in the Apache Parquet format.
+        #[arg(long)]
+        output: String,
+        /// Comma-separated properties whose claims are kept.
+        #[arg(long, value_delimiter = ',', default_values_t = ["P31".to_owned(), "P279".to_owned()])]
+        properties: Vec<String>,
+    },
+    /// Inspect the registry of the corrections of the mappings.
+    Corrections {
+        #[command(subcommand)]
+        command: CorrectionsCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum CorrectionsCommand {
+    /// Print the bundled corrections, and the ones given with `--corrections`, with their reasons
+    /// and sources, and whether they're overridden.
+    List {
+        /// Path to a TOML file with additional corrections for the mappings; these override the bundled ones.
+        #[arg(long, env = "AIDA_CORRECTIONS")]
+        corrections: Option<String>,
+    },
+}
+
+#[derive(clap::Args, Debug)]
 struct Args {
     /// Path to the AIDA ConLL-YAGO dataset in the TSV format.
-    #[arg(long)]
+    #[arg(long, env = "AIDA_INPUT_AIDA")]
     input_aida: String,
     /// Path to the ConLL2003 dataset in the TSV format.
-    #[arg(long)]
+    #[arg(long, env = "AIDA_INPUT_CONLL")]
     input_conll: String,
     /// Path to the mappings between Wikipedia's titles and Wikidata's QIDs in the Apache Avro format.
-    #[arg(long)]
-    input_wiki2qid: String,
+    /// Can be given multiple times, in which case the mappings from later files take precedence.
+    #[arg(long, env = "AIDA_WIKI2QID", required = true)]
+    input_wiki2qid: Vec<String>,
     /// Path to the output directory.
-    #[arg(long)]
+    #[arg(long, env = "AIDA_OUTPUT_DIR")]
     output_dir: String,
+    /// URL of an object store to stream the Parquet files of the splits into, instead of the output
+    /// directory (e.g. `s3://bucket/prefix`, `gs://bucket/prefix`, `az://container/prefix`, or
+    /// `file:///path`), configured by the environment variables of the store (e.g. `AWS_REGION`).
+    #[arg(long, env = "AIDA_OUTPUT_URL")]
+    output_url: Option<String>,
+    /// Skip the malformed lines of the inputs (e.g. of a corrupted download) with a warning
+    /// instead of failing.
+    #[arg(long)]
+    lenient: bool,
+    /// Path to a TOML file with any of the options of the conversion by their names (e.g. `input-aida = "..."`), which
+    /// the options given on the command line override.
+    #[arg(long, env = "AIDA_CONFIG")]
+    config: Option<String>,
+    /// Replace the outputs that already exist in the output directory.
+    #[arg(long, conflicts_with = "skip_existing")]
+    overwrite: bool,
+    /// Keep the outputs that already exist in the output directory, and only write the missing ones.
+    #[arg(long)]
+    skip_existing: bool,
+    /// Parse the inputs and resolve the titles, and report what would be written without writing any files.
+    #[arg(long)]
+    dry_run: bool,
+    /// Rerun the conversion whenever any of its inputs (e.g. the corrections) change, replacing the outputs.
+    #[arg(long, env = "AIDA_WATCH", conflicts_with = "skip_existing")]
+    watch: bool,
+    /// Template of the names of the split files in the output directory, in which `{split}` is
+    /// replaced with `train`, `validation`, or `test`.
+    #[arg(long, default_value = "{split}.parquet")]
+    name_template: String,
+    /// Name of the file of the training split, instead of the one from `--name-template`.
+    #[arg(long)]
+    train_name: Option<String>,
+    /// Name of the file of the validation split (e.g. `dev.parquet`), instead of the one from `--name-template`.
+    #[arg(long)]
+    validation_name: Option<String>,
+    /// Name of the file of the test split, instead of the one from `--name-template`.
+    #[arg(long)]
+    test_name: Option<String>,
+    /// Write a JSON summary of the run to this path: the counts of every split, the unresolved
+    /// titles, the applied corrections, the warnings, the timings, and the hashes of the outputs.
+    #[arg(long)]
+    report_json: Option<String>,
+    /// Turn the warnings into errors, which fail the conversion after the outputs are written:
+    /// every warning with `-W error`, or only the ones of a category with `-W <category>=error`
+    /// (`suspicious-target`, `suggested-correction`, `stale-title`, `fallback-resolution`,
+    /// `unmapped-title`, `qid-discrepancy`, `obsolete-qid`, `sitelink-conflict`,
+    /// `misaligned-raw-text`, `malformed-line`, `duplicate-document`, `pageid-disagreement`,
+    /// `problematic-token`, or `unwritable-cache`).
+    #[arg(short = 'W', long, value_name = "[CATEGORY=]error", value_parser = WarningsAsErrors::parse)]
+    warnings_as_errors: Vec<WarningsAsErrors>,
+    /// Only convert the first N (selected) documents of every split.
+    #[arg(long, value_name = "N")]
+    limit: Option<usize>,
+    /// Fail if the numbers of documents and linked mentions of the splits differ from the
+    /// published statistics of the AIDA CoNLL-YAGO dataset, printing the differences.
+    #[arg(long, conflicts_with_all = ["limit", "documents", "documents_file"])]
+    verify_official: bool,
+    /// Only convert the documents with these IDs, given as a comma-separated list of IDs and ranges (e.g. `1-200,946,1163`).
+    #[arg(long, value_parser = DocumentSelection::parse)]
+    documents: Option<DocumentSelection>,
+    /// Path to a file with the IDs or ranges of IDs of the documents to convert, one per line.
+    #[arg(long)]
+    documents_file: Option<String>,
+    /// Path to a TOML file with additional corrections for the mappings; these override the bundled ones.
+    #[arg(long, env = "AIDA_CORRECTIONS")]
+    corrections: Option<String>,
+    /// Path to a TSV file of Wikipedia redirects (`source<TAB>target`) to resolve titles through before the lookup.
+    #[arg(long)]
+    redirects: Option<String>,
+    /// Path to a TSV file of Wikidata redirects (`old<TAB>new`) used to replace the QIDs of merged items.
+    #[arg(long)]
+    qid_redirects: Option<String>,
+    /// Look up the titles missing from the mappings through the Wikipedia API.
+    #[arg(long)]
+    online: bool,
+    /// Path to the directory where the filtered mappings and the responses from online lookups are cached.
+    #[arg(long, env = "AIDA_CACHE_DIR", default_value = ".cache")]
+    cache_dir: String,
+    /// The maximum number of requests per second sent to the Wikipedia and Wikidata APIs.
+    #[arg(long, default_value_t = 2.0)]
+    requests_per_second: f64,
+    /// The number of times a failed request is retried, with an exponential backoff.
+    #[arg(long, default_value_t = 5)]
+    max_retries: u32,
+    /// Always scan the full mappings instead of using the cached subset from a previous run.
+    #[arg(long)]
+    no_mapping_cache: bool,
+    /// Always parse the TSV files instead of reading the records parsed by a previous run.
+    #[arg(long)]
+    no_parse_cache: bool,
+    /// Path to a TSV file of page properties (`pageid<TAB>property[<TAB>value]`), used to detect disambiguation pages
+    /// and to suggest corrections for the titles without QIDs.
+    #[arg(long)]
+    page_props: Option<String>,
+    /// Drop the mappings to disambiguation and list pages instead of only warning about them, so
+    /// no resolver but the corrections resolves a title to their pageids or QIDs.
+    #[arg(long)]
+    exclude_disambiguation: bool,
+    /// Path to a file of QIDs of disambiguation and list pages, one per line; mentions resolving to them are flagged in the `suspicious_target` column.
+    #[arg(long)]
+    target_qids: Option<String>,
+    /// Drop the mentions resolving to the QIDs given with `--target-qids` instead of flagging them.
+    #[arg(long, requires = "target_qids")]
+    strict_targets: bool,
+    /// Don't fall back to the pageids given in the TSV for the titles missing from the mappings.
+    #[arg(long)]
+    no_pageid_fallback: bool,
+    /// Check every QID against the live Wikidata and write the discrepancies to `qid_report.tsv` in the output directory. Only the titles found in the mappings are expected to be the English Wikipedia sitelinks of their items.
+    #[arg(long)]
+    verify_qids: bool,
+    /// Path to a TSV file of Wikidata sitelinks (`qid<TAB>site<TAB>title`) to check the QIDs against, writing the conflicts to `sitelink_report.tsv` in the output directory.
+    #[arg(long)]
+    sitelinks: Option<String>,
+    /// The site of another language (e.g., `dewiki`) whose sitelinks are also checked and included in the report.
+    #[arg(long, requires = "sitelinks")]
+    sitelink_site: Option<String>,
+    /// Comma-separated normalization steps applied to every title, whether it comes from the TSV, the mappings, the corrections, or the redirects.
+    #[arg(long, value_enum, value_delimiter = ',')]
+    title_normalization: Vec<TitleNormalization>,
+    /// The Unicode normalization form of the tokens and of every title, so that the lookups of the
+    /// titles in the mappings, the corrections, and the redirects don't miss differently encoded ones.
+    #[arg(long, value_enum, default_value_t = UnicodeForm::Nfc)]
+    normalize: UnicodeForm,
+    /// Fall back to case-insensitive and edit distance matching for the titles missing from the mappings; implies `--provenance`.
+    #[arg(long)]
+    fuzzy: bool,
+    /// The minimum similarity between two titles for an edit distance match.
+    #[arg(long, default_value_t = 0.9)]
+    fuzzy_threshold: f64,
+    /// Path to a TSV file of Wikipedia anchor counts (`anchor<TAB>qid<TAB>count`) to merge into `aliases.parquet`.
+    #[arg(long)]
+    anchor_counts: Option<String>,
+    /// Path to a Parquet file of Wikipedia anchors with the `surface`, `title`, and `count` columns, used to add
+    /// the `prior` of the annotated entity and the `candidates` to every mention.
+    #[arg(long)]
+    anchors: Option<String>,
+    /// The maximum number of candidates per mention, ordered by their prior.
+    #[arg(long, default_value_t = 30, requires = "anchors")]
+    max_candidates: usize,
+    /// The date of the mapping snapshot (YYYY-MM-DD) recorded in the outputs; defaults to the modification date of the mapping files.
+    #[arg(long, env = "AIDA_SNAPSHOT_DATE")]
+    snapshot_date: Option<String>,
+    /// Add a `uuid` column with a UUID for every document, drawn at random unless `--deterministic`.
+    #[arg(long)]
+    uuids: bool,
+    /// Produce byte-for-byte identical outputs from identical inputs: the UUIDs are derived from the
+    /// documents instead of drawn at random, and the snapshot date has to be given explicitly.
+    #[arg(long, requires = "snapshot_date")]
+    deterministic: bool,
+    /// Path to a TOML file with a `resolvers` array declaring the order in which the resolvers are tried,
+    /// out of `correction`, `mapping`, `redirect`, `pageid`, `online`, and `fuzzy`.
+    #[arg(long, env = "AIDA_RESOLVER_CONFIG")]
+    resolver_config: Option<String>,
+    /// Record the resolver that resolved every title in the `matched_via` column and in `resolutions.tsv` in the output directory.
+    #[arg(long)]
+    provenance: bool,
+    /// What to do with the mentions whose titles couldn't be mapped.
+    #[arg(long, value_enum, default_value_t = UnmappedPolicy::Fail)]
+    unmapped: UnmappedPolicy,
+    /// Remove the control and zero-width characters of the tokens, and replace their whitespace with underscores.
+    #[arg(long)]
+    sanitize_tokens: bool,
+    /// The layout of the columns of the AIDA CoNLL-YAGO dataset, for releases with other
+    /// columns than `AIDA-YAGO2-dataset.tsv`.
+    #[arg(long, value_enum, default_value_t = TsvProfile::AidaYago2PlusFreebase, default_value_if("tsv_columns", ArgPredicate::IsPresent, "custom"))]
+    tsv_profile: TsvProfile,
+    /// Comma-separated zero-based indices of the columns for `--tsv-profile custom`, e.g.
+    /// `token=0,prefix=1,title=3,url=4,pageid=5,columns=7`; `columns` is the most columns a line can have.
+    /// Implies `--tsv-profile custom`.
+    #[arg(long, value_parser = TsvColumns::parse, required_if_eq("tsv_profile", "custom"))]
+    tsv_columns: Option<TsvColumns>,
+    /// Comma-separated strings that mark a mention as NIL in the title or URL cells, besides an
+    /// empty cell, for releases of the dataset that use other ones than `--NME--`.
+    #[arg(long, value_delimiter = ',', allow_hyphen_values = true, default_values_t = NIL_SENTINELS.map(String::from))]
+    nil_sentinels: Vec<String>,
+    /// What to do with the mentions whose titles resolve to a page without a QID.
+    #[arg(long, value_enum, default_value_t = MissingQidPolicy::Keep)]
+    missing_qid: MissingQidPolicy,
+    /// What to do with the entities whose spans overlap other ones.
+    #[arg(long, value_enum, default_value_t = OverlapPolicy::Fail)]
+    overlaps: OverlapPolicy,
+    /// With `--unmapped fail`, tolerate at most this many mentions whose titles couldn't be mapped.
+    #[arg(long)]
+    max_unmapped: Option<usize>,
+    /// With `--unmapped fail`, tolerate at most this percentage of mentions whose titles couldn't be mapped.
+    #[arg(long)]
+    max_unmapped_pct: Option<f64>,
+    /// Use 64-bit offsets (LargeUtf8 and LargeList) for the text and entities columns.
+    #[arg(long)]
+    large_types: bool,
+    /// Write the character offsets of the entities and the tokens as 64-bit integers, for texts
+    /// longer than the 32-bit ones can hold, e.g. long concatenated documents.
+    #[arg(long)]
+    wide: bool,
+    /// The number of rows, i.e. documents or sentences, of every row group of the splits, which
+    /// bounds the memory of the writer and lets readers skip row groups.
+    #[arg(long, default_value_t = 1000, default_value_if("low_memory", ArgPredicate::IsPresent, "100"), value_parser = clap::value_parser!(u32).range(1..))]
+    row_group_size: u32,
+    /// Keep less in memory for huge inputs: spill the tokens of every split into the cache directory
+    /// as soon as they're parsed, read them back a row group of documents at a time to check,
+    /// generate, and write them, write every split before generating the next one, and write row
+    /// groups of 100 rows unless --row-group-size is given.
+    #[arg(long)]
+    low_memory: bool,
+    /// Format of the QIDs in the output; `string` writes them as "Q"-prefixed identifiers.
+    #[arg(long, value_enum, default_value_t = QidFormat::U32)]
+    qid_format: QidFormat,
+    /// Also write the per-token `tokens`, `iob_tags`, and `token_qids` columns.
+    #[arg(long)]
+    token_columns: bool,
+    /// Also write the `token_spans` column with the character offsets of every token in the text.
+    #[arg(long)]
+    token_spans: bool,
+    /// Join the tokens into natural-looking text, without spaces before punctuation or inside
+    /// brackets and quotes, instead of separating all of them with spaces.
+    #[arg(long)]
+    detokenize: bool,
+    /// Path to a directory with the raw texts of the documents (e.g. from the original Reuters
+    /// files), named by their IDs like `946.txt`, to use as the text instead of the tokens.
+    #[arg(long, value_name = "DIR")]
+    raw_texts: Option<String>,
+    /// Emit one data point per document, or per sentence with the entity offsets relative to
+    /// the sentence and a `sentence` column with its index in the document.
+    #[arg(long, value_enum, default_value_t = Granularity::Document, conflicts_with = "raw_texts")]
+    granularity: Granularity,
 }
 
-#[derive(Debug, Deserialize)]
-struct MappingRecord {
-    title: String,
-    #[allow(unused)]
-    pageid: u32,
-    qid: Option<u32>,
+impl Args {
+    /// Returns the client of the online lookups, which doesn't write into its cache in a dry run.
+    fn http_client(&self) -> HttpClient {
+        let client = HttpClient::new(&self.cache_dir, self.requests_per_second, self.max_retries);
+        if self.dry_run {
+            client.read_only()
+        } else {
+            client
+        }
+    }
+
+    /// Returns the cache of the filtered mappings, unless it's disabled with `--no-mapping-cache`.
+    /// A dry run reads it but doesn't write it.
+    fn mapping_cache(&self) -> Option<MappingCache<'_>> {
+        (!self.no_mapping_cache).then_some(MappingCache {
+            dir: &self.cache_dir,
+            writable: !self.dry_run,
+        })
+    }
+
+    fn tsv_layout(&self) -> TsvLayout {
+        TsvLayout::new(
+            self.tsv_profile,
+            self.tsv_columns,
+            self.nil_sentinels.clone(),
+        )
+    }
+
+    /// Returns the paths of every input of the conversion, which are watched with `--watch`.
+    fn input_files(&self) -> Vec<String> {
+        let mut paths = vec![self.input_aida.clone(), self.input_conll.clone()];
+        paths.extend(self.input_wiki2qid.iter().cloned());
+        paths.extend(
+            [
+                &self.config,
+                &self.documents_file,
+                &self.corrections,
+                &self.redirects,
+                &self.qid_redirects,
+                &self.page_props,
+                &self.target_qids,
+                &self.sitelinks,
+                &self.anchor_counts,
+                &self.anchors,
+                &self.resolver_config,
+            ]
+            .into_iter()
+            .flatten()
+            .cloned(),
+        );
+        paths.sort_unstable();
+        paths.dedup();
+        paths
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum UnmappedPolicy {
+    /// Fail the run, unless tolerated by `--max-unmapped` or `--max-unmapped-pct`.
+    Fail,
+    /// Drop the mentions, as if they weren't annotated.
+    Drop,
+    /// Keep the mentions without a QID and flag them in the `link_failed` column.
+    Nil,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum MissingQidPolicy {
+    /// Keep the mentions as linked, with their pageid and title but without a QID.
+    Keep,
+    /// Drop the mentions, as if they weren't annotated.
+    Drop,
+    /// Keep the mentions as NIL ones, without a pageid or title.
+    Nil,
+    /// Fail the run.
+    Fail,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Granularity {
+    Document,
+    /// Split the documents on the blank lines between their sentences.
+    Sentence,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum QidFormat {
+    U32,
+    String,
 }
 
 #[derive(Debug, PartialEq, Clone)]
 enum EntityType {
-    OutOfDistribution(String),
-    InDistribution(String, String),
+    OutOfDistribution(&'static str),
+    /// The title, shared with the other mentions of the entity, and the tag.
+    InDistribution(Arc<str>, &'static str),
     None,
 }
 
 #[derive(Debug)]
 struct TokenRecord {
     document_id: u32,
-    token: String,
+    /// The index of the document in the dataset, which tells apart documents with the same ID.
+    document: u32,
+    /// The index of the sentence in the document.
+    sentence: u32,
+    /// The text of the token, which is shared with the equal tokens.
+    token: Arc<str>,
     entity: EntityType,
+    /// Whether the token begins a mention (`B` in the second column), which separates
+    /// adjacent mentions of the same entity.
+    begins: bool,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Split {
     Train,
     Validation,
     Test,
 }
 
-#[derive(Debug, ArrowField, ArrowSerialize, ArrowDeserialize)]
+#[derive(Debug)]
 struct Entity {
-    start: u32,
-    end: u32,
+    start: u64,
+    end: u64,
     tag: String,
     pageid: Option<u32>,
     qid: Option<u32>,
     title: Option<String>,
+    matched_via: Option<String>,
+    suspicious_target: bool,
+    link_failed: bool,
+    nested: bool,
+    prior: Option<f32>,
+    candidates: Vec<Candidate>,
+}
+
+#[derive(Debug)]
+struct Candidate {
+    title: String,
+    qid: Option<u32>,
+    prior: f32,
+}
+
+#[derive(Debug)]
+struct TokenSpan {
+    start: u64,
+    end: u64,
 }
 
-#[derive(Debug, ArrowField, ArrowSerialize, ArrowDeserialize)]
+#[derive(Debug)]
 struct DataPoint {
     document_id: u32,
+    sentence: u32,
+    uuid: String,
     text: String,
     entities: Vec<Entity>,
+    tokens: Vec<Arc<str>>,
+    iob_tags: Vec<String>,
+    token_qids: Vec<Option<u32>>,
+    token_spans: Vec<TokenSpan>,
+}
+
+/// Extracts the title from the Wikipedia URL in the annotation.
+fn annotated_title(url: &str, normalizer: &TitleNormalizer) -> String {
+    let start = url.char_indices().nth(29).map_or(url.len(), |(x, _)| x);
+    normalizer.normalize(&url[start..])
+}
+
+/// Collects the distinct titles annotated in the AIDA CoNLL-YAGO dataset.
+fn read_titles(
+    path_aida: &str,
+    normalizer: &TitleNormalizer,
+    layout: &TsvLayout,
+) -> HashSet<String> {
+    let mut reader = BufReader::new(exit::open(path_aida));
+    let mut buffer = vec![];
+
+    let mut errors = LineErrors { lenient: None };
+    let mut titles = HashSet::new();
+
+    let mut i = 0;
+    while let Some(line) = read_line(&mut reader, &mut buffer, path_aida, i) {
+        match line {
+            Ok(line) => {
+                if let Some(url) = layout.url(&Fields::new(line, b'\t')) {
+                    titles.insert(annotated_title(url, normalizer));
+                }
+            }
+            Err(line) => errors.report(path_aida, i, "invalid UTF-8", &line),
+        }
+        i += 1;
+    }
+
+    titles
+}
+
+/// Reads the line `i` of `path` into `buffer`, which is reused for every line instead of
+/// allocating one per line, and returns it, or its lossy decoding if it isn't valid UTF-8.
+///
+/// Returns `None` at the end of the file.
+fn read_line<'a>(
+    reader: &mut impl BufRead,
+    buffer: &'a mut Vec<u8>,
+    path: &str,
+    i: usize,
+) -> Option<Result<&'a str, String>> {
+    buffer.clear();
+    let read = reader
+        .read_until(b'\n', buffer)
+        .unwrap_or_else(|error| fail(Failure::Parse, format!("{path}:{}: {error}.", i + 1)));
+    if read == 0 {
+        return None;
+    }
+    if buffer.last() == Some(&b'\n') {
+        buffer.pop();
+    }
+    if buffer.last() == Some(&b'\r') {
+        buffer.pop();
+    }
+
+    Some(std::str::from_utf8(buffer).map_err(|_| String::from_utf8_lossy(buffer).into_owned()))
+}
+
+/// The most characters of a malformed line quoted in its error.
+const MAX_QUOTED: usize = 80;
+
+/// Reports the malformed lines of the inputs, which fail the run unless they're recorded as
+/// warnings in the report under `--lenient`.
+struct LineErrors<'a> {
+    lenient: Option<&'a mut RunReport>,
+}
+
+impl LineErrors<'_> {
+    fn report(&mut self, path: &str, i: usize, problem: &str, line: &str) {
+        let mut quoted = line.chars().take(MAX_QUOTED).collect::<String>();
+        if quoted.len() < line.len() {
+            quoted.push_str("...");
+        }
+        let message = format!("{path}:{}: {problem}: {quoted:?}", i + 1);
+
+        match &mut self.lenient {
+            Some(run) => run.warn(
+                Warning::MalformedLine,
+                format!("Skipped the malformed line {message}."),
+            ),
+            None => fail(Failure::Parse, format!("{message}.")),
+        }
+    }
 }
 
+/// Parses the splits of the dataset. With a `spill` directory, the records of a split are spilled
+/// into it whenever the parser moves on to a document of another split, so only the records of one
+/// split are in memory at once.
+#[allow(clippy::too_many_arguments)]
 fn parse_conll(
     path_aida: &str,
     path_conll: &str,
-) -> (
-    (Vec<TokenRecord>, Vec<TokenRecord>, Vec<TokenRecord>),
-    HashSet<String>,
-) {
-    let mut train = vec![];
-    let mut validation = vec![];
-    let mut test = vec![];
+    normalizer: &TitleNormalizer,
+    selection: Option<&DocumentSelection>,
+    limit: Option<usize>,
+    layout: &TsvLayout,
+    mut errors: LineErrors,
+    spill: Option<&Path>,
+) -> Parsed {
+    let mut splits = SPLITS.map(|name| SplitRecords::new(spill, name));
+    // The records of the documents since the last one of another split.
+    let mut records = vec![];
 
     let mut document_id = 0;
+    let mut document = 0;
     let mut document_split = Split::Train;
+    let mut documents = [0; 3];
+    let mut skip = false;
+    // The number of tokens of the current document, and the IDs of the documents without any.
+    let mut tokens = 0;
+    let mut empty = vec![];
+    let mut sentence = 0;
+    // Whether a blank line ended the last sentence of the document.
+    let mut boundary = false;
 
-    let mut titles = HashSet::new();
+    let mut titles = Interner::default();
+    let mut words = Interner::default();
+    let mut pageids = HashMap::new();
+
+    let file_aida = exit::open(path_aida);
+    let bar = progress::bytes(file_aida.metadata().unwrap().len(), "Parsing the dataset");
+    let mut reader_aida = BufReader::new(bar.wrap_read(file_aida));
+    let mut reader_conll = BufReader::new(exit::open(path_conll));
+    let mut buffer_aida = vec![];
+    let mut buffer_conll = vec![];
 
-    let reader_aida = BufReader::new(File::open(path_aida).unwrap());
-    let reader_conll = BufReader::new(File::open(path_conll).unwrap());
+    let mut lines = 0;
+    while let Some(line_aida) = read_line(&mut reader_aida, &mut buffer_aida, path_aida, lines) {
+        let i = lines;
+        lines += 1;
+        let Some(line_conll) = read_line(&mut reader_conll, &mut buffer_conll, path_conll, i)
+        else {
+            fail(
+                Failure::Parse,
+                format!("{path_conll} has fewer lines than {path_aida}."),
+            );
+        };
 
-    for (line_aida, line_conll) in reader_aida.lines().zip(reader_conll.lines()) {
-        let line_aida = line_aida.unwrap();
-        let line_conll = line_conll.unwrap();
+        // The lines of both files are skipped together, so they stay aligned.
+        let (line_aida, line_conll) = match (line_aida, line_conll) {
+            (Ok(line_aida), Ok(line_conll)) => (line_aida, line_conll),
+            (Err(line), _) => {
+                errors.report(path_aida, i, "invalid UTF-8", &line);
+                continue;
+            }
+            (_, Err(line)) => {
+                errors.report(path_conll, i, "invalid UTF-8", &line);
+                continue;
+            }
+        };
 
-        let fields_aida = line_aida.split("\t").collect::<Vec<_>>();
+        let fields_aida = Fields::new(line_aida, b'\t');
 
-        if fields_aida.len() == 1 {
-            if let Some((_, id, split)) = regex_captures!(
-                r#"-DOCSTART- \(([\d]+)(testa|testb)? [^\)\\]*(?:\\.[^\)\\]*)*\)"#,
-                &fields_aida[0]
-            ) {
-                document_id = id.parse::<u32>().unwrap();
-                document_split = match split {
-                    "testa" => Split::Validation,
-                    "testb" => Split::Test,
-                    _ => Split::Train,
-                };
+        if let Some(docstart) = parse_docstart(line_aida.trim_end()) {
+            // The tokens of a document without a valid ID are skipped with it.
+            let docstart = match docstart {
+                Ok(docstart) => docstart,
+                Err(problem) => {
+                    errors.report(path_aida, i, &problem, line_aida);
+                    skip = true;
+                    continue;
+                }
+            };
+            if document > 0 && !skip && tokens == 0 {
+                empty.push(document_id);
+            }
+            document_id = docstart.document_id;
+            document += 1;
+            tokens = 0;
+            let previous = document_split;
+            document_split = match docstart.split {
+                1 => Split::Validation,
+                2 => Split::Test,
+                _ => Split::Train,
+            };
+            if document_split != previous {
+                splits[previous as usize].append(mem::take(&mut records));
+            }
+            sentence = 0;
+            boundary = false;
 
-                continue;
+            skip = selection.is_some_and(|x| !x.contains(document_id));
+            if !skip {
+                let documents = &mut documents[document_split as usize];
+                *documents += 1;
+                skip = limit.is_some_and(|x| *documents > x);
             }
+
+            continue;
         }
 
-        let token = fields_aida[0].nfc().collect::<String>();
+        if skip {
+            continue;
+        }
 
-        let split = match document_split {
-            Split::Train => &mut train,
-            Split::Validation => &mut validation,
-            Split::Test => &mut test,
-        };
+        // The blank lines separate the sentences; they're only kept as boundaries.
+        if line_aida.trim().is_empty() {
+            boundary = true;
+            continue;
+        }
 
-        let fields_conll = line_conll.split(" ").collect::<Vec<_>>();
-        let tag = fields_conll.last().unwrap();
+        let fields_conll = Fields::new(line_conll, b' ');
+        if let Err(problem) = layout.check(&fields_aida) {
+            errors.report(path_aida, i, &problem, line_aida);
+            continue;
+        }
+        if fields_conll.count() != 4 {
+            errors.report(
+                path_conll,
+                i,
+                &format!(
+                    "expected 4 space-separated fields, found {}",
+                    fields_conll.count()
+                ),
+                line_conll,
+            );
+            continue;
+        }
 
-        let tag = match tag {
-            &"B-PER" | &"I-PER" => "PER",
-            &"B-LOC" | &"I-LOC" => "LOC",
-            &"B-ORG" | &"I-ORG" => "ORG",
-            &"B-MISC" | &"I-MISC" => "MISC",
-            _ => "",
+        tokens += 1;
+
+        if boundary {
+            if records.last().is_some_and(|x| x.document == document) {
+                sentence += 1;
+            }
+            boundary = false;
         }
-        .to_owned();
 
-        if fields_aida.len() == 4 {
-            split.push(TokenRecord {
+        // The token outlives the line buffer it's borrowed from, so it's copied out of it, but
+        // only the first time its text is seen.
+        let token = words.intern(&normalizer.normalize_text(layout.token(&fields_aida)));
+
+        let tag = match fields_conll.last() {
+            "B-PER" | "I-PER" => "PER",
+            "B-LOC" | "I-LOC" => "LOC",
+            "B-ORG" | "I-ORG" => "ORG",
+            "B-MISC" | "I-MISC" => "MISC",
+            _ => "",
+        };
+
+        let mention = layout.mention(&fields_aida);
+        let begins = mention == Some(true);
+
+        if let Some(url) = layout.url(&fields_aida) {
+            let title = titles.intern(&annotated_title(url, normalizer));
+            if let Some(pageid) = layout.pageid(&fields_aida) {
+                *pageids.entry_ref(&*title).or_default() = pageid;
+            }
+
+            records.push(TokenRecord {
                 document_id,
+                document,
+                sentence,
                 token,
-                entity: EntityType::OutOfDistribution(tag),
+                entity: EntityType::InDistribution(title, tag),
+                begins,
             });
-        } else if fields_aida.len() > 4 {
-            let title = fields_aida[4].chars().skip(29).nfc().collect::<String>();
-            split.push(TokenRecord {
+        } else if mention.is_some() {
+            records.push(TokenRecord {
                 document_id,
+                document,
+                sentence,
                 token,
-                entity: EntityType::InDistribution(title.clone(), tag),
+                entity: EntityType::OutOfDistribution(tag),
+                begins,
             });
-
-            titles.insert(title);
         } else {
-            split.push(TokenRecord {
+            records.push(TokenRecord {
                 document_id,
+                document,
+                sentence,
                 token,
                 entity: EntityType::None,
+                begins,
             });
         }
     }
 
-    ((train, validation, test), titles)
+    bar.finish_and_clear();
+    splits[document_split as usize].append(records);
+
+    if read_line(&mut reader_conll, &mut buffer_conll, path_conll, lines).is_some() {
+        fail(
+            Failure::Parse,
+            format!("{path_conll} has more lines than {path_aida}."),
+        );
+    }
+    if document > 0 && !skip && tokens == 0 {
+        empty.push(document_id);
+    }
+    if !empty.is_empty() {
+        fail(
+            Failure::Parse,
+            format!(
+                "{} documents of {path_aida} have no tokens, so their DOCSTART lines are likely followed by other ones or the file is truncated: {}.",
+                empty.len(),
+                empty.iter().join(", ")
+            ),
+        );
+    }
+
+    let titles = titles.strings().map(str::to_owned).collect();
+    (splits, titles, pageids)
+}
+
+fn iob_tag(prefix: &str, tag: &str) -> String {
+    if tag.is_empty() {
+        prefix.to_owned()
+    } else {
+        format!("{prefix}-{tag}")
+    }
 }
 
+/// Splits the records of a split into about `count` chunks of whole documents.
+fn document_chunks(mut split: Vec<TokenRecord>, count: usize) -> Vec<Vec<TokenRecord>> {
+    let size = split.len().div_ceil(count).max(1);
+    let mut chunks = vec![];
+    while split.len() > size {
+        // A chunk starts with the first document that starts in the last `size` records.
+        let mut start = split.len() - size;
+        while start > 0 && split[start - 1].document == split[start].document {
+            start -= 1;
+        }
+        if start == 0 {
+            break;
+        }
+        chunks.push(split.split_off(start));
+    }
+    chunks.push(split);
+    chunks.reverse();
+    chunks
+}
+
+/// Builds the data points of a split. The titles missing from `mapping` were already reported
+/// by the coverage check, so their mentions are kept or dropped per `--unmapped` here.
+///
+/// The documents are built on every core, in chunks of whole documents, and the random UUIDs are
+/// drawn afterwards in the order of the documents, so a seed still gives the same ones.
 fn generate_dataset(
     split: Vec<TokenRecord>,
-    mapping: &HashMap<String, (u32, Option<u32>)>,
+    mapping: &HashMap<String, Resolution>,
+    suspicious: &HashSet<u32>,
+    args: &Args,
+    rng: &mut StdRng,
+) -> Vec<DataPoint> {
+    let threads = thread::available_parallelism().map_or(1, usize::from);
+    let mut examples = thread::scope(|scope| {
+        document_chunks(split, threads)
+            .into_iter()
+            .map(|chunk| scope.spawn(move || generate_examples(chunk, mapping, suspicious, args)))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .flat_map(|x| x.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+
+    if args.uuids && !args.deterministic {
+        for example in &mut examples {
+            example.uuid = random::uuid(rng).to_string();
+        }
+    }
+
+    examples
+}
+
+/// Builds the data points of the documents in `split`, with the UUIDs of `--uuids` derived from
+/// their content under `--deterministic`, and empty ones otherwise.
+fn generate_examples(
+    split: Vec<TokenRecord>,
+    mapping: &HashMap<String, Resolution>,
+    suspicious: &HashSet<u32>,
+    args: &Args,
 ) -> Vec<DataPoint> {
     let mut examples = vec![];
 
-    for (document_id, group) in &split.into_iter().group_by(|x| x.document_id) {
+    let by_sentence = args.granularity == Granularity::Sentence;
+    let groups = split.into_iter().group_by(|x| {
+        (
+            x.document_id,
+            x.document,
+            if by_sentence { x.sentence } else { 0 },
+        )
+    });
+
+    for ((document_id, document, sentence), group) in &groups {
         let mut text = String::new();
+        // The length of the text in characters, kept up to date instead of counted over again.
+        let mut length = 0;
         let mut entities = vec![];
+        let mut document_tokens = vec![];
+        let mut iob_tags = vec![];
+        let mut token_qids = vec![];
+        let mut token_spans = vec![];
+        let mut detokenizer = Detokenizer::new(args.detokenize);
+
+        // A mention runs over the tokens of the same entity up to the next `B`, so adjacent
+        // mentions of the same entity stay apart. The entities are compared in place and moved
+        // out of the first token of every mention, instead of cloned for every token.
+        let mut group = group.peekable();
+        while let Some(first) = group.next() {
+            let mention = first.entity;
+            let mut tokens = vec![first.token];
+            while let Some(record) = group.next_if(|x| !x.begins && x.entity == mention) {
+                tokens.push(record.token);
+            }
+            let group = tokens;
 
-        for (mention, group) in &group.map(|x| (x.token, x.entity)).group_by(|x| x.clone().1) {
-            let tokens = group.map(|x| x.0).collect::<Vec<_>>().join(" ");
+            let mut spans = vec![];
+            for token in &group {
+                if detokenizer.space_before(token) {
+                    text.push(' ');
+                    length += 1;
+                }
+                let start = length;
+                text.push_str(token);
+                length += token.chars().count() as u64;
+                spans.push(TokenSpan { start, end: length });
+            }
+            let (start, end) = (spans[0].start, spans[spans.len() - 1].end);
 
-            let start = (text.chars().count() + if text.is_empty() { 0 } else { 1 }) as u32;
-            let end = (text.chars().count()
-                + if text.is_empty() { 0 } else { 1 }
-                + tokens.chars().count()) as u32;
+            let nil = |tag: &str| Entity {
+                start,
+                end,
+                tag: tag.to_owned(),
+                pageid: None,
+                qid: None,
+                title: None,
+                matched_via: None,
+                suspicious_target: false,
+                link_failed: false,
+                nested: false,
+                prior: None,
+                candidates: vec![],
+            };
+            let missing_qid = |title: &str| mapping.get(title).is_some_and(|x| x.qid.is_none());
+            let targeted = |title: &str| {
+                mapping
+                    .get(title)
+                    .and_then(|x| x.qid)
+                    .is_some_and(|x| suspicious.contains(&x))
+            };
 
             let mention = match mention {
-                EntityType::OutOfDistribution(tag) => Some(Entity {
-                    start,
-                    end,
-                    tag,
-                    pageid: None,
-                    qid: None,
-                    title: None,
-                }),
+                EntityType::OutOfDistribution(tag) => Some(nil(tag)),
+                EntityType::InDistribution(title, _) if args.strict_targets && targeted(&title) => {
+                    None
+                }
+                EntityType::InDistribution(title, _)
+                    if args.unmapped == UnmappedPolicy::Drop && !mapping.contains_key(&*title) =>
+                {
+                    None
+                }
+                EntityType::InDistribution(title, _)
+                    if args.missing_qid == MissingQidPolicy::Drop && missing_qid(&title) =>
+                {
+                    None
+                }
+                EntityType::InDistribution(title, tag)
+                    if args.missing_qid == MissingQidPolicy::Nil && missing_qid(&title) =>
+                {
+                    Some(nil(tag))
+                }
                 EntityType::InDistribution(title, tag) => {
-                    let (pageid, qid) = *mapping.get(&title).unwrap();
+                    let resolution = mapping.get(&*title);
                     Some(Entity {
                         start,
                         end,
-                        tag,
-                        pageid: Some(pageid),
-                        qid,
-                        title: Some(title),
+                        tag: tag.to_owned(),
+                        pageid: resolution.map(|x| x.pageid),
+                        qid: resolution.and_then(|x| x.qid),
+                        title: Some(title.to_string()),
+                        matched_via: resolution.map(|x| x.resolver.name().to_owned()),
+                        suspicious_target: targeted(&title),
+                        link_failed: resolution.is_none(),
+                        nested: false,
+                        prior: None,
+                        candidates: vec![],
                     })
                 }
                 EntityType::None => None,
             };
 
-            if let Some(mention) = mention {
-                entities.push(mention);
+            if args.token_spans || args.raw_texts.is_some() {
+                token_spans.extend(spans);
             }
 
-            if !text.is_empty() {
-                text.push(' ')
+            if args.token_columns {
+                for i in 0..group.len() {
+                    let iob = match &mention {
+                        Some(entity) if i == 0 => iob_tag("B", &entity.tag),
+                        Some(entity) => iob_tag("I", &entity.tag),
+                        None => "O".to_owned(),
+                    };
+                    iob_tags.push(iob);
+                    token_qids.push(mention.as_ref().and_then(|x| x.qid));
+                }
+                document_tokens.extend(group);
             }
 
-            text.push_str(&tokens);
+            if let Some(mention) = mention {
+                entities.push(mention);
+            }
         }
 
+        let uuid = if args.uuids && args.deterministic {
+            random::content_uuid(&[
+                &document_id.to_le_bytes(),
+                &document.to_le_bytes(),
+                &sentence.to_le_bytes(),
+                text.as_bytes(),
+            ])
+            .to_string()
+        } else {
+            String::new()
+        };
+
         examples.push(DataPoint {
             document_id,
+            sentence,
+            uuid,
             text,
             entities,
+            tokens: document_tokens,
+            iob_tags,
+            token_qids,
+            token_spans,
         });
     }
 
     examples
 }
 
-fn write_dataset(split: Vec<DataPoint>, path: &str) {
-    let array: Box<dyn Array> = split.try_into_arrow().unwrap();
-    let array = array
-        .as_any()
-        .downcast_ref::<arrow2::array::StructArray>()
-        .unwrap();
+/// Returns the offsets in `values` as an array of `data_type`, narrowing them into 32-bit
+/// ones, which were checked to fit, unless they're written with `--wide`.
+fn offset_array(values: impl Iterator<Item = u64>, data_type: &DataType) -> ArrayRef {
+    match data_type {
+        DataType::UInt64 => Arc::new(values.collect::<UInt64Array>()),
+        _ => Arc::new(
+            values
+                .map(|x| u32::try_from(x).unwrap())
+                .collect::<UInt32Array>(),
+        ),
+    }
+}
+
+/// Returns the QIDs in `values` as an array of `data_type`, formatted as strings with
+/// `--qid-format string`.
+fn qid_array(values: impl Iterator<Item = Option<u32>>, data_type: &DataType) -> ArrayRef {
+    match data_type {
+        DataType::Utf8 => Arc::new(
+            values
+                .map(|qid| qid.map(|qid| format!("Q{qid}")))
+                .collect::<StringArray>(),
+        ),
+        _ => Arc::new(values.collect::<UInt32Array>()),
+    }
+}
+
+/// Returns the strings in `values` as an array of `data_type`, with 64-bit offsets with
+/// `--large-types`.
+fn string_array<'a>(values: impl Iterator<Item = &'a str>, data_type: &DataType) -> ArrayRef {
+    match data_type {
+        DataType::LargeUtf8 => Arc::new(values.map(Some).collect::<LargeStringArray>()),
+        _ => Arc::new(values.map(Some).collect::<StringArray>()),
+    }
+}
 
-    let chunk = Chunk::new(vec![array.clone().boxed()]).flatten().unwrap();
+/// Returns the lists of the `lengths` consecutive items in `values` as an array of the list
+/// type of `field`, whose 32-bit offsets were checked to fit unless it's a large list.
+fn list_array(field: &Field, lengths: impl Iterator<Item = usize>, values: ArrayRef) -> ArrayRef {
+    match field.data_type() {
+        DataType::LargeList(item) => Arc::new(LargeListArray::new(
+            item.clone(),
+            OffsetBuffer::from_lengths(lengths),
+            values,
+            None,
+        )),
+        DataType::List(item) => Arc::new(ListArray::new(
+            item.clone(),
+            OffsetBuffer::from_lengths(lengths),
+            values,
+            None,
+        )),
+        _ => unreachable!(),
+    }
+}
 
-    let options = WriteOptions {
-        write_statistics: true,
-        compression: CompressionOptions::Zstd(None),
-        version: Version::V2,
-        data_pagesize_limit: None,
-    };
+/// Returns the fields of the struct of the items of the list `field`.
+fn item_fields(field: &Field) -> &datatypes::Fields {
+    match field.data_type() {
+        DataType::List(item) | DataType::LargeList(item) => match item.data_type() {
+            DataType::Struct(fields) => fields,
+            _ => unreachable!(),
+        },
+        _ => unreachable!(),
+    }
+}
 
-    let iter = vec![Ok(chunk)];
+/// Returns the type of the items of the list `field`.
+fn item_type(field: &Field) -> &DataType {
+    match field.data_type() {
+        DataType::List(item) | DataType::LargeList(item) => item.data_type(),
+        _ => unreachable!(),
+    }
+}
 
-    let schema = Schema::from(vec![
-        Field::new("document_id", DataType::UInt32, false),
-        Field::new("text", DataType::Utf8, false),
-        Field::new(
-            "entities",
-            DataType::List(Box::new(Field::new(
-                "",
-                DataType::Struct(vec![
-                    Field::new("start", DataType::UInt32, false),
-                    Field::new("end", DataType::UInt32, false),
-                    Field::new("tag", DataType::Utf8, false),
-                    Field::new("pageid", DataType::UInt32, true),
-                    Field::new("qid", DataType::UInt32, true),
-                    Field::new("title", DataType::Utf8, true),
-                ]),
-                false,
-            ))),
-            false,
-        ),
-    ]);
+/// Converts the candidates of `entities` into a list array of `field`.
+fn candidates_array(entities: &[&Entity], field: &Field) -> ArrayRef {
+    let candidates = entities
+        .iter()
+        .flat_map(|x| &x.candidates)
+        .collect::<Vec<_>>();
 
-    let encodings = schema
-        .fields
+    let columns = item_fields(field)
         .iter()
-        .map(|f| transverse(&f.data_type, |_| Encoding::Plain))
+        .map(|field| -> ArrayRef {
+            match field.name().as_str() {
+                "title" => string_array(
+                    candidates.iter().map(|x| x.title.as_str()),
+                    field.data_type(),
+                ),
+                "qid" => qid_array(candidates.iter().map(|x| x.qid), field.data_type()),
+                "prior" => Arc::new(candidates.iter().map(|x| x.prior).collect::<Float32Array>()),
+                name => unreachable!("{name}"),
+            }
+        })
         .collect();
 
-    let row_groups =
-        RowGroupIterator::try_new(iter.into_iter(), &schema, options, encodings).unwrap();
+    let values = StructArray::new(item_fields(field).clone(), columns, None);
+    list_array(
+        field,
+        entities.iter().map(|x| x.candidates.len()),
+        Arc::new(values),
+    )
+}
 
-    let file = File::create(path).unwrap();
+/// Converts the entities of `split` into a list array of `field`, with only the fields of its
+/// struct.
+fn entities_array(split: &[DataPoint], field: &Field) -> ArrayRef {
+    let entities = split.iter().flat_map(|x| &x.entities).collect::<Vec<_>>();
 
-    let mut writer = FileWriter::try_new(file, schema, options).unwrap();
+    let columns = item_fields(field)
+        .iter()
+        .map(|field| -> ArrayRef {
+            let data_type = field.data_type();
+            match field.name().as_str() {
+                "start" => offset_array(entities.iter().map(|x| x.start), data_type),
+                "end" => offset_array(entities.iter().map(|x| x.end), data_type),
+                "tag" => string_array(entities.iter().map(|x| x.tag.as_str()), data_type),
+                "pageid" => Arc::new(entities.iter().map(|x| x.pageid).collect::<UInt32Array>()),
+                "qid" => qid_array(entities.iter().map(|x| x.qid), data_type),
+                "title" => Arc::new(
+                    entities
+                        .iter()
+                        .map(|x| x.title.as_deref())
+                        .collect::<StringArray>(),
+                ),
+                "matched_via" => Arc::new(
+                    entities
+                        .iter()
+                        .map(|x| x.matched_via.as_deref())
+                        .collect::<StringArray>(),
+                ),
+                "suspicious_target" => Arc::new(
+                    entities
+                        .iter()
+                        .map(|x| Some(x.suspicious_target))
+                        .collect::<BooleanArray>(),
+                ),
+                "link_failed" => Arc::new(
+                    entities
+                        .iter()
+                        .map(|x| Some(x.link_failed))
+                        .collect::<BooleanArray>(),
+                ),
+                "nested" => Arc::new(
+                    entities
+                        .iter()
+                        .map(|x| Some(x.nested))
+                        .collect::<BooleanArray>(),
+                ),
+                "prior" => Arc::new(entities.iter().map(|x| x.prior).collect::<Float32Array>()),
+                "candidates" => candidates_array(&entities, field),
+                name => unreachable!("{name}"),
+            }
+        })
+        .collect();
 
-    for group in row_groups {
-        writer.write(group.unwrap()).unwrap();
-    }
-    writer.end(None).unwrap();
+    let values = StructArray::new(item_fields(field).clone(), columns, None);
+    list_array(
+        field,
+        split.iter().map(|x| x.entities.len()),
+        Arc::new(values),
+    )
 }
 
-fn main() {
-    let args = Args::parse();
-
-    let ((train, validation, test), titles) = parse_conll(&args.input_aida, &args.input_conll);
+/// Converts the token spans of `split` into a list array of `field`.
+fn token_spans_array(split: &[DataPoint], field: &Field) -> ArrayRef {
+    let spans = split
+        .iter()
+        .flat_map(|x| &x.token_spans)
+        .collect::<Vec<_>>();
+    let fields = item_fields(field);
 
-    let mut mapping = HashMap::new();
-    // Corrections.
-    mapping.insert(
-        "International_cricketers_of_South_African_origin".to_owned(),
-        (17416221, Some(258)),
-    );
-    mapping.insert("Independence_Day_(film)".to_owned(), (52389, Some(105387)));
-    mapping.insert(
-        "Camelot,_Chesapeake,_Virginia".to_owned(),
-        (91342, Some(49222)),
+    let values = StructArray::new(
+        fields.clone(),
+        vec![
+            offset_array(spans.iter().map(|x| x.start), fields[0].data_type()),
+            offset_array(spans.iter().map(|x| x.end), fields[1].data_type()),
+        ],
+        None,
     );
-    mapping.insert("SBC_Communications".to_owned(), (26213969, Some(444015)));
-    mapping.insert("Superman_(film)".to_owned(), (28381, Some(79015)));
-    mapping.insert("Rabobank_(cycling_team)".to_owned(), (2354465, Some(6233)));
-    mapping.insert("U._Chandana".to_owned(), (896434, Some(3520028)));
-    mapping.insert("LPGA_Championship".to_owned(), (229059, Some(281917)));
-    mapping.insert(
-        "Hapoel_Be'er_Sheva_A.F.C.".to_owned(),
-        (5834903, Some(986529)),
-    );
-    let reader = File::open(&args.input_wiki2qid).unwrap();
-    for record in Reader::new(reader).unwrap() {
-        let record = from_value::<MappingRecord>(&record.unwrap()).unwrap();
+    list_array(
+        field,
+        split.iter().map(|x| x.token_spans.len()),
+        Arc::new(values),
+    )
+}
 
-        if titles.contains(&record.title) {
-            mapping
-                .try_insert(record.title, (record.pageid, record.qid))
-                .ok();
-        }
+/// Returns the schema of the splits, with the columns, and the fields of the entities, that are
+/// written with `args`.
+fn dataset_schema(args: &Args) -> SchemaRef {
+    let qid_type = match args.qid_format {
+        QidFormat::U32 => DataType::UInt32,
+        QidFormat::String => DataType::Utf8,
+    };
+
+    let offset_type = if args.wide {
+        DataType::UInt64
+    } else {
+        DataType::UInt32
+    };
+    let span_fields = vec![
+        Field::new("start", offset_type.clone(), false),
+        Field::new("end", offset_type, false),
+    ];
+
+    let mut entity_fields = span_fields.clone();
+    entity_fields.extend([
+        Field::new("tag", DataType::Utf8, false),
+        Field::new("pageid", DataType::UInt32, true),
+        Field::new("qid", qid_type.clone(), true),
+        Field::new("title", DataType::Utf8, true),
+    ]);
+    if args.provenance || args.fuzzy {
+        entity_fields.push(Field::new("matched_via", DataType::Utf8, true));
+    }
+    if args.target_qids.is_some() && !args.strict_targets {
+        entity_fields.push(Field::new("suspicious_target", DataType::Boolean, false));
+    }
+    if args.unmapped == UnmappedPolicy::Nil {
+        entity_fields.push(Field::new("link_failed", DataType::Boolean, false));
+    }
+    if args.overlaps == OverlapPolicy::Nested {
+        entity_fields.push(Field::new("nested", DataType::Boolean, false));
+    }
+    if args.anchors.is_some() {
+        let candidate_field = Field::new(
+            "",
+            DataType::Struct(
+                vec![
+                    Field::new("title", DataType::Utf8, false),
+                    Field::new("qid", qid_type.clone(), true),
+                    Field::new("prior", DataType::Float32, false),
+                ]
+                .into(),
+            ),
+            false,
+        );
+        entity_fields.push(Field::new("prior", DataType::Float32, true));
+        entity_fields.push(Field::new(
+            "candidates",
+            DataType::List(FieldRef::new(candidate_field)),
+            false,
+        ));
     }
+    // The items of the lists are unnamed, as they were in the files written with arrow2.
+    let entity_field = FieldRef::new(Field::new(
+        "",
+        DataType::Struct(entity_fields.into()),
+        false,
+    ));
 
-    let train = generate_dataset(train, &mapping);
-    let validation = generate_dataset(validation, &mapping);
-    let test = generate_dataset(test, &mapping);
+    let (text_type, entities_type) = if args.large_types {
+        (DataType::LargeUtf8, DataType::LargeList(entity_field))
+    } else {
+        (DataType::Utf8, DataType::List(entity_field))
+    };
 
-    write_dataset(
-        train,
-        Path::new(&args.output_dir)
-            .join("train.parquet")
-            .to_str()
-            .unwrap(),
-    );
-    write_dataset(
-        validation,
-        Path::new(&args.output_dir)
-            .join("validation.parquet")
-            .to_str()
-            .unwrap(),
-    );
-    write_dataset(
-        test,
-        Path::new(&args.output_dir)
-            .join("test.parquet")
-            .to_str()
-            .unwrap(),
-    );
+    let mut fields = vec![
+        Field::new("document_id", DataType::UInt32, false),
+        Field::new("text", text_type, false),
+        Field::new("entities", entities_type, false),
+    ];
+
+    if args.uuids {
+        fields.insert(1, Field::new("uuid", DataType::Utf8, false));
+    }
+
+    if args.granularity == Granularity::Sentence {
+        fields.insert(1, Field::new("sentence", DataType::UInt32, false));
+    }
+
+    if args.token_columns {
+        let string_list = DataType::List(FieldRef::new(Field::new("", DataType::Utf8, false)));
+        fields.push(Field::new("tokens", string_list.clone(), false));
+        fields.push(Field::new("iob_tags", string_list, false));
+        fields.push(Field::new(
+            "token_qids",
+            DataType::List(FieldRef::new(Field::new("", qid_type, true))),
+            false,
+        ));
+    }
+
+    if args.token_spans {
+        let span_field = Field::new("", DataType::Struct(span_fields.into()), false);
+        fields.push(Field::new(
+            "token_spans",
+            DataType::List(FieldRef::new(span_field)),
+            false,
+        ));
+    }
+
+    Arc::new(Schema::new(fields))
+}
+
+/// Splits `split` into row groups of `size` rows, which are only moved out of it as they're taken.
+fn split_row_groups(split: Vec<DataPoint>, size: usize) -> impl Iterator<Item = Vec<DataPoint>> {
+    let mut split = split.into_iter();
+    iter::from_fn(move || Some(split.by_ref().take(size).collect::<Vec<_>>()))
+        .take_while(|x| !x.is_empty())
+}
+
+/// Returns why the 32-bit offsets can't hold the `rows` of the split `name`, if they can't: the
+/// offsets of the entities can't hold a text without `--wide`, or the offsets of the columns can't
+/// hold the texts or the entities of a row group without `--large-types`.
+fn offsets_problem(name: &str, rows: &[DataPoint], args: &Args) -> Option<String> {
+    if !args.wide {
+        let longest = rows
+            .iter()
+            .map(|x| x.text.chars().count())
+            .max()
+            .unwrap_or_default();
+        if longest > u32::MAX as usize {
+            return Some(format!(
+                "The longest text of the {name} split has {longest} characters, more than the 32-bit offsets can hold; use --wide to write 64-bit offsets."
+            ));
+        }
+    }
+
+    if !args.large_types {
+        // The texts and entities of a row group share the 32-bit offsets of its columns.
+        let overflows = rows.chunks(args.row_group_size as usize).any(|x| {
+            x.iter().map(|x| x.text.len()).sum::<usize>() > i32::MAX as usize
+                || x.iter().map(|x| x.entities.len()).sum::<usize>() > i32::MAX as usize
+        });
+        if overflows {
+            return Some(format!(
+                "A row group of the {name} split has more text or entities than the 32-bit offsets of their columns can hold; use --large-types or a smaller --row-group-size."
+            ));
+        }
+    }
+
+    None
+}
+
+/// Writes the `row_groups` of the split `name` into its staged output on another thread, and
+/// returns the thread, unless its file isn't written.
+fn write_split<'scope>(
+    scope: &'scope thread::Scope<'scope, '_>,
+    name: &str,
+    row_groups: impl Iterator<Item = Vec<DataPoint>> + Send + 'scope,
+    outputs: &mut Outputs,
+    metadata: &[KeyValue],
+    args: &'scope Args,
+    bar: &'scope ProgressBar,
+) -> Option<ScopedJoinHandle<'scope, ()>> {
+    let Some(output) = outputs.stage_split(name) else {
+        bar.inc(1);
+        return None;
+    };
+
+    let mut metadata = metadata.to_vec();
+    metadata.push(KeyValue {
+        key: "split".to_owned(),
+        value: Some(name.to_owned()),
+    });
+    Some(scope.spawn(move || {
+        write_dataset(row_groups, &output, &metadata, args);
+        bar.inc(1);
+    }))
+}
+
+/// Writes the `row_groups` of a split into `output`, which are converted into arrays only as
+/// they're written.
+fn write_dataset(
+    row_groups: impl Iterator<Item = Vec<DataPoint>>,
+    output: &SplitOutput,
+    metadata: &[KeyValue],
+    args: &Args,
+) {
+    let schema = dataset_schema(args);
+
+    // An empty split is still written as an empty row group.
+    let mut row_groups = row_groups.peekable();
+    let empty = row_groups.peek().is_none();
+    let batches = row_groups
+        .chain(empty.then(Vec::new))
+        .map(|rows| dataset_batch(&rows, &schema));
+
+    match output {
+        SplitOutput::File(path) => write_batches(batches, schema.clone(), path, metadata),
+        SplitOutput::Store(store, name) => {
+            let result =
+                store.write_parquet(name, batches, schema.clone(), writer_properties(metadata));
+            if let Err(error) = result {
+                fail(
+                    Failure::Write,
+                    format!("Couldn't write {}: {error}.", store.url(name)),
+                );
+            }
+        }
+    }
+}
+
+/// Converts the data points of `split` into a record batch of the columns of `schema`.
+fn dataset_batch(split: &[DataPoint], schema: &SchemaRef) -> RecordBatch {
+    let columns = schema
+        .fields()
+        .iter()
+        .map(|field| -> ArrayRef {
+            let data_type = field.data_type();
+            match field.name().as_str() {
+                "document_id" => {
+                    Arc::new(split.iter().map(|x| x.document_id).collect::<UInt32Array>())
+                }
+                "sentence" => Arc::new(split.iter().map(|x| x.sentence).collect::<UInt32Array>()),
+                "uuid" => string_array(split.iter().map(|x| x.uuid.as_str()), data_type),
+                "text" => string_array(split.iter().map(|x| x.text.as_str()), data_type),
+                "entities" => entities_array(split, field),
+                "tokens" => list_array(
+                    field,
+                    split.iter().map(|x| x.tokens.len()),
+                    string_array(
+                        split.iter().flat_map(|x| &x.tokens).map(|x| &**x),
+                        item_type(field),
+                    ),
+                ),
+                "iob_tags" => list_array(
+                    field,
+                    split.iter().map(|x| x.iob_tags.len()),
+                    string_array(
+                        split.iter().flat_map(|x| &x.iob_tags).map(|x| x.as_str()),
+                        item_type(field),
+                    ),
+                ),
+                "token_qids" => list_array(
+                    field,
+                    split.iter().map(|x| x.token_qids.len()),
+                    qid_array(
+                        split.iter().flat_map(|x| &x.token_qids).copied(),
+                        item_type(field),
+                    ),
+                ),
+                "token_spans" => token_spans_array(split, field),
+                name => unreachable!("{name}"),
+            }
+        })
+        .collect();
+
+    RecordBatch::try_new(schema.clone(), columns).unwrap()
+}
+
+/// Writes a single batch into a zstd compressed Parquet file with its schema and the key-value metadata.
+fn write_batch(batch: RecordBatch, path: &str, metadata: &[KeyValue]) {
+    let schema = batch.schema();
+    write_batches(iter::once(batch), schema, path, metadata);
+}
+
+/// Writes every batch as a row group of a zstd compressed Parquet file, like [`write_batch`].
+fn write_batches(
+    batches: impl Iterator<Item = RecordBatch>,
+    schema: SchemaRef,
+    path: &str,
+    metadata: &[KeyValue],
+) {
+    let file = exit::create(path);
+
+    let result = (|| -> Result<(), ParquetError> {
+        let mut writer = parquet_writer(file, &schema, metadata)?;
+        let factory = ArrowRowGroupWriterFactory::new(&writer, schema);
+        for (index, batch) in batches.enumerate() {
+            let columns = encode_row_group(&batch, writer.schema_descr(), &factory, index)?;
+            let mut row_group = writer.next_row_group()?;
+            for column in columns {
+                column.append_to_row_group(&mut row_group)?;
+            }
+            row_group.close()?;
+        }
+        writer.close()?;
+        Ok(())
+    })();
+    if let Err(error) = result {
+        fail(Failure::Write, format!("Couldn't write {path}: {error}."));
+    }
+}
+
+/// Returns the properties of the written Parquet files: plain encoded, zstd compressed V2 data
+/// pages, and the key-value metadata.
+fn writer_properties(metadata: &[KeyValue]) -> WriterProperties {
+    WriterProperties::builder()
+        .set_writer_version(WriterVersion::PARQUET_2_0)
+        // The default level of zstd, which the files written with arrow2 were compressed with.
+        .set_compression(Compression::ZSTD(ZstdLevel::try_new(3).unwrap()))
+        .set_dictionary_enabled(false)
+        .set_encoding(Encoding::PLAIN)
+        .set_key_value_metadata(Some(metadata.to_vec()))
+        .build()
+}
+
+/// Returns a writer of a Parquet file of `schema` into `file`, with the [`writer_properties`]
+/// and the Arrow schema in the key-value metadata.
+fn parquet_writer<W: std::io::Write + Send>(
+    file: W,
+    schema: &Schema,
+    metadata: &[KeyValue],
+) -> Result<SerializedFileWriter<W>, ParquetError> {
+    let mut properties = writer_properties(metadata);
+    add_encoded_arrow_schema_to_metadata(schema, &mut properties);
+
+    // The root is named like in the files written with arrow2.
+    let parquet_schema = ArrowSchemaConverter::new()
+        .schema_root("root")
+        .convert(schema)?;
+    SerializedFileWriter::new(file, parquet_schema.root_schema_ptr(), Arc::new(properties))
+}
+
+fn main() {
+    let cli = Cli::parse_from(expand_config(
+        &Cli::command(),
+        std::env::args_os().collect(),
+    ));
+    progress::set_quiet(cli.quiet);
+
+    match cli.command {
+        Command::Corrections {
+            command: CorrectionsCommand::List { corrections },
+        } => list_corrections(
+            corrections.as_deref(),
+            &TitleNormalizer::new(&[], UnicodeForm::Nfc),
+        ),
+        Command::ValidateMapping { input_wiki2qid } => {
+            if !validate_mapping(&input_wiki2qid) {
+                exit(Failure::Validation);
+            }
+        }
+        Command::BuildMapping {
+            input_aida,
+            from_api,
+            output,
+            cache_dir,
+            requests_per_second,
+            max_retries,
+            title_normalization,
+            normalize,
+            tsv_profile,
+            tsv_columns,
+            nil_sentinels,
+        } => {
+            if !from_api {
+                fail(
+                    Failure::Usage,
+                    "The mappings can currently only be built with --from-api.",
+                );
+            }
+            build_mapping(
+                &input_aida,
+                &output,
+                &cache_dir,
+                HttpClient::new(&cache_dir, requests_per_second, max_retries),
+                &TitleNormalizer::new(&title_normalization, normalize),
+                &TsvLayout::new(tsv_profile, tsv_columns, nil_sentinels),
+            );
+        }
+        Command::ExtractKb {
+            input_dir,
+            input_wikidata,
+            output,
+            properties,
+        } => extract_kb(&input_dir, &input_wikidata, &output, &properties),
+        Command::Doctor {
+            input_aida,
+            input_conll,
+            input_wiki2qid,
+            output_dir,
+        } => {
+            if !doctor(&input_aida, &input_conll, &input_wiki2qid, &output_dir) {
+                exit(Failure::Validation);
+            }
+        }
+        Command::Bench {
+            runs,
+            label,
+            history,
+            args,
+        } => bench(&args, runs as usize, label.as_deref(), &history),
+        Command::VerifyRoundtrip {
+            input_aida,
+            input_conll,
+            args,
+        } => {
+            if !verify_roundtrip(&input_aida, &input_conll, &args) {
+                exit(Failure::Validation);
+            }
+        }
+        Command::Completions { shell } => clap_complete::generate(
+            shell,
+            &mut Cli::command(),
+            env!("CARGO_BIN_NAME"),
+            &mut std::io::stdout(),
+        ),
+        Command::Manpage => clap_mangen::Man::new(Cli::command())
+            .render(&mut std::io::stdout())
+            .unwrap(),
+        Command::Convert(args) if args.watch => watch(&args.input_files()),
+        Command::Convert(args) => convert(*args, cli.seed),
+        Command::Stats { input } => print_stats(&input),
+        Command::Inspect { input, document_id } => inspect(&input, document_id),
+        Command::Sample {
+            input,
+            n,
+            split,
+            mentions,
+        } => print_sample(&input, n, split.as_deref(), cli.seed, mentions),
+        Command::Grep {
+            input,
+            qid,
+            title,
+            surface,
+        } => {
+            if !grep(
+                &input,
+                &Query {
+                    qid,
+                    title,
+                    surface,
+                },
+            ) {
+                exit(Failure::Other);
+            }
+        }
+        Command::Diff { dir_a, dir_b } => {
+            if !diff_datasets(&dir_a, &dir_b) {
+                exit(Failure::Other);
+            }
+        }
+        Command::Merge { inputs, output_dir } => {
+            let sources = inputs.iter().map(|x| Source::parse(x)).collect::<Vec<_>>();
+            merge_datasets(&sources, &output_dir);
+        }
+        Command::Export {
+            input,
+            from,
+            to,
+            output_dir,
+            fix_offsets,
+        } => export(&input, from, to, &output_dir, fix_offsets),
+        Command::Fetch {
+            cache_dir,
+            aida_url,
+            aida_sha256,
+            mapping_url,
+            mapping_sha256,
+            no_verify,
+        } => {
+            let aida = Download {
+                url: aida_url,
+                sha256: aida_sha256,
+            };
+            let mapping = mapping_url.map(|url| Download {
+                url,
+                sha256: mapping_sha256,
+            });
+            fetch(&cache_dir, &aida, mapping.as_ref(), !no_verify);
+        }
+        Command::Serve { input, address } => serve(&input, &address),
+        Command::Browse { input } => browse(&input),
+        Command::Report {
+            input,
+            output,
+            max_documents,
+        } => write_report(&input, &output, max_documents),
+        Command::Validate { input_dir } => {
+            if !validate_dataset(&input_dir) {
+                exit(Failure::Validation);
+            }
+        }
+    }
+}
+
+fn build_mapping(
+    input_aida: &str,
+    output: &str,
+    cache_dir: &str,
+    client: HttpClient,
+    normalizer: &TitleNormalizer,
+    layout: &TsvLayout,
+) {
+    let mut titles = read_titles(input_aida, normalizer, layout)
+        .into_iter()
+        .collect::<Vec<_>>();
+    titles.sort_unstable();
+
+    // There's no report of the run, so a corrupt cache is only reported on the standard error.
+    let mut resolver = OnlineResolver::new(cache_dir, client, &mut RunReport::new(&[]));
+    let resolved = resolver.resolve(&titles);
+
+    let entries = titles
+        .iter()
+        .filter_map(|title| {
+            let resolution = resolved.get(title)?;
+            Some(MappingEntry {
+                title: title.clone(),
+                pageid: resolution.pageid,
+                qid: resolution.qid,
+            })
+        })
+        .collect::<Vec<_>>();
+    write_mapping(output, &entries);
+
+    eprintln!(
+        "Wrote the mappings for {} of {} titles to {output}.",
+        entries.len(),
+        titles.len()
+    );
+}
+
+fn convert(args: Args, seed: Option<u64>) {
+    let normalizer = TitleNormalizer::new(&args.title_normalization, args.normalize);
+    let mut outputs = Outputs::new(&args);
+    if !outputs.check_existing() {
+        return;
+    }
+
+    let selection = match &args.documents_file {
+        Some(path) => match DocumentSelection::from_file(path) {
+            Ok(selection) => Some(selection.union(args.documents.clone().unwrap_or_default())),
+            Err(error) => fail(
+                Failure::Usage,
+                format!("Invalid document selection in {error}."),
+            ),
+        },
+        None => args.documents.clone(),
+    };
+    // The mappings are read only after the TSV files are parsed, so their schemas are
+    // checked first to not fail late on the first record.
+    check_mapping_schemas(&args.input_wiki2qid);
+
+    let mut run = RunReport::new(&args.warnings_as_errors);
+    let layout = args.tsv_layout();
+    // With `--low-memory`, the records of the splits wait on disk until their turn, except in a
+    // dry run, which writes nothing. The parse cache isn't used then, since it would read them
+    // all into memory at once.
+    let spill_dir =
+        (args.low_memory && !args.dry_run).then(|| Path::new(&args.cache_dir).join("spill"));
+    let cache = (!args.no_parse_cache && !args.low_memory).then(|| {
+        parse_cache_path(
+            &[&args.input_aida, &args.input_conll],
+            &format!("{normalizer:?} {selection:?} {:?} {layout:?}", args.limit),
+            &args.cache_dir,
+        )
+    });
+    let (splits, titles, pageids) = match cache.as_deref().and_then(read_parse_cache) {
+        Some(parsed) => parsed,
+        None => {
+            let warnings = run.warning_count();
+            let parsed = parse_conll(
+                &args.input_aida,
+                &args.input_conll,
+                &normalizer,
+                selection.as_ref(),
+                args.limit,
+                &layout,
+                LineErrors {
+                    lenient: args.lenient.then_some(&mut run),
+                },
+                spill_dir.as_deref(),
+            );
+            // The runs that skipped malformed lines aren't cached, so their warnings
+            // are never lost, and neither are dry runs, which write nothing.
+            if let Some(path) = cache
+                .as_deref()
+                .filter(|_| run.warning_count() == warnings && !args.dry_run)
+            {
+                write_parse_cache(path, &parsed, &mut run);
+            }
+            parsed
+        }
+    };
+
+    // The splits are checked one at a time, and with `--low-memory` a row group of documents at a
+    // time, so only the records of those are in memory at once.
+    let size = args.row_group_size as usize;
+    let mut duplicates = DuplicateFinder::default();
+    let mut official = OfficialCounts::default();
+    let mut problematic = vec![];
+    let mut mentions = Mentions::default();
+    let mut checked = vec![];
+    for (name, mut split) in SPLITS.into_iter().zip(splits) {
+        let mut records = SplitRecords::new(spill_dir.as_deref(), name);
+        let mut empty = true;
+        for mut split in split.documents(size) {
+            duplicates.add(name, &split);
+            if args.verify_official {
+                official.count(name, &split);
+            }
+            problematic.extend(check_tokens(
+                &mut [(name, &mut split)],
+                args.sanitize_tokens,
+            ));
+            mentions.count(&split);
+
+            empty = false;
+            records.append(split);
+        }
+
+        // A split can only be empty when the documents are selected, otherwise the suffixes of
+        // the document IDs are likely missing or the file is truncated.
+        if selection.is_none() && empty {
+            fail(
+                Failure::Parse,
+                format!(
+                    "The {name} split of {} has no documents; the IDs on its DOCSTART lines may lack the testa and testb suffixes, or the file may be truncated.",
+                    args.input_aida
+                ),
+            );
+        }
+
+        checked.push((name, records));
+    }
+    let official = official.differences();
+
+    let duplicates = duplicates.finish();
+    if !duplicates.ids.is_empty() {
+        run.warn(
+            Warning::DuplicateDocument,
+            format!(
+                "Found {} document IDs that appear more than once in a split: {}.",
+                duplicates.ids.len(),
+                duplicates
+                    .ids
+                    .iter()
+                    .map(|(id, split)| format!("{id} ({split})"))
+                    .join(", ")
+            ),
+        );
+    }
+    if !duplicates.shared.is_empty() {
+        run.warn(
+            Warning::DuplicateDocument,
+            format!(
+                "Found {} documents in more than one split: {}.",
+                duplicates.shared.len(),
+                duplicates
+                    .shared
+                    .iter()
+                    .map(|(id, splits)| format!("{id} ({})", splits.join(", ")))
+                    .join(", ")
+            ),
+        );
+    }
+    if !duplicates.texts.is_empty() {
+        run.warn(
+            Warning::DuplicateDocument,
+            format!(
+                "Found {} pairs of documents with identical texts: {}.",
+                duplicates.texts.len(),
+                duplicates
+                    .texts
+                    .iter()
+                    .map(|(a, b)| format!("{a} and {b}"))
+                    .join(", ")
+            ),
+        );
+    }
+
+    if args.sanitize_tokens && !problematic.is_empty() {
+        eprintln!("Sanitized {} tokens:", problematic.len());
+        for token in &problematic {
+            eprintln!("{token}");
+        }
+    } else if !problematic.is_empty() {
+        run.warn(
+            Warning::ProblematicToken,
+            format!(
+                "Found {} tokens with whitespace, control characters, or zero-width characters; sanitize them with --sanitize-tokens: {}.",
+                problematic.len(),
+                problematic.join(", ")
+            ),
+        );
+    }
+    run.end_stage("parsing");
+
+    let chain = load_resolver_chain(args.resolver_config.as_deref());
+    let mapping = resolve_titles(&args, &chain, &titles, &pageids, &normalizer, &mut run);
+    run.end_stage("resolution");
+
+    let suspicious = match &args.target_qids {
+        Some(path) => load_suspicious_targets(path),
+        None => HashSet::new(),
+    };
+    let mut targeted = mapping
+        .iter()
+        .filter(|(_, resolution)| resolution.qid.is_some_and(|x| suspicious.contains(&x)))
+        .map(|(title, _)| title.clone())
+        .collect::<Vec<_>>();
+    targeted.sort_unstable();
+
+    if !targeted.is_empty() {
+        if args.strict_targets {
+            eprintln!(
+                "Dropping the mentions of {} titles resolved to disambiguation or list QIDs.",
+                targeted.len()
+            );
+        } else {
+            run.warn(
+                Warning::SuspiciousTarget,
+                format!(
+                    "{} titles resolve to disambiguation or list QIDs: {}.",
+                    targeted.len(),
+                    targeted.join(", ")
+                ),
+            );
+        }
+    }
+    run.add_resolutions(&mapping);
+
+    if args.provenance {
+        if let Some(report) = outputs.path("resolutions.tsv") {
+            write_resolution_report(&mapping, &report.partial);
+        }
+    }
+
+    let suggestions = suggest_corrections(&args, &mapping, &normalizer, &mut run);
+    if !suggestions.is_empty() {
+        if let Some(output) = outputs.path("suggested_corrections.toml") {
+            write_suggestions(&suggestions, &output.partial);
+            run.warn(
+                Warning::SuggestedCorrection,
+                format!(
+                "Found QIDs for {} titles resolved to pages without one; review {} and pass it with --corrections.",
+                suggestions.len(),
+                output.path,
+            ),
+            );
+        }
+    }
+
+    if let Some(index) = outputs.path("qid2pageid.tsv") {
+        write_qid_index(&mapping, &index.partial);
+    }
+
+    let snapshot = snapshot_date(args.snapshot_date.as_deref(), &args.input_wiki2qid);
+    if let Some(report) = outputs.path("stale.tsv") {
+        let stale = write_staleness_report(&mapping, &report.partial);
+        let report = report.path;
+        if stale > 0 {
+            run.warn(
+                Warning::StaleTitle,
+                format!(
+                "{stale} titles resolved outside the mapping snapshot from {snapshot} may resolve differently in other snapshots; see {report}."
+            ),
+            );
+        }
+    }
+    let metadata = provenance_metadata(&snapshot, &args.input_wiki2qid);
+
+    let report = outputs.path("pageid_report.tsv");
+    let disagreements = write_pageid_report(
+        &mapping,
+        &pageids,
+        report.as_ref().map(|x| x.partial.as_str()),
+    );
+    if !disagreements.is_empty() {
+        run.warn(
+            Warning::PageidDisagreement,
+            format!(
+                "Found {} titles whose pageids in the mappings differ from the ones in the TSV{}.",
+                disagreements.len(),
+                match &report {
+                    Some(report) => format!("; see {}", report.path),
+                    None => format!(
+                        ": {}",
+                        disagreements
+                            .iter()
+                            .map(|(title, tsv, mapped)| format!("{title} ({tsv} vs. {mapped})"))
+                            .join(", ")
+                    ),
+                }
+            ),
+        );
+    }
+
+    // The mentions dropped with `--strict-targets` aren't counted, since they're neither linked
+    // nor unmappable.
+    let dropped = if args.strict_targets {
+        targeted.iter().cloned().collect()
+    } else {
+        HashSet::new()
+    };
+    let coverage = compute_coverage(&mentions, &mapping, &dropped);
+    let report = outputs.path("unmapped.tsv");
+    if let Some(report) = &report {
+        write_coverage_report(&coverage, &report.partial);
+    }
+    run.add_coverage(&coverage);
+
+    if !coverage.unmapped.is_empty() {
+        run.warn(
+            Warning::UnmappedTitle,
+            format!(
+                "Couldn't map {} of {} mentions ({:.2}%) with {} distinct titles{}.",
+                coverage.unmapped_mentions(),
+                coverage.mentions,
+                coverage.unmapped_percentage(),
+                coverage.unmapped.len(),
+                match &report {
+                    Some(report) => format!("; see {}", report.path),
+                    None => format!(": {}", coverage.unmapped.keys().join(", ")),
+                }
+            ),
+        );
+
+        let tolerated = args.unmapped != UnmappedPolicy::Fail
+            || ((args.max_unmapped.is_some() || args.max_unmapped_pct.is_some())
+                && args
+                    .max_unmapped
+                    .is_none_or(|x| coverage.unmapped_mentions() <= x)
+                && args
+                    .max_unmapped_pct
+                    .is_none_or(|x| coverage.unmapped_percentage() <= x));
+
+        if !tolerated {
+            eprintln!("Too many unmapped mentions; use --max-unmapped or --max-unmapped-pct to allow them, or --unmapped to drop or keep them.");
+            outputs.discard_staged();
+            if let Some(path) = &args.report_json {
+                run.fail();
+                run.write(path);
+            }
+            exit(Failure::Coverage);
+        }
+    }
+
+    if args.missing_qid == MissingQidPolicy::Fail {
+        let mut missing = titles
+            .iter()
+            .filter(|x| mapping.get(*x).is_some_and(|x| x.qid.is_none()))
+            .collect::<Vec<_>>();
+        missing.sort_unstable();
+
+        if !missing.is_empty() {
+            eprintln!(
+                "{} titles resolve to pages without a QID: {}.",
+                missing.len(),
+                missing.iter().join(", ")
+            );
+            eprintln!("Use --corrections to give them QIDs, or --missing-qid to keep, drop, or unlink their mentions.");
+            outputs.discard_staged();
+            if let Some(path) = &args.report_json {
+                run.fail();
+                run.write(path);
+            }
+            exit(Failure::Coverage);
+        }
+    }
+
+    if args.verify_qids {
+        if let Some(report) = outputs.path("qid_report.tsv") {
+            let discrepancies = verify_qids(
+                &titles,
+                &mapping,
+                &normalizer,
+                &mut args.http_client(),
+                &report.partial,
+            );
+            let message = format!(
+                "Found {discrepancies} QID discrepancies; see {}.",
+                report.path
+            );
+            if discrepancies > 0 {
+                run.warn(Warning::QidDiscrepancy, message);
+            } else {
+                eprintln!("{message}");
+            }
+        }
+    }
+
+    if let Some(sitelinks) = &args.sitelinks {
+        if let Some(report) = outputs.path("sitelink_report.tsv") {
+            let conflicts = check_sitelinks(
+                &titles,
+                &mapping,
+                sitelinks,
+                args.sitelink_site.as_deref(),
+                &normalizer,
+                &report.partial,
+            );
+            let message = format!("Found {conflicts} sitelink conflicts; see {}.", report.path);
+            if conflicts > 0 {
+                run.warn(Warning::SitelinkConflict, message);
+            } else {
+                eprintln!("{message}");
+            }
+        }
+    }
+
+    // The splits are generated, checked, and written one at a time, so only the data points of
+    // one of them are in memory at once. The spilled splits of `--low-memory` are even read back,
+    // generated, and checked a row group of documents at a time, and their row groups written as
+    // they're generated, so only a few of them are in memory. The splits are written next to their
+    // files and only moved into place once every split passed the checks, so a failing run still
+    // writes none of them.
+    //
+    // Every split is written on another thread while the next one is generated.
+    let mut rng = random::rng(seed);
+    let mut aliases = HashMap::new();
+    let bar = progress::items(SPLITS.len() as u64, "Writing the splits");
+    thread::scope(|scope| {
+        let mut writing = None;
+        for (name, mut split) in checked {
+            if args.low_memory {
+                wait_for(&mut writing);
+            }
+            let spilled = split.in_memory().is_none();
+
+            // The anchors of the mentions of a split in memory are loaded once it's generated, but
+            // the ones of a spilled split take a first pass over its records, so they're loaded
+            // before its row groups are written. The pass draws no random UUIDs from `rng`.
+            let mut priors = None;
+            if let Some(path) = args.anchors.as_ref().filter(|_| spilled) {
+                let mut surfaces = HashSet::new();
+                for records in split.documents(size) {
+                    let mut rows =
+                        generate_dataset(records, &mapping, &suspicious, &args, &mut rng.clone());
+                    if let Some(dir) = &args.raw_texts {
+                        align_raw_texts(&mut [&mut rows], dir, &normalizer);
+                    }
+                    surfaces.extend(mention_surfaces(&[&rows]));
+                }
+                priors = Some(Priors::load(
+                    path,
+                    &surfaces,
+                    &mapping,
+                    &args,
+                    &normalizer,
+                    &mut run,
+                ));
+            }
+
+            let mut misaligned = vec![];
+            let mut overlaps = vec![];
+            let mut problems = vec![];
+            let (mut documents, mut entities) = (0, 0);
+            let mut rows = vec![];
+            // The row groups of a spilled split, which are sent to the thread writing them as soon
+            // as they're full, if its file is written.
+            let mut streamed = false;
+            let mut row_groups = None;
+            for records in split.documents(size) {
+                let mut batch = generate_dataset(records, &mapping, &suspicious, &args, &mut rng);
+
+                if let Some(dir) = &args.raw_texts {
+                    misaligned.extend(align_raw_texts(&mut [&mut batch], dir, &normalizer));
+                }
+
+                if let Some(path) = &args.anchors {
+                    let priors = priors.get_or_insert_with(|| {
+                        let surfaces = mention_surfaces(&[&batch]);
+                        Priors::load(path, &surfaces, &mapping, &args, &normalizer, &mut run)
+                    });
+                    priors.add_candidates(&mut batch, &mapping, &args);
+                }
+
+                overlaps.extend(resolve_overlaps(&mut batch, args.overlaps));
+
+                // The entities are written in the order of their spans, as recorded in the metadata.
+                for document in &mut batch {
+                    document.entities.sort_by_key(|x| (x.start, x.end));
+                }
+
+                problems.extend(check_offsets(&[(name, &batch)]));
+
+                if name == "train" {
+                    count_aliases(&mut aliases, &batch);
+                }
+                run.add_split(name, &batch);
+                documents += batch.len();
+                entities += batch.iter().map(|x| x.entities.len()).sum::<usize>();
+                rows.append(&mut batch);
+
+                while spilled && rows.len() >= size {
+                    let row_group = rows.drain(..size).collect::<Vec<_>>();
+                    if let Some(problem) = offsets_problem(name, &row_group, &args) {
+                        drop(row_groups.take());
+                        wait_for(&mut writing);
+                        outputs.discard_staged();
+                        fail(Failure::Validation, problem);
+                    }
+
+                    if !streamed {
+                        streamed = true;
+                        let (sender, receiver) = mpsc::sync_channel(1);
+                        writing = write_split(
+                            scope,
+                            name,
+                            receiver.into_iter(),
+                            &mut outputs,
+                            &metadata,
+                            &args,
+                            &bar,
+                        );
+                        row_groups = writing.is_some().then_some(sender);
+                    }
+                    if let Some(row_groups) = &row_groups {
+                        row_groups.send(row_group).unwrap();
+                    }
+                }
+            }
+
+            if !misaligned.is_empty() {
+                run.warn(
+                    Warning::MisalignedRawText,
+                    format!(
+                        "Couldn't align {} documents of the {name} split with their raw texts in {}, so they keep the text joined from the tokens: {}.",
+                        misaligned.len(),
+                        args.raw_texts.as_deref().unwrap(),
+                        misaligned.iter().join(", ")
+                    ),
+                );
+            }
+
+            if !overlaps.is_empty() {
+                if args.overlaps == OverlapPolicy::Fail {
+                    drop(row_groups.take());
+                    wait_for(&mut writing);
+                    outputs.discard_staged();
+                    fail(
+                        Failure::Parse,
+                        format!(
+                            "Found {} pairs of overlapping entities in the {name} split; use --overlaps to resolve them: {}.",
+                            overlaps.len(),
+                            overlaps.join(", ")
+                        ),
+                    );
+                }
+                eprintln!(
+                    "Resolved {} pairs of overlapping entities in the {name} split with --overlaps {}.",
+                    overlaps.len(),
+                    args.overlaps.to_possible_value().unwrap().get_name()
+                );
+            }
+
+            if let Some(problem) = offsets_problem(name, &rows, &args) {
+                drop(row_groups.take());
+                wait_for(&mut writing);
+                outputs.discard_staged();
+                fail(Failure::Validation, problem);
+            }
+
+            if !problems.is_empty() {
+                for problem in &problems {
+                    eprintln!("{problem}");
+                }
+                eprintln!(
+                    "Found {} inconsistent offsets in the {name} split of the generated dataset, so the splits weren't written.",
+                    problems.len()
+                );
+                drop(row_groups.take());
+                wait_for(&mut writing);
+                outputs.discard_staged();
+                if let Some(path) = &args.report_json {
+                    run.fail();
+                    run.write(path);
+                }
+                exit(Failure::Validation);
+            }
+            run.end_stage("generation");
+
+            if args.dry_run {
+                eprintln!("{name}: {documents} documents, {entities} mentions");
+            }
+
+            if streamed {
+                if let Some(row_groups) = row_groups {
+                    if !rows.is_empty() {
+                        row_groups.send(rows).unwrap();
+                    }
+                }
+            } else {
+                // Only one split is written at a time, so at most two are in memory.
+                wait_for(&mut writing);
+                writing = write_split(
+                    scope,
+                    name,
+                    split_row_groups(rows, size),
+                    &mut outputs,
+                    &metadata,
+                    &args,
+                    &bar,
+                );
+            }
+        }
+        wait_for(&mut writing);
+    });
+    bar.finish_and_clear();
+    run.end_stage("writing");
+
+    if let Some(path) = &args.anchor_counts {
+        add_anchor_counts(&mut aliases, path);
+    }
+    if let Some(output) = outputs.path("aliases.parquet") {
+        write_aliases(aliases, &output.partial, &metadata);
+    }
+    outputs.commit_staged();
+    run.end_stage("writing");
+
+    let entries = outputs.write_manifest();
+    run.add_outputs(&entries);
+    run.end_stage("writing");
+
+    if args.dry_run {
+        eprintln!("Would write:");
+        for path in &outputs.paths {
+            eprintln!("  {path}");
+        }
+    }
+
+    if !official.is_empty() {
+        println!("split\tstatistic\tofficial\tconverted");
+        for difference in &official {
+            println!("{difference}");
+        }
+        eprintln!(
+            "The converted dataset differs from the published statistics of AIDA CoNLL-YAGO in {} numbers.",
+            official.len()
+        );
+        if let Some(path) = &args.report_json {
+            run.fail();
+            run.write(path);
+        }
+        exit(Failure::Validation);
+    }
+
+    run.check_errors(args.report_json.as_deref());
+
+    if let Some(path) = &args.report_json {
+        run.write(path);
+    }
+}
+
+/// Waits for the split being written on another thread, if any.
+fn wait_for(writing: &mut Option<ScopedJoinHandle<()>>) {
+    if let Some(writing) = writing.take() {
+        writing.join().unwrap();
+    }
+}
+
+/// Every file besides the splits the conversion can write into the output directory.
+const OUTPUT_FILES: [&str; 11] = [
+    "aliases.parquet",
+    "qid2pageid.tsv",
+    "unmapped.tsv",
+    "stale.tsv",
+    "pageid_report.tsv",
+    "resolutions.tsv",
+    "suggested_corrections.toml",
+    "qid_report.tsv",
+    "sitelink_report.tsv",
+    CHECKSUMS_FILE,
+    MANIFEST_FILE,
+];
+
+/// An output file, which is written at `partial` and only moved to `path` with
+/// [`Outputs::commit_staged`] once the conversion has passed every check.
+struct OutputPath {
+    partial: String,
+    path: String,
+}
+
+/// The files written into the output directory, which are only collected without
+/// being written in a dry run, and kept if they exist with `--skip-existing`.
+struct Outputs {
+    dir: PathBuf,
+    dry_run: bool,
+    overwrite: bool,
+    skip_existing: bool,
+    /// The names of the files of the splits, in the order of [`SPLITS`].
+    split_names: [String; 3],
+    paths: Vec<String>,
+    /// The files the outputs were written into, with the paths they're moved to.
+    staged: Vec<(String, String)>,
+    /// The object store the splits are written into, instead of the output directory.
+    store: Option<Arc<OutputStore>>,
+    /// The objects the splits were written into, with the names they're moved to.
+    staged_objects: Vec<(String, String)>,
+}
+
+/// Where a split is written.
+enum SplitOutput {
+    File(String),
+    /// The store, and the name of the object in it.
+    Store(Arc<OutputStore>, String),
+}
+
+impl Outputs {
+    fn new(args: &Args) -> Self {
+        let split_names = [&args.train_name, &args.validation_name, &args.test_name]
+            .into_iter()
+            .zip(SPLITS)
+            .map(|(name, split)| match name {
+                Some(name) => name.clone(),
+                None => args.name_template.replace("{split}", split),
+            })
+            .collect::<Vec<_>>();
+
+        if !split_names.iter().all_unique() {
+            fail(
+                Failure::Usage,
+                format!(
+                    "The splits would be written into the same file {}; pass a --name-template with {{split}} or distinct names.",
+                    split_names.iter().duplicates().join(", ")
+                ),
+            );
+        }
+        if let Some(name) = split_names
+            .iter()
+            .find(|x| OUTPUT_FILES.contains(&x.as_str()))
+        {
+            fail(
+                Failure::Usage,
+                format!(
+                    "A split can't be named {name}, which is another output of the conversion."
+                ),
+            );
+        }
+
+        Self {
+            dir: PathBuf::from(&args.output_dir),
+            dry_run: args.dry_run,
+            overwrite: args.overwrite,
+            skip_existing: args.skip_existing,
+            split_names: split_names.try_into().unwrap(),
+            paths: vec![],
+            staged: vec![],
+            store: args
+                .output_url
+                .as_deref()
+                .map(|url| Arc::new(OutputStore::new(url))),
+            staged_objects: vec![],
+        }
+    }
+
+    /// Exits if the output directory already has any of the outputs and they may neither
+    /// be overwritten nor skipped. Returns whether there's anything left to write.
+    fn check_existing(&self) -> bool {
+        let existing = self.existing();
+
+        if !existing.is_empty() && !self.overwrite && !self.skip_existing {
+            eprintln!(
+                "{} already contains {}; pass --overwrite to replace them or --skip-existing to keep them.",
+                self.location(),
+                existing.join(", ")
+            );
+            exit(Failure::Write);
+        }
+
+        if self.skip_existing
+            && self
+                .split_names
+                .iter()
+                .all(|name| existing.contains(&name.as_str()))
+        {
+            eprintln!(
+                "Every split already exists in {}; there's nothing to do.",
+                self.location()
+            );
+            return false;
+        }
+
+        true
+    }
+
+    /// Writes the manifest of every output in the output directory, including the ones kept with
+    /// `--skip-existing`, so it's rewritten even if it exists. Returns the entries of the manifest.
+    fn write_manifest(&mut self) -> Vec<ManifestEntry> {
+        let manifest = [CHECKSUMS_FILE, MANIFEST_FILE];
+        self.paths
+            .extend(manifest.map(|x| self.dir.join(x).to_string_lossy().into_owned()));
+        if self.dry_run {
+            return vec![];
+        }
+
+        // The splits written into an object store aren't in the manifest.
+        let names = self
+            .existing()
+            .into_iter()
+            .filter(|x| !manifest.contains(x) && self.dir.join(x).exists())
+            .collect::<Vec<_>>();
+        let entries = manifest_entries(&self.dir, &names);
+        write_manifest(&self.dir, &entries);
+        entries
+    }
+
+    /// Returns the names of the outputs in the output directory, or of the splits in the object
+    /// store if they're written into one.
+    fn existing(&self) -> Vec<&str> {
+        let splits = self.split_names.iter().map(String::as_str);
+        let splits = match &self.store {
+            Some(store) => splits.filter(|name| store.exists(name)).collect::<Vec<_>>(),
+            None => splits.filter(|name| self.dir.join(name).exists()).collect(),
+        };
+        splits
+            .into_iter()
+            .chain(
+                OUTPUT_FILES
+                    .into_iter()
+                    .filter(|name| self.dir.join(name).exists()),
+            )
+            .collect()
+    }
+
+    /// Returns where the outputs are written, for the messages.
+    fn location(&self) -> String {
+        match &self.store {
+            Some(store) => format!("{} or {}", self.dir.display(), store.location()),
+            None => self.dir.display().to_string(),
+        }
+    }
+
+    /// Returns the paths of the output file `name`, or `None` if it shouldn't be written.
+    ///
+    /// The file is written next to its path and only moved into place with
+    /// [`Outputs::commit_staged`], so a failing run leaves no outputs behind, and keeps the
+    /// ones of an earlier run.
+    fn path(&mut self, name: &str) -> Option<OutputPath> {
+        let path = self.dir.join(name);
+        if self.skip_existing && path.exists() {
+            eprintln!("Keeping {}, which already exists.", path.display());
+            return None;
+        }
+
+        let path = path.to_string_lossy().into_owned();
+        self.paths.push(path.clone());
+        if self.dry_run {
+            return None;
+        }
+
+        let partial = format!("{path}.partial");
+        self.staged.push((partial.clone(), path.clone()));
+        Some(OutputPath { partial, path })
+    }
+
+    /// Returns where `split` is written, like [`Outputs::path`], either in the output directory
+    /// or in the object store.
+    fn stage_split(&mut self, split: &str) -> Option<SplitOutput> {
+        let index = SPLITS.iter().position(|x| *x == split).unwrap();
+        let name = self.split_names[index].clone();
+        let Some(store) = self.store.clone() else {
+            return Some(SplitOutput::File(self.path(&name)?.partial));
+        };
+
+        let url = store.url(&name);
+        if self.skip_existing && store.exists(&name) {
+            eprintln!("Keeping {url}, which already exists.");
+            return None;
+        }
+
+        self.paths.push(url);
+        if self.dry_run {
+            return None;
+        }
+
+        let partial = format!("{name}.partial");
+        self.staged_objects.push((partial.clone(), name));
+        Some(SplitOutput::Store(store, partial))
+    }
+
+    /// Moves the written outputs into place.
+    fn commit_staged(&mut self) {
+        for (staged, path) in self.staged.drain(..) {
+            if let Err(error) = fs::rename(&staged, &path) {
+                fail(
+                    Failure::Write,
+                    format!("Couldn't move {staged} to {path}: {error}."),
+                );
+            }
+        }
+        if let Some(store) = &self.store {
+            for (staged, name) in self.staged_objects.drain(..) {
+                store.rename(&staged, &name);
+            }
+        }
+    }
+
+    /// Removes the outputs written so far, when the conversion fails.
+    fn discard_staged(&mut self) {
+        for (staged, _) in self.staged.drain(..) {
+            let _ = fs::remove_file(staged);
+        }
+        if let Some(store) = &self.store {
+            for (staged, _) in self.staged_objects.drain(..) {
+                store.delete(&staged);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mapping::Resolver;
+
+    /// Parses the options of `convert`, with placeholder inputs and `extra`.
+    fn args(extra: &[&str]) -> Args {
+        let mut argv = vec![
+            "aida-conll-yago-wikidata",
+            "convert",
+            "--input-aida",
+            "aida.tsv",
+            "--input-conll",
+            "conll.tsv",
+            "--input-wiki2qid",
+            "mapping.avro",
+            "--output-dir",
+            "out",
+        ];
+        argv.extend(extra);
+        match Cli::parse_from(argv).command {
+            Command::Convert(args) => *args,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Returns the records of the tokens of one document, each with a title or `None` and
+    /// whether it begins a mention.
+    fn records(tokens: &[(&str, Option<&str>, bool)]) -> Vec<TokenRecord> {
+        tokens
+            .iter()
+            .map(|(token, title, begins)| TokenRecord {
+                document_id: 1,
+                document: 1,
+                sentence: 0,
+                token: Arc::from(*token),
+                entity: match title {
+                    Some(title) => EntityType::InDistribution(Arc::from(*title), "LOC"),
+                    None => EntityType::None,
+                },
+                begins: *begins,
+            })
+            .collect()
+    }
+
+    fn resolution(qid: u32) -> Resolution {
+        Resolution {
+            pageid: qid,
+            qid: Some(qid),
+            resolver: Resolver::Mapping,
+        }
+    }
+
+    #[test]
+    fn strict_targets_drop_the_mentions_but_not_their_resolutions() {
+        let split = records(&[
+            ("Paris", Some("Paris"), true),
+            ("and", None, false),
+            ("Mercury", Some("Mercury"), true),
+        ]);
+        let mapping = HashMap::from([
+            ("Paris".to_owned(), resolution(90)),
+            ("Mercury".to_owned(), resolution(308)),
+        ]);
+        let suspicious = HashSet::from([308]);
+        let args = args(&["--target-qids", "targets.txt", "--strict-targets"]);
+
+        let dropped = HashSet::from(["Mercury".to_owned()]);
+        let mut mentions = Mentions::default();
+        mentions.count(&split);
+        let coverage = compute_coverage(&mentions, &mapping, &dropped);
+        assert_eq!(coverage.mentions, 1);
+        assert!(coverage.unmapped.is_empty());
+
+        let examples = generate_examples(split, &mapping, &suspicious, &args);
+        let qids = examples[0]
+            .entities
+            .iter()
+            .map(|x| x.qid)
+            .collect::<Vec<_>>();
+        assert_eq!(qids, [Some(90)]);
+    }
+
+    #[test]
+    fn adjacent_mentions_of_the_same_entity_are_separated() {
+        let split = records(&[
+            ("Germany", Some("Germany"), true),
+            ("Germany", Some("Germany"), true),
+            ("New", Some("New_York_City"), true),
+            ("York", Some("New_York_City"), false),
+        ]);
+        let mapping = HashMap::from([
+            ("Germany".to_owned(), resolution(183)),
+            ("New_York_City".to_owned(), resolution(60)),
+        ]);
+
+        let examples = generate_examples(split, &mapping, &HashSet::new(), &args(&[]));
+        let spans = examples[0]
+            .entities
+            .iter()
+            .map(|x| (x.start, x.end, x.qid))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            spans,
+            [(0, 7, Some(183)), (8, 15, Some(183)), (16, 24, Some(60))]
+        );
+    }
+
+    #[test]
+    fn written_splits_are_read_back() {
+        let split = || {
+            records(&[
+                ("Paris", Some("Paris"), true),
+                ("and", None, false),
+                ("Mercury", Some("Mercury"), true),
+            ])
+        };
+        let mapping = HashMap::from([
+            ("Paris".to_owned(), resolution(90)),
+            ("Mercury".to_owned(), resolution(308)),
+        ]);
+        let path = std::env::temp_dir().join(format!("split-{}.parquet", std::process::id()));
+
+        for extra in [
+            &[][..],
+            &["--large-types", "--wide", "--qid-format", "string"],
+        ] {
+            let args = args(extra);
+            let examples = generate_examples(split(), &mapping, &HashSet::new(), &args);
+            write_dataset(
+                split_row_groups(examples, args.row_group_size as usize),
+                &SplitOutput::File(path.to_string_lossy().into_owned()),
+                &[],
+                &args,
+            );
+
+            let stored = dataset::read_split(&path);
+            let document = &stored.documents[0];
+            assert_eq!(document.text, "Paris and Mercury");
+            let entities = document
+                .entities
+                .iter()
+                .map(|x| (x.start, x.end, x.qid))
+                .collect::<Vec<_>>();
+            assert_eq!(entities, [(0, 5, Some(90)), (10, 17, Some(308))]);
+        }
+
+        // The layout of the files written with arrow2 is kept.
+        let metadata = parquet::file::metadata::ParquetMetaDataReader::new()
+            .parse_and_finish(&fs::File::open(&path).unwrap())
+            .unwrap();
+        let schema = metadata.file_metadata().schema_descr();
+        assert_eq!(schema.name(), "root");
+        assert_eq!(schema.column(0).path().string(), "document_id");
+        assert_eq!(schema.column(2).path().string(), "entities.list..start");
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn streamed_splits_match_the_written_ones() {
+        let dir = std::env::temp_dir().join(format!("store-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let store = Arc::new(OutputStore::new(&format!("file://{}", dir.display())));
+        let mapping = HashMap::from([("Paris".to_owned(), resolution(90))]);
+        let args = args(&["--row-group-size", "1"]);
+        let split = |tokens: &[(&str, Option<&str>, bool)]| {
+            let examples = generate_examples(records(tokens), &mapping, &HashSet::new(), &args);
+            split_row_groups(examples, 1)
+        };
+        let row_groups = |name: &str| {
+            parquet::file::metadata::ParquetMetaDataReader::new()
+                .parse_and_finish(&fs::File::open(dir.join(name)).unwrap())
+                .unwrap()
+                .row_groups()
+                .iter()
+                .map(|x| x.num_rows())
+                .collect::<Vec<_>>()
+        };
+
+        let tokens = [("Paris", Some("Paris"), true), ("Mercury", None, false)];
+        let written = dir.join("written.parquet").to_string_lossy().into_owned();
+        write_dataset(split(&tokens), &SplitOutput::File(written), &[], &args);
+        let streamed = SplitOutput::Store(store.clone(), "streamed.parquet".to_owned());
+        write_dataset(split(&tokens), &streamed, &[], &args);
+        assert_eq!(
+            row_groups("written.parquet"),
+            row_groups("streamed.parquet")
+        );
+        assert_eq!(
+            dataset::read_split(&dir.join("streamed.parquet")).documents[0].text,
+            "Paris Mercury"
+        );
+
+        // An empty split is streamed without row groups, which is read back the same.
+        write_dataset(split(&[]), &streamed, &[], &args);
+        assert!(row_groups("streamed.parquet").is_empty());
+        assert!(dataset::read_split(&dir.join("streamed.parquet"))
+            .documents
+            .is_empty());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn failing_runs_keep_the_outputs_of_earlier_runs() {
+        let dir = std::env::temp_dir().join(format!("outputs-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut args = args(&["--overwrite"]);
+        args.output_dir = dir.to_string_lossy().into_owned();
+
+        let mut outputs = Outputs::new(&args);
+        let output = outputs.path("unmapped.tsv").unwrap();
+        fs::write(&output.partial, "first").unwrap();
+        outputs.commit_staged();
+        assert_eq!(fs::read_to_string(&output.path).unwrap(), "first");
+
+        let mut outputs = Outputs::new(&args);
+        let output = outputs.path("unmapped.tsv").unwrap();
+        fs::write(&output.partial, "second").unwrap();
+        outputs.discard_staged();
+        assert_eq!(fs::read_to_string(&output.path).unwrap(), "first");
+        assert!(!Path::new(&output.partial).exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn every_warning_category_is_documented() {
+        let command = Cli::command();
+        let convert = command.find_subcommand("convert").unwrap();
+        let argument = convert
+            .get_arguments()
+            .find(|x| x.get_id() == "warnings_as_errors")
+            .unwrap();
+        let help = argument.get_help().unwrap().to_string();
+
+        for category in Warning::value_variants() {
+            let value = category.to_possible_value().unwrap();
+            let name = value.get_name();
+            assert!(
+                help.contains(&format!("`{name}`")),
+                "{name} isn't documented"
+            );
+        }
+    }
 }
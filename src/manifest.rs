@@ -0,0 +1,74 @@
+use crate::exit::{self, fail, Failure};
+use crate::http::file_checksum;
+use parquet::file::metadata::ParquetMetaDataReader;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// The file with the checksums of the outputs, in the format of `sha256sum`.
+pub const CHECKSUMS_FILE: &str = "SHA256SUMS";
+/// The file with the checksums, sizes, and row counts of the outputs.
+pub const MANIFEST_FILE: &str = "manifest.tsv";
+
+/// An output of the conversion, as listed in the manifest.
+#[derive(Debug)]
+pub struct ManifestEntry {
+    pub name: String,
+    pub sha256: String,
+    pub bytes: u64,
+    /// The number of rows of a Parquet file.
+    pub rows: Option<usize>,
+}
+
+/// Collects the checksums, sizes, and, for the Parquet files, row counts of the
+/// files `names` in `dir`.
+pub fn manifest_entries(dir: &Path, names: &[&str]) -> Vec<ManifestEntry> {
+    names
+        .iter()
+        .map(|name| {
+            let path = dir.join(name);
+            let rows = match path.extension() {
+                Some(extension) if extension == "parquet" => {
+                    let metadata = ParquetMetaDataReader::new()
+                        .parse_and_finish(&exit::open(&path))
+                        .unwrap_or_else(|error| {
+                            fail(
+                                Failure::Parse,
+                                format!("{} isn't a valid Parquet file: {error}.", path.display()),
+                            )
+                        });
+                    Some(metadata.file_metadata().num_rows() as usize)
+                }
+                _ => None,
+            };
+
+            ManifestEntry {
+                name: name.to_string(),
+                sha256: file_checksum(&path),
+                bytes: fs::metadata(&path).unwrap().len(),
+                rows,
+            }
+        })
+        .collect()
+}
+
+/// Writes the checksums into `SHA256SUMS`, which can be verified with `sha256sum -c`,
+/// and together with the sizes and row counts into `manifest.tsv`, both in `dir`.
+pub fn write_manifest(dir: &Path, entries: &[ManifestEntry]) {
+    let mut writer = BufWriter::new(exit::create(dir.join(CHECKSUMS_FILE)));
+    for entry in entries {
+        writeln!(writer, "{}  {}", entry.sha256, entry.name).unwrap();
+    }
+
+    let mut writer = BufWriter::new(exit::create(dir.join(MANIFEST_FILE)));
+    writeln!(writer, "file\tsha256\tbytes\trows").unwrap();
+    for entry in entries {
+        let rows = entry.rows.map(|x| x.to_string()).unwrap_or_default();
+        writeln!(
+            writer,
+            "{}\t{}\t{}\t{rows}",
+            entry.name, entry.sha256, entry.bytes
+        )
+        .unwrap();
+    }
+}
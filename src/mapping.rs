@@ -0,0 +1,695 @@
+use crate::avro::MappingScanner;
+use crate::exit::{self, fail, Failure};
+use crate::normalize::TitleNormalizer;
+use crate::progress;
+use crate::redirects::parse_qid;
+use crate::run_report::{RunReport, Warning};
+use apache_avro::{from_value, types::Value, Reader, Schema, Writer};
+use hashbrown::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::collections::hash_map::DefaultHasher;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+#[derive(Debug, Deserialize)]
+struct MappingRecord {
+    title: String,
+    pageid: u32,
+    qid: Option<u32>,
+}
+
+/// The source that resolved a title to its page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolver {
+    Correction,
+    Mapping,
+    Redirect,
+    Pageid,
+    Online,
+    CaseInsensitive,
+    EditDistance,
+}
+
+impl Resolver {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Resolver::Correction => "correction",
+            Resolver::Mapping => "mapping",
+            Resolver::Redirect => "redirect",
+            Resolver::Pageid => "pageid",
+            Resolver::Online => "online",
+            Resolver::CaseInsensitive => "case-insensitive",
+            Resolver::EditDistance => "edit-distance",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Resolution {
+    pub pageid: u32,
+    pub qid: Option<u32>,
+    pub resolver: Resolver,
+}
+
+/// The prefixes of the titles outside of the main namespace.
+const NAMESPACES: &[&str] = &[
+    "Talk",
+    "User",
+    "User_talk",
+    "Wikipedia",
+    "Wikipedia_talk",
+    "File",
+    "File_talk",
+    "Image",
+    "MediaWiki",
+    "MediaWiki_talk",
+    "Template",
+    "Template_talk",
+    "Help",
+    "Help_talk",
+    "Category",
+    "Category_talk",
+    "Portal",
+    "Portal_talk",
+    "Draft",
+    "Draft_talk",
+    "TimedText",
+    "TimedText_talk",
+    "Module",
+    "Module_talk",
+    "Book",
+    "Book_talk",
+    "Special",
+    "Media",
+];
+
+/// Checks whether the title belongs to the main (article) namespace.
+pub fn is_main_namespace(title: &str) -> bool {
+    match title.split_once(':') {
+        Some((prefix, _)) => !NAMESPACES.contains(&prefix.replace(' ', "_").as_str()),
+        None => true,
+    }
+}
+
+/// Checks whether the title is one of Wikipedia's list pages.
+pub fn is_list_page(title: &str) -> bool {
+    ["List_of_", "Lists_of_", "List of ", "Lists of "]
+        .iter()
+        .any(|x| title.starts_with(x))
+}
+
+/// Loads the pageids of the disambiguation pages from a TSV file with one
+/// `pageid<TAB>property` pair per line, as in Wikipedia's `page_props` table.
+pub fn load_disambiguation_pages(path: &str) -> HashSet<u32> {
+    let reader = BufReader::new(exit::open(path));
+
+    reader
+        .lines()
+        .map(|x| x.unwrap())
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let pageid = fields.next()?.trim().parse::<u32>().ok()?;
+            (fields.next()?.trim() == "disambiguation").then_some(pageid)
+        })
+        .collect()
+}
+
+/// Loads the QIDs of the pages in `pageids` from a TSV file of page properties with
+/// `pageid<TAB>wikibase_item<TAB>qid` lines, as in Wikipedia's `page_props` table.
+pub fn load_page_items(path: &str, pageids: &HashSet<u32>) -> HashMap<u32, u32> {
+    let reader = BufReader::new(exit::open(path));
+
+    reader
+        .lines()
+        .map(|x| x.unwrap())
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let pageid = fields.next()?.trim().parse::<u32>().ok()?;
+            if !pageids.contains(&pageid) || fields.next()?.trim() != "wikibase_item" {
+                return None;
+            }
+            Some((pageid, parse_qid(fields.next()?)?))
+        })
+        .collect()
+}
+
+/// Loads the QIDs of the disambiguation and list pages from a file with one QID
+/// (with or without the "Q" prefix) per line, ignoring any further columns.
+pub fn load_suspicious_targets(path: &str) -> HashSet<u32> {
+    let reader = BufReader::new(exit::open(path));
+
+    reader
+        .lines()
+        .map(|x| x.unwrap())
+        .filter_map(|line| parse_qid(line.split('\t').next()?))
+        .collect()
+}
+
+/// Calls `f` with the title as it's written in the mappings, the pageid, and the QID of every
+/// record in the mappings, skipping the titles outside of the main namespace, until `f` breaks.
+///
+/// Only the title, the pageid, and the QID of the records are read, and the titles are borrowed
+/// from the decompressed blocks of the file, since most of them are never needed.
+pub fn scan_mapping(path: &str, mut f: impl FnMut(&str, u32, Option<u32>) -> ControlFlow<()>) {
+    let reader = exit::open(path);
+    let bar = progress::bytes(
+        reader.metadata().unwrap().len(),
+        &format!("Scanning {path}"),
+    );
+    let mut scanner = open_scanner(path, BufReader::new(bar.wrap_read(reader)));
+    for i in 1.. {
+        let record = scanner
+            .next_record()
+            .unwrap_or_else(|error| fail(Failure::Parse, format!("{path}: record {i}: {error}.")));
+        let Some((title, pageid, qid)) = record else {
+            break;
+        };
+        if !is_main_namespace(title) {
+            continue;
+        }
+        if f(title, pageid, qid).is_break() {
+            break;
+        }
+    }
+    bar.finish_and_clear();
+}
+
+/// Bumped whenever the way the subset of the mappings is computed changes, invalidating old caches.
+const CACHE_VERSION: u32 = 4;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MappingEntry {
+    pub title: String,
+    pub pageid: u32,
+    pub qid: Option<u32>,
+}
+
+/// Computes the path of the cache for the subset of the mappings at `path` with the `titles` and `pageids`.
+///
+/// The mappings are identified by their path, size, and modification time rather
+/// than their contents, since hashing a full dump would cost as much as scanning it.
+fn mapping_cache_path(
+    path: &str,
+    titles: &HashSet<String>,
+    pageids: &HashSet<u32>,
+    normalizer: &TitleNormalizer,
+    cache_dir: &str,
+) -> PathBuf {
+    let metadata = fs::metadata(path).unwrap();
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|x| x.duration_since(UNIX_EPOCH).ok())
+        .map(|x| x.as_nanos())
+        .unwrap_or_default();
+
+    let mut hasher = Sha256::new();
+    hasher.update(CACHE_VERSION.to_le_bytes());
+    hasher.update(fs::canonicalize(path).unwrap().to_string_lossy().as_bytes());
+    hasher.update(metadata.len().to_le_bytes());
+    hasher.update(modified.to_le_bytes());
+    hasher.update(format!("{:?}", normalizer).as_bytes());
+
+    let mut titles = titles.iter().collect::<Vec<_>>();
+    titles.sort_unstable();
+    for title in titles {
+        hasher.update(title.as_bytes());
+        hasher.update([0]);
+    }
+
+    let mut pageids = pageids.iter().collect::<Vec<_>>();
+    pageids.sort_unstable();
+    for pageid in pageids {
+        hasher.update(pageid.to_le_bytes());
+    }
+
+    let hash = hasher
+        .finalize()
+        .iter()
+        .map(|x| format!("{x:02x}"))
+        .collect::<String>();
+
+    Path::new(cache_dir).join(format!("mapping-{hash}.json"))
+}
+
+/// Where the filtered mappings are cached, and whether the ones that aren't cached yet are
+/// written there, which they aren't in a dry run.
+#[derive(Debug, Clone, Copy)]
+pub struct MappingCache<'a> {
+    pub dir: &'a str,
+    pub writable: bool,
+}
+
+/// Returns the first entry of the mappings of every title in `titles` and of every pageid in
+/// `pageids`, in order. The scan stops as soon as all of them are found, since the later entries
+/// of the same titles and pageids are never used.
+///
+/// The result is cached in `cache` (unless it's `None`), so that subsequent runs
+/// with the same titles and mappings can skip the scan altogether. A cache that can't be
+/// written is reported to `run` and skipped.
+pub fn load_filtered_mapping(
+    path: &str,
+    titles: &HashSet<String>,
+    pageids: &HashSet<u32>,
+    normalizer: &TitleNormalizer,
+    cache: Option<MappingCache>,
+    run: &mut RunReport,
+) -> Vec<MappingEntry> {
+    let cache_path = cache.map(|x| mapping_cache_path(path, titles, pageids, normalizer, x.dir));
+
+    if let Some((file, cache_path)) = cache_path
+        .as_ref()
+        .and_then(|x| Some((File::open(x).ok()?, x)))
+    {
+        match serde_json::from_reader(BufReader::new(file)) {
+            Ok(entries) => return entries,
+            // A corrupt cache, e.g. of an older version, is rebuilt like a missing one.
+            Err(error) => run.warn(
+                Warning::UnwritableCache,
+                format!(
+                    "Couldn't read the mapping cache {}, so {path} is scanned again: {error}.",
+                    cache_path.display()
+                ),
+            ),
+        }
+    }
+
+    let mut entries = vec![];
+    let mut found_titles = HashSet::new();
+    let mut found_pageids = HashSet::new();
+    scan_mapping(path, |raw, pageid, qid| {
+        // The title is looked up as it's written first, and only normalized if it's missing and
+        // normalizing it could change it, since that's the case for virtually every record.
+        let known = titles
+            .get(raw)
+            .or_else(|| match normalizer.normalize_borrowed(raw) {
+                Cow::Owned(title) => titles.get(&title),
+                Cow::Borrowed(_) => None,
+            });
+        let new_title = known.is_some_and(|x| found_titles.insert(x));
+        let new_pageid = pageids.contains(&pageid) && found_pageids.insert(pageid);
+        if new_title || new_pageid {
+            let title = match known {
+                Some(title) => title.clone(),
+                None => normalizer.normalize(raw),
+            };
+            entries.push(MappingEntry { title, pageid, qid });
+        }
+
+        if found_titles.len() == titles.len() && found_pageids.len() == pageids.len() {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    });
+
+    if let Some(cache_path) = cache_path.filter(|_| cache.is_some_and(|x| x.writable)) {
+        if let Err(error) = write_mapping_cache(&cache_path, &entries) {
+            run.warn(
+                Warning::UnwritableCache,
+                format!(
+                    "Couldn't write the mapping cache {}, so the next run scans {path} again: {error}.",
+                    cache_path.display()
+                ),
+            );
+        }
+    }
+
+    entries
+}
+
+fn write_mapping_cache(path: &Path, entries: &[MappingEntry]) -> io::Result<()> {
+    fs::create_dir_all(path.parent().unwrap())?;
+
+    // The cache is only moved into place once it's complete, so an interrupted run, or one
+    // running concurrently, never reads a partially written cache.
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+    let result = File::create(&partial).and_then(|file| {
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer(&mut writer, entries)?;
+        writer.flush()?;
+        drop(writer);
+        fs::rename(&partial, path)
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    result
+}
+
+/// Computes the Levenshtein distance between `a` and `b`, giving up with `None`
+/// as soon as it's certain to exceed `max`.
+fn bounded_levenshtein(a: &[char], b: &[char], max: usize) -> Option<usize> {
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, x) in a.iter().enumerate() {
+        current[0] = i + 1;
+        let mut minimum = current[0];
+
+        for (j, y) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(x != y);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+            minimum = minimum.min(current[j + 1]);
+        }
+
+        if minimum > max {
+            return None;
+        }
+
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    Some(previous[b.len()]).filter(|distance| *distance <= max)
+}
+
+/// Looks for the closest match of every title in `missing` among the mappings.
+///
+/// A title that matches a record case-insensitively always wins; otherwise, the
+/// record with the smallest edit distance is taken, as long as the similarity
+/// (one minus the distance normalized by the length of the longer title) is at
+/// least `threshold`. Only records sharing the first (lowercased) character are
+/// considered for edit distance matches, which keeps the scan tractable.
+pub fn fuzzy_match(
+    path: &str,
+    missing: &[String],
+    normalizer: &TitleNormalizer,
+    threshold: f64,
+) -> HashMap<String, Resolution> {
+    let mut lowercase = HashMap::<String, Vec<&String>>::new();
+    let mut by_first = HashMap::<char, Vec<(&String, Vec<char>)>>::new();
+
+    for title in missing {
+        let lower = title.to_lowercase();
+        let chars = lower.chars().collect::<Vec<_>>();
+        if let Some(first) = chars.first() {
+            by_first
+                .entry(*first)
+                .or_default()
+                .push((title, chars.clone()));
+        }
+        lowercase.entry(lower).or_default().push(title);
+    }
+
+    let mut best = HashMap::<&String, (f64, Resolution)>::new();
+
+    scan_mapping(path, |title, pageid, qid| {
+        let lower = normalizer.normalize_borrowed(title).to_lowercase();
+
+        if let Some(titles) = lowercase.get(&lower) {
+            for title in titles {
+                let resolution = Resolution {
+                    pageid,
+                    qid,
+                    resolver: Resolver::CaseInsensitive,
+                };
+                if best.get(title).is_none_or(|x| x.0 < 1.0) {
+                    best.insert(title, (1.0, resolution));
+                }
+            }
+            return ControlFlow::Continue(());
+        }
+
+        let chars = lower.chars().collect::<Vec<_>>();
+        let Some(candidates) = chars.first().and_then(|x| by_first.get(x)) else {
+            return ControlFlow::Continue(());
+        };
+
+        for (title, candidate) in candidates {
+            let length = chars.len().max(candidate.len());
+            // The epsilon avoids losing a whole edit to floating point errors.
+            let max = ((1.0 - threshold) * length as f64 + 1e-9).floor() as usize;
+
+            let Some(distance) = bounded_levenshtein(&chars, candidate, max) else {
+                continue;
+            };
+
+            let similarity = 1.0 - distance as f64 / length as f64;
+            if similarity < threshold {
+                continue;
+            }
+
+            let resolution = Resolution {
+                pageid,
+                qid,
+                resolver: Resolver::EditDistance,
+            };
+            match best.get_mut(title) {
+                Some(x) if x.0 >= similarity => {}
+                Some(x) => *x = (similarity, resolution),
+                None => {
+                    best.insert(title, (similarity, resolution));
+                }
+            }
+        }
+
+        ControlFlow::Continue(())
+    });
+
+    best.into_iter()
+        .map(|(title, (_, resolution))| (title.clone(), resolution))
+        .collect()
+}
+
+/// The number of examples printed for every kind of problem.
+const EXAMPLES: usize = 10;
+
+type SchemaCheck = fn(&Schema) -> bool;
+
+/// Returns the problems with the schema of the mappings: every field that's missing or has
+/// the wrong type.
+pub fn check_schema(schema: &Schema) -> Vec<String> {
+    let Schema::Record { fields, .. } = schema else {
+        return vec![format!(
+            "expected a record, found {}",
+            schema.canonical_form()
+        )];
+    };
+
+    let expected: [(&str, SchemaCheck, &str); 3] = [
+        ("title", |x| matches!(x, Schema::String), "a string"),
+        (
+            "pageid",
+            |x| matches!(x, Schema::Int | Schema::Long),
+            "an int or a long",
+        ),
+        (
+            "qid",
+            |x| match x {
+                Schema::Union(union) => {
+                    union.is_nullable()
+                        && union
+                            .variants()
+                            .iter()
+                            .all(|x| matches!(x, Schema::Null | Schema::Int | Schema::Long))
+                }
+                _ => false,
+            },
+            "a nullable int or long",
+        ),
+    ];
+
+    let mut problems = vec![];
+    for (name, check, description) in expected {
+        match fields.iter().find(|x| x.name == name) {
+            Some(field) if check(&field.schema) => {}
+            Some(field) => problems.push(format!(
+                "field `{name}` should be {description}, found {}",
+                field.schema.canonical_form()
+            )),
+            None => problems.push(format!("missing field `{name}`")),
+        }
+    }
+
+    problems
+}
+
+/// Opens the Avro reader of the mappings at `path` from `reader`, exiting with the exact
+/// problems if the file isn't an Avro file or its schema doesn't match the expected one.
+fn open_reader<R: Read>(path: &str, reader: R) -> Reader<'static, R> {
+    let reader = Reader::new(reader).unwrap_or_else(|error| {
+        fail(
+            Failure::Parse,
+            format!("{path} isn't an Apache Avro file: {error}."),
+        )
+    });
+    exit_on_schema_problems(path, reader.writer_schema());
+
+    reader
+}
+
+/// Opens the [`MappingScanner`] of the mappings at `path` from `reader`, exiting like
+/// [`open_reader`].
+fn open_scanner<R: Read>(path: &str, reader: R) -> MappingScanner<R> {
+    let (scanner, schema) = MappingScanner::new(reader).unwrap_or_else(|error| {
+        fail(
+            Failure::Parse,
+            format!("{path} isn't an Apache Avro file: {error}."),
+        )
+    });
+    exit_on_schema_problems(path, &schema);
+
+    scanner
+}
+
+fn exit_on_schema_problems(path: &str, schema: &Schema) {
+    let problems = check_schema(schema);
+    if !problems.is_empty() {
+        fail(
+            Failure::Parse,
+            format!(
+                "{path} doesn't have the schema of the mappings: {}.",
+                problems.join("; ")
+            ),
+        );
+    }
+}
+
+/// Checks the schemas of the mappings at `paths` before they're scanned,
+/// exiting with the exact problems if any of them doesn't match.
+pub fn check_mapping_schemas(paths: &[String]) {
+    for path in paths {
+        open_reader(path, exit::open(path));
+    }
+}
+
+fn hash_title(title: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    title.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn print_examples(name: &str, count: usize, examples: &[String]) {
+    println!("{name}: {count}");
+    for example in examples {
+        println!("    {example}");
+    }
+    if count > examples.len() {
+        println!("    ...");
+    }
+}
+
+/// Checks the mappings at `path` and prints a summary of the problems found.
+///
+/// Returns `false` if the file doesn't conform to the expected schema, since it
+/// can't be used for the conversion at all in that case.
+pub fn validate_mapping(path: &str) -> bool {
+    let reader = Reader::new(exit::open(path)).unwrap_or_else(|error| {
+        fail(
+            Failure::Parse,
+            format!("{path} isn't an Apache Avro file: {error}."),
+        )
+    });
+
+    let schema_problems = check_schema(reader.writer_schema());
+    if !schema_problems.is_empty() {
+        println!("The schema doesn't match the expected one:");
+        for problem in &schema_problems {
+            println!("    {problem}");
+        }
+        return false;
+    }
+
+    let mut records = 0;
+    let mut malformed = (0, vec![]);
+    let mut duplicates = (0, vec![]);
+    let mut missing_qids = (0, vec![]);
+    let mut collisions = (0, vec![]);
+
+    // Hashes of the titles are kept instead of the titles themselves, which matters for full dumps.
+    let mut titles = HashSet::new();
+    let mut pageids = HashMap::new();
+
+    for record in reader {
+        records += 1;
+
+        let record = record.and_then(|x| from_value::<MappingRecord>(&x));
+        let record = match record {
+            Ok(record) => record,
+            Err(error) => {
+                malformed.0 += 1;
+                if malformed.1.len() < EXAMPLES {
+                    malformed.1.push(format!("record {records}: {error}"));
+                }
+                continue;
+            }
+        };
+
+        if !titles.insert(hash_title(&record.title)) {
+            duplicates.0 += 1;
+            if duplicates.1.len() < EXAMPLES {
+                duplicates.1.push(record.title.clone());
+            }
+        }
+
+        if record.qid.is_none() {
+            missing_qids.0 += 1;
+            if missing_qids.1.len() < EXAMPLES {
+                missing_qids.1.push(record.title.clone());
+            }
+        }
+
+        if let Some(first) = pageids.insert(record.pageid, hash_title(&record.title)) {
+            if first != hash_title(&record.title) {
+                collisions.0 += 1;
+                if collisions.1.len() < EXAMPLES {
+                    collisions
+                        .1
+                        .push(format!("{} ({})", record.pageid, record.title));
+                }
+            }
+        }
+    }
+
+    println!("Records: {records}");
+    print_examples("Malformed records", malformed.0, &malformed.1);
+    print_examples("Duplicate titles", duplicates.0, &duplicates.1);
+    print_examples("Titles without QIDs", missing_qids.0, &missing_qids.1);
+    print_examples("Pageid collisions", collisions.0, &collisions.1);
+
+    true
+}
+
+/// The schema of the mappings generated by wiki2qid.
+const MAPPING_SCHEMA: &str = r#"{
+    "type": "record",
+    "name": "Mapping",
+    "fields": [
+        {"name": "title", "type": "string"},
+        {"name": "pageid", "type": "int"},
+        {"name": "qid", "type": ["null", "int"]}
+    ]
+}"#;
+
+/// Writes the entries into an Apache Avro file in the same format as the mappings generated by wiki2qid.
+pub fn write_mapping(path: &str, entries: &[MappingEntry]) {
+    let schema = Schema::parse_str(MAPPING_SCHEMA).unwrap();
+    let mut writer = Writer::new(&schema, exit::create(path));
+
+    for entry in entries {
+        let qid = match entry.qid {
+            Some(qid) => Value::Union(1, Box::new(Value::Int(qid as i32))),
+            None => Value::Union(0, Box::new(Value::Null)),
+        };
+        let record = Value::Record(vec![
+            ("title".to_owned(), Value::String(entry.title.clone())),
+            ("pageid".to_owned(), Value::Int(entry.pageid as i32)),
+            ("qid".to_owned(), qid),
+        ]);
+        writer.append(record).unwrap();
+    }
+
+    writer.flush().unwrap();
+}
@@ -0,0 +1,227 @@
+use crate::dataset::{key_values, open_parquet, split_path, SPLITS};
+use crate::exit::{self, fail, Failure};
+use crate::provenance::{ENTITY_ORDER, SCHEMA_VERSION};
+use crate::write_batch;
+use arrow::array::{Array, ArrayRef, StringArray, UInt32Array};
+use arrow::compute::concat;
+use arrow::datatypes::{DataType, Field, FieldRef, Schema};
+use arrow::record_batch::RecordBatch;
+use parquet::file::metadata::KeyValue;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+/// A generated dataset to merge, together with the name it's identified by in the `source` column.
+#[derive(Debug, Clone)]
+pub struct Source {
+    pub name: String,
+    pub dir: String,
+}
+
+impl Source {
+    /// Parses a source given as either `NAME=DIR` or just `DIR`, in which case it's
+    /// named after the directory.
+    pub fn parse(source: &str) -> Self {
+        match source.split_once('=') {
+            Some((name, dir)) => Self {
+                name: name.to_owned(),
+                dir: dir.to_owned(),
+            },
+            None => Self {
+                name: Path::new(source)
+                    .file_name()
+                    .map(|x| x.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| source.to_owned()),
+                dir: source.to_owned(),
+            },
+        }
+    }
+}
+
+/// Reads every column of a Parquet file into a single array, together with the
+/// fields of the columns and the schema version in the metadata.
+fn read_columns(path: &Path) -> (Vec<FieldRef>, Vec<ArrayRef>, Option<String>) {
+    let builder = open_parquet(path);
+    let version = key_values(builder.metadata()).remove("schema_version");
+
+    let fields = builder.schema().fields().to_vec();
+    let batches = builder
+        .build()
+        .unwrap()
+        .map(|x| x.unwrap())
+        .collect::<Vec<_>>();
+
+    let columns = (0..fields.len())
+        .map(|i| {
+            let arrays = batches
+                .iter()
+                .map(|x| x.column(i).as_ref())
+                .collect::<Vec<_>>();
+            concat(&arrays).unwrap()
+        })
+        .collect();
+
+    (fields, columns, version)
+}
+
+/// Returns why the columns of `b` aren't compatible with the ones of `a`, or `None` if they are.
+fn incompatibility(a: &[FieldRef], b: &[FieldRef]) -> Option<String> {
+    for field in a {
+        match b.iter().find(|x| x.name() == field.name()) {
+            None => return Some(format!("no {} column", field.name())),
+            Some(other) if other.data_type() != field.data_type() => {
+                return Some(format!("different type of the {} column", field.name()))
+            }
+            Some(_) => {}
+        }
+    }
+
+    b.iter()
+        .find(|x| !a.iter().any(|y| y.name() == x.name()))
+        .map(|x| format!("an extra {} column", x.name()))
+}
+
+/// Merges the generated datasets in `sources` into `output_dir`, concatenating each split
+/// in the order of the sources.
+///
+/// Since the document IDs of different datasets can collide, the documents are renumbered,
+/// and their original IDs are kept in the `source_document_id` column next to the name of
+/// their dataset in the `source` column. The conversion of every dataset has to have been
+/// run with the same options, so that the columns are the same.
+pub fn merge_datasets(sources: &[Source], output_dir: &str) {
+    fs::create_dir_all(output_dir).unwrap();
+
+    let mut next_id: u32 = 1;
+
+    for split in SPLITS {
+        let mut schema: Option<(Vec<FieldRef>, &str)> = None;
+        let mut parts = vec![];
+
+        for source in sources {
+            let Some(path) = split_path(Path::new(&source.dir), split) else {
+                eprintln!("{} has no {split} split; skipping it.", source.dir);
+                continue;
+            };
+
+            let (fields, columns, version) = read_columns(&path);
+
+            if version.as_deref() != Some(SCHEMA_VERSION) {
+                eprintln!(
+                    "{} has schema version {}, but only version {SCHEMA_VERSION} can be merged.",
+                    path.display(),
+                    version.as_deref().unwrap_or("(none)"),
+                );
+                exit::exit(Failure::Validation);
+            }
+
+            if fields.iter().any(|x| x.name() == "source") {
+                eprintln!("{} is already a merged dataset.", source.dir);
+                exit::exit(Failure::Validation);
+            }
+
+            match &schema {
+                Some((expected, first)) => {
+                    if let Some(reason) = incompatibility(expected, &fields) {
+                        eprintln!(
+                            "The {split} split of {} isn't compatible with the one of {first}: {reason}.",
+                            source.dir
+                        );
+                        exit::exit(Failure::Validation);
+                    }
+                }
+                None => schema = Some((fields.clone(), &source.dir)),
+            }
+
+            // Orders the columns the same way as in the first dataset.
+            let expected = &schema.as_ref().unwrap().0;
+            let columns = expected
+                .iter()
+                .map(|field| {
+                    let index = fields
+                        .iter()
+                        .position(|x| x.name() == field.name())
+                        .unwrap();
+                    columns[index].clone()
+                })
+                .collect::<Vec<_>>();
+
+            parts.push((source, columns));
+        }
+
+        let Some((mut fields, _)) = schema else {
+            continue;
+        };
+
+        let id_index = fields
+            .iter()
+            .position(|x| x.name() == "document_id")
+            .unwrap();
+        let mut columns = (0..fields.len())
+            .map(|i| {
+                let arrays = parts.iter().map(|(_, x)| x[i].as_ref()).collect::<Vec<_>>();
+                concat(&arrays).unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        let Some(length) = u32::try_from(columns[id_index].len())
+            .ok()
+            .filter(|x| next_id.checked_add(*x).is_some())
+        else {
+            fail(
+                Failure::Validation,
+                format!(
+                    "The {split} splits have too many documents to be renumbered with 32-bit IDs."
+                ),
+            );
+        };
+        let source_names = parts
+            .iter()
+            .flat_map(|(source, x)| std::iter::repeat_n(source.name.as_str(), x[0].len()))
+            .collect::<Vec<_>>();
+
+        let original_ids = std::mem::replace(
+            &mut columns[id_index],
+            Arc::new(UInt32Array::from_iter_values(next_id..next_id + length)),
+        );
+        next_id += length;
+
+        fields.push(FieldRef::new(Field::new("source", DataType::Utf8, false)));
+        fields.push(FieldRef::new(Field::new(
+            "source_document_id",
+            original_ids.data_type().clone(),
+            false,
+        )));
+        columns.push(Arc::new(StringArray::from(source_names)));
+        columns.push(original_ids);
+
+        let metadata = [
+            KeyValue {
+                key: "schema_version".to_owned(),
+                value: Some(SCHEMA_VERSION.to_owned()),
+            },
+            // The documents are concatenated, so their entities keep their order.
+            KeyValue {
+                key: "entity_order".to_owned(),
+                value: Some(ENTITY_ORDER.to_owned()),
+            },
+            KeyValue {
+                key: "split".to_owned(),
+                value: Some(split.to_owned()),
+            },
+            KeyValue {
+                key: "sources".to_owned(),
+                value: Some(
+                    parts
+                        .iter()
+                        .map(|(x, _)| x.name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(","),
+                ),
+            },
+        ];
+
+        let path = Path::new(output_dir).join(format!("{split}.parquet"));
+        let batch = RecordBatch::try_new(Arc::new(Schema::new(fields)), columns).unwrap();
+        write_batch(batch, path.to_str().unwrap(), &metadata);
+    }
+}
@@ -0,0 +1,212 @@
+use clap::ValueEnum;
+use std::borrow::Cow;
+use unicode_normalization::{is_nfc_quick, is_nfkc_quick, IsNormalized, UnicodeNormalization};
+
+/// A single step of the title normalization.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TitleNormalization {
+    /// Decode percent-encoded bytes (e.g., `%27` to `'`).
+    Percent,
+    /// Decode HTML entities (e.g., `&amp;` to `&`).
+    Html,
+    /// Replace spaces with underscores.
+    Underscores,
+    /// Capitalize the first letter, as MediaWiki does.
+    Capitalize,
+    /// Apply the Unicode NFC normalization, regardless of the form given with `--normalize`.
+    Nfc,
+}
+
+/// The Unicode normalization form applied to both the tokens and the titles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum UnicodeForm {
+    Nfc,
+    /// The compatibility form, which also folds e.g. ligatures and full-width characters.
+    Nfkc,
+    /// Keep the text as it is.
+    None,
+}
+
+impl UnicodeForm {
+    /// Returns `text` in the form, which is borrowed unless normalizing it changes it.
+    pub fn apply(self, text: &str) -> Cow<'_, str> {
+        if self.is_normalized(text) {
+            return Cow::Borrowed(text);
+        }
+        match self {
+            UnicodeForm::None => Cow::Borrowed(text),
+            UnicodeForm::Nfc => Cow::Owned(text.nfc().collect()),
+            UnicodeForm::Nfkc => Cow::Owned(text.nfkc().collect()),
+        }
+    }
+
+    /// Returns whether `text` is certainly in the form already, which is much cheaper to check
+    /// than normalizing it: virtually every token and title is ASCII, which is in every form,
+    /// and the quick check of Unicode settles most of the rest.
+    pub fn is_normalized(self, text: &str) -> bool {
+        text.is_ascii()
+            || match self {
+                UnicodeForm::None => true,
+                UnicodeForm::Nfc => is_nfc_quick(text.chars()) == IsNormalized::Yes,
+                UnicodeForm::Nfkc => is_nfkc_quick(text.chars()) == IsNormalized::Yes,
+            }
+    }
+}
+
+/// Normalizes Wikipedia's titles so that the same page is always spelled the same
+/// way, no matter whether the title came from the TSV, the mapping, or elsewhere.
+///
+/// The steps are always applied in the order they're declared in
+/// [`TitleNormalization`], regardless of the order they were given in, followed
+/// by the Unicode normalization `form`, which the tokens are normalized with too.
+#[derive(Debug, Clone)]
+pub struct TitleNormalizer {
+    steps: Vec<TitleNormalization>,
+    form: UnicodeForm,
+}
+
+impl TitleNormalizer {
+    pub fn new(steps: &[TitleNormalization], form: UnicodeForm) -> Self {
+        let mut steps = steps.to_vec();
+        steps.sort_unstable_by_key(|x| *x as u8);
+        steps.dedup();
+
+        Self { steps, form }
+    }
+
+    /// Normalizes a token, or any other text, with the Unicode normalization form alone.
+    pub fn normalize_text<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        self.form.apply(text)
+    }
+
+    /// Normalizes `title` like [`TitleNormalizer::normalize`], but borrows it if it's certainly
+    /// normalized already, which is much cheaper to check than normalizing it.
+    pub fn normalize_borrowed<'a>(&self, title: &'a str) -> Cow<'a, str> {
+        if self.is_normalized(title) {
+            Cow::Borrowed(title)
+        } else {
+            Cow::Owned(self.normalize(title))
+        }
+    }
+
+    /// Returns whether none of the steps, nor the Unicode normalization, changes `title`. Every
+    /// step is checked against `title` itself, since it's only passed on as it is.
+    fn is_normalized(&self, title: &str) -> bool {
+        self.steps.iter().all(|step| match step {
+            TitleNormalization::Percent => !title.contains('%'),
+            TitleNormalization::Html => !title.contains('&'),
+            TitleNormalization::Underscores => !title.contains(' '),
+            TitleNormalization::Capitalize => !title.starts_with(char::is_lowercase),
+            TitleNormalization::Nfc => UnicodeForm::Nfc.is_normalized(title),
+        }) && self.form.is_normalized(title)
+    }
+
+    pub fn normalize(&self, title: &str) -> String {
+        let mut title = title.to_owned();
+
+        for step in &self.steps {
+            title = match step {
+                TitleNormalization::Percent => decode_percent(&title),
+                TitleNormalization::Html => decode_html(&title),
+                TitleNormalization::Underscores => title.replace(' ', "_"),
+                TitleNormalization::Capitalize => capitalize(&title),
+                TitleNormalization::Nfc if UnicodeForm::Nfc.is_normalized(&title) => title,
+                TitleNormalization::Nfc => title.nfc().collect(),
+            };
+        }
+
+        if self.form.is_normalized(&title) {
+            title
+        } else {
+            self.form.apply(&title).into_owned()
+        }
+    }
+}
+
+fn decode_percent(title: &str) -> String {
+    if !title.contains('%') {
+        return title.to_owned();
+    }
+
+    let bytes = title.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let (Some(high), Some(low)) = (hex_value(bytes[i + 1]), hex_value(bytes[i + 2])) {
+                decoded.push(high << 4 | low);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8(decoded).unwrap_or_else(|_| title.to_owned())
+}
+
+fn hex_value(byte: u8) -> Option<u8> {
+    (byte as char).to_digit(16).map(|x| x as u8)
+}
+
+fn decode_html(title: &str) -> String {
+    if !title.contains('&') {
+        return title.to_owned();
+    }
+
+    let mut decoded = String::with_capacity(title.len());
+
+    let mut rest = title;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let entity = rest
+            .find(';')
+            .filter(|end| *end <= 10)
+            .and_then(|end| Some((decode_entity(&rest[1..end])?, end)));
+
+        match entity {
+            Some((c, end)) => {
+                decoded.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+
+    decoded
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+    match entity {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some('\u{a0}'),
+        _ => {
+            let code = entity.strip_prefix('#')?;
+            let code = match code.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => code.parse::<u32>().ok()?,
+            };
+            char::from_u32(code)
+        }
+    }
+}
+
+fn capitalize(title: &str) -> String {
+    let mut chars = title.chars();
+    match chars.next() {
+        Some(first) if first.is_lowercase() => first.to_uppercase().chain(chars).collect(),
+        _ => title.to_owned(),
+    }
+}
@@ -0,0 +1,52 @@
+use crate::{EntityType, TokenRecord};
+use hashbrown::HashMap;
+use itertools::Itertools;
+
+/// The numbers of documents and of mentions linked to a Wikipedia page of every split of the
+/// AIDA CoNLL-YAGO dataset, as published with it (Hoffart et al., 2011).
+const OFFICIAL_STATISTICS: [(&str, usize, usize); 3] = [
+    ("train", 946, 18541),
+    ("validation", 216, 4791),
+    ("test", 231, 4485),
+];
+
+/// The numbers of documents and of linked mentions of the parsed splits, which are counted a part
+/// of a split at a time and compared with the published statistics of the AIDA CoNLL-YAGO dataset,
+/// which catches regressions of the parser.
+#[derive(Debug, Default)]
+pub struct OfficialCounts(HashMap<String, (usize, usize)>);
+
+impl OfficialCounts {
+    /// Counts the documents and the linked mentions of `records`, which are whole documents of the
+    /// split `name`.
+    pub fn count(&mut self, name: &str, records: &[TokenRecord]) {
+        let (documents, mentions) = self.0.entry_ref(name).or_default();
+        *documents += records.iter().group_by(|x| x.document).into_iter().count();
+        *mentions += records
+            .iter()
+            .filter(|x| x.begins && matches!(x.entity, EntityType::InDistribution(..)))
+            .count();
+    }
+
+    /// Returns the differences from the published statistics, as
+    /// `split<TAB>statistic<TAB>official<TAB>converted`.
+    pub fn differences(&self) -> Vec<String> {
+        let mut differences = vec![];
+
+        for (name, documents, mentions) in OFFICIAL_STATISTICS {
+            let Some(&(converted_documents, converted_mentions)) = self.0.get(name) else {
+                continue;
+            };
+            for (statistic, official, converted) in [
+                ("documents", documents, converted_documents),
+                ("linked mentions", mentions, converted_mentions),
+            ] {
+                if official != converted {
+                    differences.push(format!("{name}\t{statistic}\t{official}\t{converted}"));
+                }
+            }
+        }
+
+        differences
+    }
+}
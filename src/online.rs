@@ -0,0 +1,277 @@
+use crate::exit::{fail, Failure};
+use crate::http::{write_atomically, HttpClient};
+use crate::mapping::{Resolution, Resolver};
+use crate::run_report::{RunReport, Warning};
+use hashbrown::HashMap;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::PathBuf;
+
+const API_URL: &str = "https://en.wikipedia.org/w/api.php";
+/// The maximum number of titles the API accepts in a single query.
+const BATCH_SIZE: usize = 50;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct CachedPage {
+    pageid: u32,
+    qid: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Response {
+    query: Option<Query>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Query {
+    #[serde(default)]
+    normalized: Vec<Renaming>,
+    #[serde(default)]
+    redirects: Vec<Renaming>,
+    #[serde(default)]
+    pages: Vec<Page>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Renaming {
+    from: String,
+    to: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Page {
+    title: String,
+    pageid: Option<u32>,
+    #[serde(default)]
+    missing: bool,
+    pageprops: Option<PageProps>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PageProps {
+    wikibase_item: Option<String>,
+}
+
+/// Resolves titles missing from the mapping through the MediaWiki API.
+///
+/// The resolution of every title is cached on disk (including titles that don't
+/// exist), so repeated runs only query the titles they haven't seen before.
+pub struct OnlineResolver {
+    client: HttpClient,
+    cache_path: PathBuf,
+    cache: BTreeMap<String, Option<CachedPage>>,
+}
+
+impl OnlineResolver {
+    /// Returns the resolver with the cache in `cache_dir`. A cache that can't be read, e.g. a
+    /// corrupt one, is reported as a warning in `run` and replaced, so its titles are queried again.
+    pub fn new(cache_dir: &str, client: HttpClient, run: &mut RunReport) -> Self {
+        let cache_path = PathBuf::from(cache_dir).join("online.json");
+
+        let cache = match File::open(&cache_path) {
+            Ok(file) => serde_json::from_reader(BufReader::new(file)).unwrap_or_else(|error| {
+                run.warn(
+                    Warning::UnwritableCache,
+                    format!(
+                        "Couldn't read the cache of the online lookups {}, so its titles are queried again: {error}.",
+                        cache_path.display()
+                    ),
+                );
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        };
+
+        Self {
+            client,
+            cache_path,
+            cache,
+        }
+    }
+
+    /// Returns the resolution of every title that could be resolved.
+    pub fn resolve(&mut self, titles: &[String]) -> HashMap<String, Resolution> {
+        let uncached = titles
+            .iter()
+            .filter(|title| !self.cache.contains_key(*title))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for batch in uncached.chunks(BATCH_SIZE) {
+            let pages = fetch_batch(&mut self.client, batch);
+            for title in batch {
+                self.cache.insert(title.clone(), pages.get(title).copied());
+            }
+            self.save();
+        }
+
+        titles
+            .iter()
+            .filter_map(|title| {
+                let page = (*self.cache.get(title)?)?;
+                let resolution = Resolution {
+                    pageid: page.pageid,
+                    qid: page.qid,
+                    resolver: Resolver::Online,
+                };
+                Some((title.clone(), resolution))
+            })
+            .collect()
+    }
+
+    /// Writes the cache, unless the client keeps its cache as it is.
+    fn save(&self) {
+        if self.client.is_read_only() {
+            return;
+        }
+        let dir = self.cache_path.parent().unwrap();
+        if let Err(error) = fs::create_dir_all(dir) {
+            fail(
+                Failure::Write,
+                format!("Couldn't create {}: {error}.", dir.display()),
+            );
+        }
+        // The cache is replaced as a whole, so an interrupted run keeps the previous one.
+        write_atomically(&self.cache_path, &serde_json::to_vec(&self.cache).unwrap());
+    }
+}
+
+fn fetch_batch(client: &mut HttpClient, titles: &[String]) -> HashMap<String, CachedPage> {
+    let response: Response = client.get_json(
+        API_URL,
+        &[
+            ("action", "query"),
+            ("format", "json"),
+            ("formatversion", "2"),
+            ("prop", "pageprops"),
+            ("ppprop", "wikibase_item"),
+            ("redirects", "1"),
+            ("titles", &titles.join("|")),
+        ],
+    );
+
+    let Some(query) = response.query else {
+        return HashMap::new();
+    };
+
+    let renamings = query
+        .normalized
+        .into_iter()
+        .chain(query.redirects)
+        .map(|x| (x.from, x.to))
+        .collect::<HashMap<_, _>>();
+
+    let pages = query
+        .pages
+        .into_iter()
+        .filter(|page| !page.missing)
+        .filter_map(|page| {
+            let qid = page
+                .pageprops
+                .and_then(|x| x.wikibase_item)
+                .and_then(|x| x.trim_start_matches('Q').parse::<u32>().ok());
+            Some((
+                page.title,
+                CachedPage {
+                    pageid: page.pageid?,
+                    qid,
+                },
+            ))
+        })
+        .collect::<HashMap<_, _>>();
+
+    let mut resolved = HashMap::new();
+    for title in titles {
+        let mut current = title;
+        for _ in 0..=renamings.len() {
+            match renamings.get(current) {
+                Some(next) => current = next,
+                None => break,
+            }
+        }
+
+        if let Some(page) = pages.get(current) {
+            resolved.insert(title.clone(), *page);
+        }
+    }
+
+    resolved
+}
+
+const WIKIDATA_API_URL: &str = "https://www.wikidata.org/w/api.php";
+
+/// The state of a Wikidata item according to the API.
+#[derive(Debug, Clone)]
+pub enum ItemStatus {
+    Missing,
+    Found {
+        /// The QID the item resolves to, which differs from the requested one for redirects.
+        qid: u32,
+        /// The title of the item's English Wikipedia sitelink.
+        enwiki: Option<String>,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+struct EntitiesResponse {
+    #[serde(default)]
+    entities: HashMap<String, EntityRecord>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EntityRecord {
+    id: Option<String>,
+    missing: Option<serde_json::Value>,
+    #[serde(default)]
+    sitelinks: HashMap<String, Sitelink>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Sitelink {
+    title: String,
+}
+
+fn parse_qid(qid: &str) -> Option<u32> {
+    qid.trim_start_matches('Q').parse::<u32>().ok()
+}
+
+/// Fetches the status of every QID through `wbgetentities`.
+pub fn fetch_items(client: &mut HttpClient, qids: &[u32]) -> HashMap<u32, ItemStatus> {
+    let mut items = HashMap::new();
+
+    for batch in qids.chunks(BATCH_SIZE) {
+        let ids = batch.iter().map(|qid| format!("Q{qid}")).join("|");
+
+        let response: EntitiesResponse = client.get_json(
+            WIKIDATA_API_URL,
+            &[
+                ("action", "wbgetentities"),
+                ("format", "json"),
+                ("props", "sitelinks"),
+                ("sitefilter", "enwiki"),
+                ("ids", &ids),
+            ],
+        );
+
+        for (requested, record) in response.entities {
+            let Some(requested) = parse_qid(&requested) else {
+                continue;
+            };
+
+            let status = match (&record.missing, record.id.as_deref().and_then(parse_qid)) {
+                (None, Some(qid)) => ItemStatus::Found {
+                    qid,
+                    enwiki: record.sitelinks.get("enwiki").map(|x| x.title.clone()),
+                },
+                _ => ItemStatus::Missing,
+            };
+
+            items.insert(requested, status);
+        }
+    }
+
+    items
+}
@@ -0,0 +1,76 @@
+use crate::{DataPoint, Entity};
+use clap::ValueEnum;
+use std::cmp::Reverse;
+
+/// What's done with the entities whose spans overlap, or are nested in, other ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OverlapPolicy {
+    /// Fail the run, since the AIDA CoNLL-YAGO dataset itself has no overlapping mentions.
+    Fail,
+    /// Keep the longest of the overlapping entities, or the first of equally long ones.
+    Longest,
+    /// Keep the first of the overlapping entities.
+    First,
+    /// Keep all of them, and flag the ones overlapping a longer one in the `nested` column.
+    Nested,
+}
+
+fn overlap(a: &Entity, b: &Entity) -> bool {
+    a.start < b.end && b.start < a.end
+}
+
+/// Finds the overlapping entities of every document of `split` and resolves them per `policy`,
+/// unless it's [`OverlapPolicy::Fail`], keeping the order of the rest.
+///
+/// Returns the overlapping pairs, as `document: start..end and start..end`.
+pub fn resolve_overlaps(split: &mut [DataPoint], policy: OverlapPolicy) -> Vec<String> {
+    let mut overlaps = vec![];
+
+    for document in split {
+        let entities = &document.entities;
+        for (i, a) in entities.iter().enumerate() {
+            for b in entities[i + 1..].iter().filter(|b| overlap(a, b)) {
+                overlaps.push(format!(
+                    "{}: {}..{} and {}..{}",
+                    document.document_id, a.start, a.end, b.start, b.end
+                ));
+            }
+        }
+        if policy == OverlapPolicy::Fail {
+            continue;
+        }
+
+        // The entities are taken in the order of their priority, and each one is kept
+        // unless it overlaps one taken before it.
+        let mut order = (0..entities.len()).collect::<Vec<_>>();
+        match policy {
+            OverlapPolicy::First => order.sort_by_key(|&i| entities[i].start),
+            _ => order.sort_by_key(|&i| {
+                let entity = &entities[i];
+                (Reverse(entity.end - entity.start), entity.start)
+            }),
+        }
+
+        let mut kept = vec![false; entities.len()];
+        let mut nested = vec![false; entities.len()];
+        for (k, &i) in order.iter().enumerate() {
+            nested[i] = order[..k]
+                .iter()
+                .any(|&j| overlap(&entities[i], &entities[j]));
+            kept[i] = !order[..k]
+                .iter()
+                .any(|&j| kept[j] && overlap(&entities[i], &entities[j]));
+        }
+
+        if policy == OverlapPolicy::Nested {
+            for (entity, nested) in document.entities.iter_mut().zip(nested) {
+                entity.nested = nested;
+            }
+        } else {
+            let mut kept = kept.into_iter();
+            document.entities.retain(|_| kept.next().unwrap());
+        }
+    }
+
+    overlaps
+}
@@ -0,0 +1,115 @@
+use crate::exit;
+use crate::intern::Interner;
+use crate::records::{read_records, read_string, read_u32, write_records, write_str, write_u32};
+use crate::run_report::{RunReport, Warning};
+use crate::spill::SplitRecords;
+use crate::TokenRecord;
+use hashbrown::{HashMap, HashSet};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Bumped whenever the parsed records or their format change, invalidating old caches.
+const PARSE_CACHE_VERSION: u32 = 1;
+
+/// The parsed splits, the distinct titles, and the pageids of the titles, as returned by
+/// `parse_conll`.
+pub type Parsed = ([SplitRecords; 3], HashSet<String>, HashMap<String, u32>);
+
+/// Computes the path of the cache of the dataset parsed from the files at `paths` with the
+/// parsing `options`, which are hashed together with the contents of the files.
+///
+/// The files are hashed by their contents rather than their modification times, since hashing
+/// them costs a fraction of parsing them.
+pub fn parse_cache_path(paths: &[&str], options: &str, cache_dir: &str) -> PathBuf {
+    let mut hasher = Sha256::new();
+    hasher.update(PARSE_CACHE_VERSION.to_le_bytes());
+    for path in paths {
+        io::copy(&mut exit::open(path), &mut hasher).unwrap();
+        hasher.update([0]);
+    }
+    hasher.update(options.as_bytes());
+
+    let hash = hasher
+        .finalize()
+        .iter()
+        .map(|x| format!("{x:02x}"))
+        .collect::<String>();
+
+    Path::new(cache_dir)
+        .join("parsed")
+        .join(format!("parsed-{hash}.bin"))
+}
+
+/// Reads the parsed dataset from the cache at `path`, or returns `None` if there's none or it
+/// can't be read.
+pub fn read_parse_cache(path: &Path) -> Option<Parsed> {
+    let mut reader = BufReader::new(File::open(path).ok()?);
+    read_parsed(&mut reader).ok()
+}
+
+fn read_parsed(reader: &mut impl Read) -> io::Result<Parsed> {
+    let mut titles = Interner::default();
+    let mut words = Interner::default();
+    let train = read_records(reader, &mut titles, &mut words)?;
+    let validation = read_records(reader, &mut titles, &mut words)?;
+    let test = read_records(reader, &mut titles, &mut words)?;
+
+    let mut pageids = HashMap::new();
+    for _ in 0..read_u32(reader)? {
+        let title = read_string(reader)?;
+        pageids.insert(title, read_u32(reader)?);
+    }
+
+    let titles = titles.strings().map(str::to_owned).collect();
+    let splits = [train, validation, test].map(SplitRecords::Memory);
+    Ok((splits, titles, pageids))
+}
+
+/// Writes the parsed dataset into the cache at `path`, unless some splits were spilled. The titles
+/// aren't written, since they're the titles of the records.
+///
+/// A cache that can't be written is only a missed shortcut for the next run, so it's reported as a
+/// warning in `run`.
+pub fn write_parse_cache(path: &Path, parsed: &Parsed, run: &mut RunReport) {
+    let (splits, _, pageids) = parsed;
+    let Some(splits) = splits
+        .iter()
+        .map(SplitRecords::in_memory)
+        .collect::<Option<Vec<_>>>()
+    else {
+        return;
+    };
+
+    if let Err(error) = write_parsed(path, &splits, pageids) {
+        // A partially written cache would fail to be read by the next run.
+        let _ = fs::remove_file(path);
+        run.warn(
+            Warning::UnwritableCache,
+            format!(
+                "Couldn't write the parse cache {}, so the next run parses the dataset again: {error}.",
+                path.display()
+            ),
+        );
+    }
+}
+
+fn write_parsed(
+    path: &Path,
+    splits: &[&[TokenRecord]],
+    pageids: &HashMap<String, u32>,
+) -> io::Result<()> {
+    fs::create_dir_all(path.parent().unwrap())?;
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    for split in splits {
+        write_records(&mut writer, split)?;
+    }
+    write_u32(&mut writer, pageids.len() as u32)?;
+    for (title, pageid) in pageids {
+        write_str(&mut writer, title)?;
+        write_u32(&mut writer, *pageid)?;
+    }
+    writer.flush()
+}
@@ -0,0 +1,197 @@
+use crate::dataset::open_parquet;
+use crate::exit::{fail, Failure};
+use crate::mapping::{load_filtered_mapping, Resolution};
+use crate::normalize::TitleNormalizer;
+use crate::run_report::RunReport;
+use crate::{Args, Candidate, DataPoint};
+use arrow::array::{Array, AsArray};
+use arrow::datatypes::{DataType, Int32Type, Int64Type, UInt32Type, UInt64Type};
+use hashbrown::{HashMap, HashSet};
+use parquet::arrow::ProjectionMask;
+use std::path::Path;
+
+/// The number of occurrences of every target title of every surface form.
+pub type AnchorCounts = HashMap<String, Vec<(String, u64)>>;
+
+/// Returns the surface forms of every mention in `splits`.
+pub fn mention_surfaces(splits: &[&[DataPoint]]) -> HashSet<String> {
+    let mut surfaces = HashSet::new();
+
+    for example in splits.iter().flat_map(|x| x.iter()) {
+        let chars = example.text.chars().collect::<Vec<_>>();
+        for entity in &example.entities {
+            surfaces.insert(
+                chars[entity.start as usize..entity.end as usize]
+                    .iter()
+                    .collect::<String>(),
+            );
+        }
+    }
+
+    surfaces
+}
+
+fn counts_to_u64(array: &dyn Array) -> Vec<Option<u64>> {
+    macro_rules! collect {
+        ($t:ty) => {
+            array
+                .as_primitive::<$t>()
+                .iter()
+                .map(|x| x.and_then(|x| u64::try_from(x).ok()))
+                .collect()
+        };
+    }
+
+    match array.data_type() {
+        DataType::Int32 => collect!(Int32Type),
+        DataType::Int64 => collect!(Int64Type),
+        DataType::UInt32 => collect!(UInt32Type),
+        DataType::UInt64 => collect!(UInt64Type),
+        data_type => fail(
+            Failure::Parse,
+            format!("Unsupported type of the anchor counts: {data_type:?}."),
+        ),
+    }
+}
+
+/// Loads the anchors of the surface forms in `surfaces` from a Parquet file with
+/// the `surface`, `title`, and `count` columns.
+fn load_anchor_counts(
+    path: &str,
+    surfaces: &HashSet<String>,
+    normalizer: &TitleNormalizer,
+) -> AnchorCounts {
+    let builder = open_parquet(Path::new(path));
+
+    let column = |name: &str| match builder.schema().index_of(name) {
+        Ok(index) => index,
+        Err(_) => fail(
+            Failure::Parse,
+            format!("The anchors file {path} has no {name} column."),
+        ),
+    };
+    let indices = [column("surface"), column("title"), column("count")];
+    let projection = ProjectionMask::roots(builder.parquet_schema(), indices);
+
+    let mut anchors = AnchorCounts::new();
+    let reader = builder.with_projection(projection).build().unwrap();
+
+    for batch in reader {
+        let batch = batch.unwrap();
+        let column = |name: &str| batch.column_by_name(name).unwrap();
+
+        let surface = column("surface").as_string::<i32>();
+        let title = column("title").as_string::<i32>();
+        let count = counts_to_u64(column("count").as_ref());
+
+        for ((surface, title), count) in surface.iter().zip(title.iter()).zip(count) {
+            let (Some(surface), Some(title), Some(count)) = (surface, title, count) else {
+                continue;
+            };
+            if !surfaces.contains(surface) {
+                continue;
+            }
+
+            anchors
+                .entry(surface.to_owned())
+                .or_default()
+                .push((normalizer.normalize(&title.replace(' ', "_")), count));
+        }
+    }
+
+    anchors
+}
+
+/// The anchors of the surface forms of the mentions, with the QIDs of their targets that are
+/// missing from the mapping of the annotated titles.
+pub struct Priors {
+    anchors: AnchorCounts,
+    qids: HashMap<String, Option<u32>>,
+}
+
+impl Priors {
+    /// Loads the anchors of the surface forms in `surfaces` from the Parquet file at `path`, as
+    /// [`load_anchor_counts`] does, and the QIDs of their targets from the mappings.
+    pub fn load(
+        path: &str,
+        surfaces: &HashSet<String>,
+        mapping: &HashMap<String, Resolution>,
+        args: &Args,
+        normalizer: &TitleNormalizer,
+        run: &mut RunReport,
+    ) -> Self {
+        let anchors = load_anchor_counts(path, surfaces, normalizer);
+        let titles = anchors
+            .values()
+            .flatten()
+            .map(|(title, _)| title.clone())
+            .filter(|x| !mapping.contains_key(x))
+            .collect::<HashSet<_>>();
+
+        let mut qids = HashMap::new();
+        let cache = args.mapping_cache();
+        for path in args.input_wiki2qid.iter().rev() {
+            for entry in
+                load_filtered_mapping(path, &titles, &HashSet::new(), normalizer, cache, run)
+            {
+                qids.try_insert(entry.title, entry.qid).ok();
+            }
+        }
+
+        Self { anchors, qids }
+    }
+
+    /// Computes the candidates of every mention in `split` from the anchors, keeping at most
+    /// `max_candidates` of them per mention, together with the prior of the annotated entity.
+    pub fn add_candidates(
+        &self,
+        split: &mut [DataPoint],
+        mapping: &HashMap<String, Resolution>,
+        args: &Args,
+    ) {
+        let Self { anchors, qids } = self;
+
+        for example in split.iter_mut() {
+            let chars = example.text.chars().collect::<Vec<_>>();
+
+            for entity in &mut example.entities {
+                let surface = chars[entity.start as usize..entity.end as usize]
+                    .iter()
+                    .collect::<String>();
+                let Some(targets) = anchors.get(&surface) else {
+                    continue;
+                };
+
+                let mut counts = HashMap::<&str, u64>::new();
+                for (title, count) in targets {
+                    *counts.entry(title).or_insert(0) += count;
+                }
+                let total = counts.values().sum::<u64>().max(1) as f64;
+
+                let mut counts = counts.into_iter().collect::<Vec<_>>();
+                counts.sort_unstable_by(|(a, x), (b, y)| y.cmp(x).then(a.cmp(b)));
+
+                entity.prior = entity.title.as_deref().map(|title| {
+                    counts
+                        .iter()
+                        .find(|(x, _)| *x == title)
+                        .map_or(0.0, |(_, count)| (*count as f64 / total) as f32)
+                });
+
+                let candidates = counts
+                    .into_iter()
+                    .take(args.max_candidates)
+                    .map(|(title, count)| Candidate {
+                        title: title.to_owned(),
+                        qid: match mapping.get(title) {
+                            Some(resolution) => resolution.qid,
+                            None => qids.get(title).copied().flatten(),
+                        },
+                        prior: (count as f64 / total) as f32,
+                    })
+                    .collect();
+                entity.candidates = candidates;
+            }
+        }
+    }
+}
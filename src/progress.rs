@@ -0,0 +1,38 @@
+use indicatif::{ProgressBar, ProgressStyle};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Hides every progress bar created afterwards, for `--quiet`.
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+fn create(length: u64, template: &str, message: &str) -> ProgressBar {
+    if QUIET.load(Ordering::Relaxed) {
+        return ProgressBar::hidden();
+    }
+
+    // Progress bars are only drawn if stderr is a terminal, so they never end up in logs.
+    let bar = ProgressBar::new(length).with_message(message.to_owned());
+    bar.set_style(
+        ProgressStyle::with_template(template)
+            .unwrap()
+            .progress_chars("=> "),
+    );
+    bar
+}
+
+/// Creates a progress bar over the `length` bytes of an input, e.g. to wrap its reader in.
+pub fn bytes(length: u64, message: &str) -> ProgressBar {
+    create(
+        length,
+        "{msg} [{bar:40}] {bytes}/{total_bytes} ({eta})",
+        message,
+    )
+}
+
+/// Creates a progress bar over `length` items.
+pub fn items(length: u64, message: &str) -> ProgressBar {
+    create(length, "{msg} [{bar:40}] {pos}/{len}", message)
+}
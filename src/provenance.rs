@@ -0,0 +1,141 @@
+use crate::exit::{self, fail, Failure};
+use crate::mapping::{Resolution, Resolver};
+use hashbrown::HashMap;
+use lazy_regex::regex_is_match;
+use parquet::file::metadata::KeyValue;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+/// The version of the layout of the generated Parquet files, bumped on breaking changes.
+pub const SCHEMA_VERSION: &str = "1";
+
+/// The order of the entities of every document, recorded in the `entity_order` key of the
+/// metadata: by their start offsets, and then by their end offsets, so consumers can
+/// binary-search the spans.
+pub const ENTITY_ORDER: &str = "start,end";
+
+/// Returns the date of the mapping snapshot in the `YYYY-MM-DD` format, either as given
+/// with `--snapshot-date` or as the latest modification date of the mapping files.
+pub fn snapshot_date(date: Option<&str>, paths: &[String]) -> String {
+    if let Some(date) = date {
+        if !regex_is_match!(r"^\d{4}-\d{2}-\d{2}$", date) {
+            fail(
+                Failure::Usage,
+                format!("Invalid snapshot date {date}; expected YYYY-MM-DD."),
+            );
+        }
+        return date.to_owned();
+    }
+
+    let seconds = paths
+        .iter()
+        .map(|path| {
+            fs::metadata(path)
+                .unwrap()
+                .modified()
+                .unwrap()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs()
+        })
+        .max()
+        .unwrap();
+
+    civil_date(seconds / 86400)
+}
+
+/// Converts the number of days since the Unix epoch into a date in the proleptic Gregorian calendar.
+fn civil_date(days: u64) -> String {
+    let days = days as i64 + 719468;
+    let era = days / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// The key-value metadata written into the footer of every Parquet file.
+pub fn provenance_metadata(snapshot_date: &str, paths: &[String]) -> Vec<KeyValue> {
+    let files = paths
+        .iter()
+        .map(|path| {
+            Path::new(path)
+                .file_name()
+                .map(|x| x.to_string_lossy().into_owned())
+                .unwrap_or_else(|| path.clone())
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+
+    vec![
+        KeyValue {
+            key: "schema_version".to_owned(),
+            value: Some(SCHEMA_VERSION.to_owned()),
+        },
+        KeyValue {
+            key: "entity_order".to_owned(),
+            value: Some(ENTITY_ORDER.to_owned()),
+        },
+        KeyValue {
+            key: "mapping_snapshot_date".to_owned(),
+            value: Some(snapshot_date.to_owned()),
+        },
+        KeyValue {
+            key: "mapping_files".to_owned(),
+            value: Some(files),
+        },
+    ]
+}
+
+/// Explains why the resolution of a title is likely to differ when the dataset is
+/// regenerated from a newer snapshot, or returns `None` if it's resolved directly.
+fn staleness_reason(resolver: Resolver) -> Option<&'static str> {
+    match resolver {
+        Resolver::Mapping => None,
+        Resolver::Correction => Some("missing or wrong in the snapshot, fixed by hand"),
+        Resolver::Redirect => Some("the title is a redirect whose target may have moved"),
+        Resolver::Pageid => Some("the title was renamed since the dataset was annotated"),
+        Resolver::Online => Some("resolved against the live Wikipedia, not the snapshot"),
+        Resolver::CaseInsensitive | Resolver::EditDistance => {
+            Some("matched approximately to a title in the snapshot")
+        }
+    }
+}
+
+/// Writes a TSV report with the titles whose resolution likely changes across snapshots, and returns their number.
+pub fn write_staleness_report(mapping: &HashMap<String, Resolution>, path: &str) -> usize {
+    let mut stale = mapping
+        .iter()
+        .filter_map(|(title, resolution)| {
+            Some((title, resolution, staleness_reason(resolution.resolver)?))
+        })
+        .collect::<Vec<_>>();
+    stale.sort_unstable_by_key(|(title, _, _)| *title);
+
+    let mut writer = BufWriter::new(exit::create(path));
+    writeln!(writer, "title\tresolver\tqid\treason").unwrap();
+
+    for (title, resolution, reason) in &stale {
+        let qid = resolution.qid.map(|x| format!("Q{x}")).unwrap_or_default();
+        writeln!(
+            writer,
+            "{title}\t{}\t{qid}\t{reason}",
+            resolution.resolver.name()
+        )
+        .unwrap();
+    }
+
+    stale.len()
+}
@@ -0,0 +1,30 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use sha2::{Digest, Sha256};
+use uuid::{Builder, Uuid};
+
+/// Creates the random number generator for all the randomized behavior, which is
+/// seeded with `--seed` so runs with the same seed are reproducible.
+pub fn rng(seed: Option<u64>) -> StdRng {
+    match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    }
+}
+
+/// Generates a random (version 4) UUID with `rng`.
+pub fn uuid(rng: &mut StdRng) -> Uuid {
+    Builder::from_random_bytes(rng.gen()).into_uuid()
+}
+
+/// Derives a (version 8) UUID from the hash of `parts`, so the same content always gets
+/// the same UUID, without any seed.
+pub fn content_uuid(parts: &[&[u8]]) -> Uuid {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part);
+    }
+
+    let hash = hasher.finalize();
+    Builder::from_custom_bytes(hash[..16].try_into().unwrap()).into_uuid()
+}
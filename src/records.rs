@@ -0,0 +1,186 @@
+use crate::intern::Interner;
+use crate::{EntityType, TokenRecord};
+use std::io::{self, Read, Write};
+
+/// The tags of the entities, which are written as their indices.
+const TAGS: [&str; 5] = ["", "PER", "LOC", "ORG", "MISC"];
+
+pub fn write_u32(writer: &mut impl Write, value: u32) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+pub fn write_str(writer: &mut impl Write, value: &str) -> io::Result<()> {
+    write_u32(writer, value.len() as u32)?;
+    writer.write_all(value.as_bytes())
+}
+
+pub fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u8(reader: &mut impl Read) -> io::Result<u8> {
+    let mut byte = [0];
+    reader.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+pub fn read_string(reader: &mut impl Read) -> io::Result<String> {
+    let mut bytes = vec![0; read_u32(reader)? as usize];
+    reader.read_exact(&mut bytes)?;
+    String::from_utf8(bytes).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+fn tag_index(tag: &str) -> u8 {
+    TAGS.iter().position(|x| *x == tag).unwrap() as u8
+}
+
+fn read_tag(reader: &mut impl Read) -> io::Result<&'static str> {
+    TAGS.get(read_u8(reader)? as usize)
+        .copied()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid tag"))
+}
+
+/// Writes `records` in a compact binary format, which [`read_records`] reads back.
+pub fn write_records(writer: &mut impl Write, records: &[TokenRecord]) -> io::Result<()> {
+    writer.write_all(&(records.len() as u64).to_le_bytes())?;
+
+    for record in records {
+        write_u32(writer, record.document_id)?;
+        write_u32(writer, record.document)?;
+        write_u32(writer, record.sentence)?;
+        writer.write_all(&[record.begins as u8])?;
+        match &record.entity {
+            EntityType::None => writer.write_all(&[0])?,
+            EntityType::OutOfDistribution(tag) => writer.write_all(&[1, tag_index(tag)])?,
+            EntityType::InDistribution(title, tag) => {
+                writer.write_all(&[2, tag_index(tag)])?;
+                write_str(writer, title)?;
+            }
+        }
+        write_str(writer, &record.token)?;
+    }
+
+    Ok(())
+}
+
+/// Reads the number of records written by [`write_records`], which [`read_record`] then reads one
+/// at a time.
+pub fn read_length(reader: &mut impl Read) -> io::Result<usize> {
+    let mut length = [0; 8];
+    reader.read_exact(&mut length)?;
+    Ok(u64::from_le_bytes(length) as usize)
+}
+
+/// Reads one of the records written by [`write_records`], interning its title with `titles` and
+/// its token with `words` again.
+pub fn read_record(
+    reader: &mut impl Read,
+    titles: &mut Interner,
+    words: &mut Interner,
+) -> io::Result<TokenRecord> {
+    let document_id = read_u32(reader)?;
+    let document = read_u32(reader)?;
+    let sentence = read_u32(reader)?;
+    let begins = read_u8(reader)? != 0;
+    let entity = match read_u8(reader)? {
+        0 => EntityType::None,
+        1 => EntityType::OutOfDistribution(read_tag(reader)?),
+        2 => {
+            let tag = read_tag(reader)?;
+            EntityType::InDistribution(titles.intern(&read_string(reader)?), tag)
+        }
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid entity")),
+    };
+    let token = words.intern(&read_string(reader)?);
+
+    Ok(TokenRecord {
+        document_id,
+        document,
+        sentence,
+        token,
+        entity,
+        begins,
+    })
+}
+
+/// Reads the records written by [`write_records`], interning their titles with `titles` and their
+/// tokens with `words` again.
+pub fn read_records(
+    reader: &mut impl Read,
+    titles: &mut Interner,
+    words: &mut Interner,
+) -> io::Result<Vec<TokenRecord>> {
+    let length = read_length(reader)?;
+
+    let mut records = Vec::with_capacity(length);
+    for _ in 0..length {
+        records.push(read_record(reader, titles, words)?);
+    }
+
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn record(token: &str, entity: EntityType, begins: bool) -> TokenRecord {
+        TokenRecord {
+            document_id: 946,
+            document: 3,
+            sentence: 1,
+            token: Arc::from(token),
+            entity,
+            begins,
+        }
+    }
+
+    #[test]
+    fn records_are_read_back_as_written() {
+        let title = Arc::<str>::from("New_York_City");
+        let records = [
+            record("In", EntityType::None, false),
+            record(
+                "New",
+                EntityType::InDistribution(title.clone(), "LOC"),
+                true,
+            ),
+            record("York", EntityType::InDistribution(title, "LOC"), false),
+            record("Reuters", EntityType::OutOfDistribution("ORG"), true),
+        ];
+        let mut bytes = vec![];
+        write_records(&mut bytes, &records).unwrap();
+
+        let read = read_records(
+            &mut bytes.as_slice(),
+            &mut Interner::default(),
+            &mut Interner::default(),
+        )
+        .unwrap();
+        assert_eq!(format!("{read:?}"), format!("{records:?}"));
+
+        let (EntityType::InDistribution(a, _), EntityType::InDistribution(b, _)) =
+            (&read[1].entity, &read[2].entity)
+        else {
+            unreachable!();
+        };
+        assert!(Arc::ptr_eq(a, b));
+    }
+
+    #[test]
+    fn truncated_records_are_an_error() {
+        let mut bytes = vec![];
+        write_records(&mut bytes, &[record("EU", EntityType::None, false)]).unwrap();
+        bytes.pop();
+
+        assert!(read_records(
+            &mut bytes.as_slice(),
+            &mut Interner::default(),
+            &mut Interner::default(),
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,77 @@
+use crate::exit;
+use crate::normalize::TitleNormalizer;
+use hashbrown::{HashMap, HashSet};
+use std::io::{BufRead, BufReader};
+
+/// Loads the redirects whose source is one of `titles` from a TSV file with
+/// one `source<TAB>target` pair per line.
+///
+/// Only a single hop is followed since Wikipedia doesn't keep double redirects around.
+pub fn load_redirects(
+    path: &str,
+    titles: &HashSet<String>,
+    normalizer: &TitleNormalizer,
+) -> HashMap<String, String> {
+    let mut redirects = HashMap::new();
+
+    let reader = BufReader::new(exit::open(path));
+    for line in reader.lines() {
+        let line = line.unwrap();
+
+        let Some((source, target)) = line.split_once('\t') else {
+            continue;
+        };
+
+        let source = normalizer.normalize(source);
+        if titles.contains(&source) {
+            let target = normalizer.normalize(target);
+            if source != target {
+                redirects.insert(source, target);
+            }
+        }
+    }
+
+    redirects
+}
+
+pub fn parse_qid(qid: &str) -> Option<u32> {
+    qid.trim().trim_start_matches('Q').parse::<u32>().ok()
+}
+
+/// Loads redirects between Wikidata's items from a TSV file with one `old<TAB>new`
+/// pair of QIDs (with or without the "Q" prefix) per line.
+pub fn load_qid_redirects(path: &str) -> HashMap<u32, u32> {
+    let mut redirects = HashMap::new();
+
+    let reader = BufReader::new(exit::open(path));
+    for line in reader.lines() {
+        let line = line.unwrap();
+
+        let Some((old, new)) = line.split_once('\t') else {
+            continue;
+        };
+
+        if let (Some(old), Some(new)) = (parse_qid(old), parse_qid(new)) {
+            if old != new {
+                redirects.insert(old, new);
+            }
+        }
+    }
+
+    redirects
+}
+
+/// Follows the redirects starting from `qid`, stopping on cycles.
+pub fn resolve_qid(qid: u32, redirects: &HashMap<u32, u32>) -> u32 {
+    let mut visited = HashSet::new();
+    let mut qid = qid;
+
+    while let Some(&target) = redirects.get(&qid) {
+        if !visited.insert(qid) {
+            break;
+        }
+        qid = target;
+    }
+
+    qid
+}
@@ -0,0 +1,86 @@
+use crate::dataset::{Document, StoredSplit};
+
+/// Returns the spans of the space-separated tokens of `text`, in characters.
+fn token_spans(text: &str) -> Vec<(u64, u64)> {
+    let mut spans = vec![];
+    let mut start = 0;
+    for token in text.split(' ') {
+        let end = start + token.chars().count() as u64;
+        if end > start {
+            spans.push((start, end));
+        }
+        start = end + 1;
+    }
+    spans
+}
+
+/// Returns the span of the tokens `spans` closest to `start..end`, or `None` if the text has no
+/// tokens to snap to.
+fn snap(spans: &[(u64, u64)], start: u64, end: u64) -> Option<(u64, u64)> {
+    let (first, _) = spans
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, x)| x.0.abs_diff(start))?;
+    let last = spans[first..]
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, x)| x.1.abs_diff(end))
+        .map(|(i, _)| first + i)?;
+    Some((spans[first].0, spans[last].1))
+}
+
+/// Snaps the offsets of the entities of `document` to the nearest boundaries of its tokens,
+/// dropping the ones that end up empty, and returns the adjustments.
+fn snap_document(document: &mut Document) -> Vec<String> {
+    let spans = token_spans(&document.text);
+    let mut adjustments = vec![];
+
+    let mut entities = std::mem::take(&mut document.entities);
+    entities.retain_mut(|entity| {
+        let before = format!(
+            "{}..{} {:?}",
+            entity.start,
+            entity.end,
+            document.surface(entity)
+        );
+        match snap(&spans, entity.start, entity.end) {
+            Some((start, end)) if (start, end) == (entity.start, entity.end) => true,
+            Some((start, end)) => {
+                entity.start = start;
+                entity.end = end;
+                adjustments.push(format!(
+                    "{before} -> {start}..{end} {:?}",
+                    document.surface(entity)
+                ));
+                true
+            }
+            None => {
+                adjustments.push(format!("{before} dropped"));
+                false
+            }
+        }
+    });
+    document.entities = entities;
+
+    adjustments
+}
+
+/// Snaps the offsets of the entities of every document of `splits` to the nearest boundaries of
+/// the space-separated tokens of its text, for inputs converted by other tools whose spans are
+/// slightly off, e.g. include a trailing space or cut a token.
+///
+/// Returns every adjustment, as `split: document: start..end "surface" -> start..end "surface"`.
+pub fn fix_offsets(splits: &mut [StoredSplit]) -> Vec<String> {
+    let mut adjustments = vec![];
+    for split in splits {
+        for document in &mut split.documents {
+            let id = document.document_id;
+            adjustments.extend(
+                snap_document(document)
+                    .into_iter()
+                    .map(|x| format!("{}: document {id}: {x}", split.name)),
+            );
+        }
+    }
+    adjustments
+}
@@ -0,0 +1,128 @@
+use crate::dataset::{read_dataset, Document};
+use crate::exit;
+use crate::stats::stats_json;
+use std::fmt::Write;
+
+const STYLE: &str = "
+body { font-family: sans-serif; max-width: 60em; margin: 2em auto; color: #222; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.8em; text-align: right; }
+th:first-child, td:first-child { text-align: left; }
+.document { line-height: 2.5; margin-bottom: 2em; }
+mark { padding: 0.2em 0.4em; border-radius: 0.35em; }
+mark.linked { background: #c8e6c9; }
+mark.nil { background: #ffe0b2; }
+mark a { color: inherit; text-decoration: none; }
+.tag { font-size: 0.7em; font-weight: bold; margin-left: 0.4em; text-transform: uppercase; }
+";
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Renders the text of `document` with its mentions highlighted in the style of displaCy,
+/// with the title and the QID of every mention in its tooltip.
+fn render_document(document: &Document) -> String {
+    let chars = document.text.chars().collect::<Vec<_>>();
+    let mut mentions = document.entities.iter().collect::<Vec<_>>();
+    mentions.sort_by_key(|x| x.start);
+
+    let mut html = String::new();
+    let mut position = 0;
+    for mention in mentions {
+        let (start, end) = (mention.start as usize, mention.end as usize);
+        if start < position || end > chars.len() {
+            continue;
+        }
+
+        html.push_str(&escape(&chars[position..start].iter().collect::<String>()));
+        let surface = escape(&chars[start..end].iter().collect::<String>());
+        let tag = escape(&mention.tag);
+
+        match (&mention.title, mention.qid) {
+            (Some(title), Some(qid)) => write!(
+                html,
+                "<mark class=\"linked\" title=\"{} (Q{qid})\"><a href=\"https://www.wikidata.org/wiki/Q{qid}\">{surface}</a><span class=\"tag\">{tag}</span></mark>",
+                escape(title)
+            ),
+            (Some(title), None) => write!(
+                html,
+                "<mark class=\"nil\" title=\"{} (no QID)\">{surface}<span class=\"tag\">{tag}</span></mark>",
+                escape(title)
+            ),
+            (None, _) => write!(
+                html,
+                "<mark class=\"nil\" title=\"NIL\">{surface}<span class=\"tag\">{tag}</span></mark>"
+            ),
+        }
+        .unwrap();
+        position = end;
+    }
+    html.push_str(&escape(&chars[position..].iter().collect::<String>()));
+
+    html
+}
+
+/// Writes a static HTML page to `output` with the statistics of every split of the generated
+/// dataset in `input`, followed by the first `max_documents` documents of every split (or all
+/// of them if `max_documents` is 0) with their mentions highlighted.
+pub fn write_report(input: &str, output: &str, max_documents: usize) {
+    let splits = read_dataset(input);
+    let stats = stats_json(&splits);
+
+    let mut html = String::new();
+    write!(
+        html,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>AIDA CoNLL-YAGO Wikidata</title>\n<style>{STYLE}</style>\n</head>\n<body>\n<h1>AIDA CoNLL-YAGO Wikidata</h1>\n"
+    )
+    .unwrap();
+
+    html.push_str("<h2>Statistics</h2>\n<table>\n<tr><th>split</th><th>documents</th><th>mentions</th><th>NIL mentions</th><th>unique entities</th></tr>\n");
+    for split in &splits {
+        let stats = &stats[&split.name];
+        writeln!(
+            html,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            escape(&split.name),
+            stats["documents"],
+            stats["mentions"],
+            stats["nil_mentions"],
+            stats["unique_entities"]
+        )
+        .unwrap();
+    }
+    html.push_str("</table>\n");
+
+    for split in &splits {
+        writeln!(html, "<h2>{}</h2>", escape(&split.name)).unwrap();
+
+        let count = match max_documents {
+            0 => split.documents.len(),
+            n => n.min(split.documents.len()),
+        };
+        if count < split.documents.len() {
+            writeln!(
+                html,
+                "<p>The first {count} of {} documents.</p>",
+                split.documents.len()
+            )
+            .unwrap();
+        }
+
+        for document in &split.documents[..count] {
+            writeln!(
+                html,
+                "<h3>Document {}</h3>\n<div class=\"document\">{}</div>",
+                document.document_id,
+                render_document(document)
+            )
+            .unwrap();
+        }
+    }
+
+    html.push_str("</body>\n</html>\n");
+    exit::write(output, html);
+}
@@ -0,0 +1,445 @@
+use crate::corrections::{load_corrections, Suggestion};
+use crate::exit::{self, fail, Failure};
+use crate::mapping::{
+    fuzzy_match, is_list_page, load_disambiguation_pages, load_filtered_mapping, load_page_items,
+    Resolution, Resolver,
+};
+use crate::normalize::TitleNormalizer;
+use crate::online::OnlineResolver;
+use crate::redirects::{load_qid_redirects, load_redirects, resolve_qid};
+use crate::run_report::{RunReport, Warning};
+use crate::Args;
+use hashbrown::{HashMap, HashSet};
+use serde::Deserialize;
+use std::io::{BufWriter, Write};
+
+/// A single link in the chain of resolvers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Step {
+    /// The bundled corrections and the ones given with `--corrections`.
+    Correction,
+    /// The mappings given with `--input-wiki2qid`.
+    Mapping,
+    /// The mappings of the targets of the redirects given with `--redirects`.
+    Redirect,
+    /// The mappings of the pageids given in the TSV.
+    Pageid,
+    /// The Wikipedia API, if enabled with `--online`.
+    Online,
+    /// Approximate matches against the mappings, if enabled with `--fuzzy`.
+    Fuzzy,
+}
+
+/// The order in which the resolvers are tried when no configuration is given.
+const DEFAULT_CHAIN: [Step; 6] = [
+    Step::Correction,
+    Step::Redirect,
+    Step::Mapping,
+    Step::Pageid,
+    Step::Online,
+    Step::Fuzzy,
+];
+
+#[derive(Debug, Deserialize)]
+struct ResolverConfig {
+    resolvers: Vec<Step>,
+}
+
+/// Loads the order of the resolvers from a TOML file with a `resolvers` array,
+/// e.g., `resolvers = ["correction", "mapping", "redirect", "pageid", "online"]`.
+///
+/// The resolvers left out of the array are never tried.
+pub fn load_resolver_chain(path: Option<&str>) -> Vec<Step> {
+    let Some(path) = path else {
+        return DEFAULT_CHAIN.to_vec();
+    };
+
+    let source = exit::read_to_string(path);
+    match toml::from_str::<ResolverConfig>(&source) {
+        Ok(config) => config.resolvers,
+        Err(error) => fail(
+            Failure::Usage,
+            format!("Invalid resolver configuration {path}: {error}."),
+        ),
+    }
+}
+
+/// Resolves every title by trying the resolvers in `chain` in order, with each
+/// resolver only seeing the titles that none of the previous ones resolved.
+pub fn resolve_titles(
+    args: &Args,
+    chain: &[Step],
+    titles: &HashSet<String>,
+    pageids: &HashMap<String, u32>,
+    normalizer: &TitleNormalizer,
+    run: &mut RunReport,
+) -> HashMap<String, Resolution> {
+    let corrections = load_corrections(args.corrections.as_deref(), normalizer);
+
+    let redirects = match &args.redirects {
+        Some(path) => load_redirects(path, titles, normalizer),
+        None => HashMap::new(),
+    };
+    let mut needed = titles.clone();
+    needed.extend(redirects.values().cloned());
+
+    let cache = args.mapping_cache();
+    let needed_pageids = if args.no_pageid_fallback {
+        HashSet::new()
+    } else {
+        pageids.values().copied().collect::<HashSet<_>>()
+    };
+
+    let mut local = HashMap::new();
+    let mut by_pageid = HashMap::new();
+    for path in &args.input_wiki2qid {
+        let entries = load_filtered_mapping(path, &needed, &needed_pageids, normalizer, cache, run);
+
+        let mut file_by_title = HashMap::new();
+        let mut file_by_pageid = HashMap::new();
+        for entry in entries {
+            file_by_pageid.try_insert(entry.pageid, entry.qid).ok();
+            if needed.contains(&entry.title) {
+                file_by_title
+                    .try_insert(entry.title, (entry.pageid, entry.qid))
+                    .ok();
+            }
+        }
+        local.extend(file_by_title);
+        by_pageid.extend(file_by_pageid);
+    }
+
+    let disambiguation = match &args.page_props {
+        Some(path) => load_disambiguation_pages(path),
+        None => HashSet::new(),
+    };
+    let mut ambiguous = local
+        .iter()
+        .filter(|(title, (pageid, _))| disambiguation.contains(pageid) || is_list_page(title))
+        .map(|(title, _)| title.clone())
+        .collect::<Vec<_>>();
+    ambiguous.sort_unstable();
+
+    // The pages and items excluded with `--exclude-disambiguation`, which no resolver but the
+    // corrections may resolve a title to.
+    let mut excluded_pageids = HashSet::new();
+    let mut excluded_qids = HashSet::new();
+    if args.exclude_disambiguation {
+        excluded_pageids.extend(disambiguation.iter().copied());
+        for title in &ambiguous {
+            let (pageid, qid) = local.remove(title).unwrap();
+            excluded_pageids.insert(pageid);
+            excluded_qids.extend(qid);
+        }
+        by_pageid.retain(|pageid, _| !excluded_pageids.contains(pageid));
+    }
+
+    if !ambiguous.is_empty() {
+        if args.exclude_disambiguation {
+            run.warn(
+                Warning::SuspiciousTarget,
+                format!(
+                    "Excluded {} titles of disambiguation or list pages from the mappings.",
+                    ambiguous.len()
+                ),
+            );
+        } else {
+            run.warn(
+                Warning::SuspiciousTarget,
+                format!(
+                    "{} titles map to disambiguation or list pages: {}.",
+                    ambiguous.len(),
+                    ambiguous.join(", ")
+                ),
+            );
+        }
+    }
+
+    let mut mapping = HashMap::<String, Resolution>::new();
+    let mut excluded = 0;
+
+    for step in chain {
+        let mut missing = titles
+            .iter()
+            .filter(|title| !mapping.contains_key(*title))
+            .cloned()
+            .collect::<Vec<_>>();
+        if missing.is_empty() {
+            break;
+        }
+        missing.sort_unstable();
+
+        let mut resolved = match step {
+            Step::Pageid if args.no_pageid_fallback => continue,
+            Step::Correction => missing
+                .into_iter()
+                .filter_map(|title| {
+                    let resolution = *corrections.get(&title)?;
+                    Some((title, resolution))
+                })
+                .collect::<HashMap<_, _>>(),
+            Step::Mapping => missing
+                .into_iter()
+                .filter_map(|title| {
+                    let (pageid, qid) = *local.get(&title)?;
+                    let resolution = Resolution {
+                        pageid,
+                        qid,
+                        resolver: Resolver::Mapping,
+                    };
+                    Some((title, resolution))
+                })
+                .collect(),
+            Step::Redirect => missing
+                .into_iter()
+                .filter_map(|title| {
+                    let target = redirects.get(&title)?;
+                    let (pageid, qid) = match corrections.get(target) {
+                        Some(resolution) => (resolution.pageid, resolution.qid),
+                        None => *local.get(target)?,
+                    };
+                    let resolution = Resolution {
+                        pageid,
+                        qid,
+                        resolver: Resolver::Redirect,
+                    };
+                    Some((title, resolution))
+                })
+                .collect(),
+            Step::Pageid => missing
+                .into_iter()
+                .filter_map(|title| {
+                    let pageid = *pageids.get(&title)?;
+                    let qid = *by_pageid.get(&pageid)?;
+                    let resolution = Resolution {
+                        pageid,
+                        qid,
+                        resolver: Resolver::Pageid,
+                    };
+                    Some((title, resolution))
+                })
+                .collect(),
+            Step::Online if args.online => {
+                let mut resolver = OnlineResolver::new(&args.cache_dir, args.http_client(), run);
+                resolver.resolve(&missing)
+            }
+            Step::Fuzzy if args.fuzzy => {
+                let mut matched = HashMap::new();
+                for path in &args.input_wiki2qid {
+                    matched.extend(fuzzy_match(
+                        path,
+                        &missing,
+                        normalizer,
+                        args.fuzzy_threshold,
+                    ));
+                }
+                matched
+            }
+            Step::Online | Step::Fuzzy => continue,
+        };
+
+        if *step != Step::Correction {
+            let before = resolved.len();
+            resolved.retain(|_, resolution| {
+                !excluded_pageids.contains(&resolution.pageid)
+                    && !resolution.qid.is_some_and(|x| excluded_qids.contains(&x))
+            });
+            excluded += before - resolved.len();
+        }
+
+        if !resolved.is_empty() && !matches!(step, Step::Correction | Step::Mapping) {
+            run.warn(
+                Warning::FallbackResolution,
+                format!(
+                    "Resolved {} of {} remaining titles through the {step:?} resolver.",
+                    resolved.len(),
+                    titles.len() - mapping.len()
+                ),
+            );
+        }
+        mapping.extend(resolved);
+    }
+
+    if excluded > 0 {
+        run.warn(
+            Warning::SuspiciousTarget,
+            format!(
+                "Excluded {excluded} titles that the other resolvers resolved to disambiguation or list pages."
+            ),
+        );
+    }
+
+    if let Some(path) = &args.qid_redirects {
+        let qid_redirects = load_qid_redirects(path);
+
+        let mut remapped = 0;
+        for resolution in mapping.values_mut() {
+            if let Some(old) = resolution.qid {
+                let new = resolve_qid(old, &qid_redirects);
+                if new != old {
+                    resolution.qid = Some(new);
+                    remapped += 1;
+                }
+            }
+        }
+
+        if remapped > 0 {
+            run.warn(
+                Warning::ObsoleteQid,
+                format!("Remapped {remapped} obsolete QIDs through Wikidata redirects."),
+            );
+        }
+    }
+
+    mapping
+}
+
+/// Writes a TSV report with the resolver that resolved every title, together with the resolution itself.
+pub fn write_resolution_report(mapping: &HashMap<String, Resolution>, path: &str) {
+    let mut resolutions = mapping.iter().collect::<Vec<_>>();
+    resolutions.sort_unstable_by_key(|(title, _)| *title);
+
+    let mut writer = BufWriter::new(exit::create(path));
+    writeln!(writer, "title\tresolver\tpageid\tqid").unwrap();
+
+    for (title, resolution) in resolutions {
+        let qid = resolution.qid.map(|x| format!("Q{x}")).unwrap_or_default();
+        writeln!(
+            writer,
+            "{title}\t{}\t{}\t{qid}",
+            resolution.resolver.name(),
+            resolution.pageid
+        )
+        .unwrap();
+    }
+}
+
+/// Writes a TSV file mapping every QID in the dataset back to its pageid and title.
+///
+/// When several titles resolve to the same QID, the one resolved directly rather than
+/// through a redirect is preferred, and then the alphabetically first one.
+pub fn write_qid_index(mapping: &HashMap<String, Resolution>, path: &str) {
+    let mut index = HashMap::<u32, (&str, Resolution)>::new();
+
+    for (title, resolution) in mapping {
+        let Some(qid) = resolution.qid else {
+            continue;
+        };
+        let key = (resolution.resolver == Resolver::Redirect, title.as_str());
+
+        let replace = index
+            .get(&qid)
+            .is_none_or(|(x, y)| key < (y.resolver == Resolver::Redirect, *x));
+        if replace {
+            index.insert(qid, (title, *resolution));
+        }
+    }
+
+    let mut index = index.into_iter().collect::<Vec<_>>();
+    index.sort_unstable_by_key(|(qid, _)| *qid);
+
+    let mut writer = BufWriter::new(exit::create(path));
+    writeln!(writer, "qid\tpageid\ttitle").unwrap();
+
+    for (qid, (title, resolution)) in index {
+        writeln!(writer, "Q{qid}\t{}\t{title}", resolution.pageid).unwrap();
+    }
+}
+
+/// Looks for the QIDs of the titles that resolved to a page without one, through the
+/// redirects, the page properties, and, if enabled with `--online`, the Wikipedia API.
+pub fn suggest_corrections(
+    args: &Args,
+    mapping: &HashMap<String, Resolution>,
+    normalizer: &TitleNormalizer,
+    run: &mut RunReport,
+) -> Vec<Suggestion> {
+    let mut missing = mapping
+        .iter()
+        .filter(|(_, resolution)| resolution.qid.is_none())
+        .map(|(title, resolution)| (title.clone(), resolution.pageid))
+        .collect::<Vec<_>>();
+    missing.sort_unstable();
+
+    if missing.is_empty() {
+        return vec![];
+    }
+
+    let titles = missing
+        .iter()
+        .map(|(title, _)| title.clone())
+        .collect::<HashSet<_>>();
+    let pageids = missing
+        .iter()
+        .map(|(_, pageid)| *pageid)
+        .collect::<HashSet<_>>();
+
+    let redirects = match &args.redirects {
+        Some(path) => load_redirects(path, &titles, normalizer),
+        None => HashMap::new(),
+    };
+    let targets = redirects.values().cloned().collect::<HashSet<_>>();
+
+    let mut target_qids = HashMap::new();
+    if !targets.is_empty() {
+        let cache = args.mapping_cache();
+        for path in &args.input_wiki2qid {
+            let entries =
+                load_filtered_mapping(path, &targets, &HashSet::new(), normalizer, cache, run);
+            for entry in entries {
+                if let Some(qid) = entry.qid {
+                    target_qids.insert(entry.title, (entry.pageid, qid));
+                }
+            }
+        }
+    }
+
+    let page_items = match &args.page_props {
+        Some(path) => load_page_items(path, &pageids),
+        None => HashMap::new(),
+    };
+
+    let online = if args.online {
+        let titles = missing
+            .iter()
+            .map(|(title, _)| title.clone())
+            .collect::<Vec<_>>();
+        OnlineResolver::new(&args.cache_dir, args.http_client(), run).resolve(&titles)
+    } else {
+        HashMap::new()
+    };
+
+    missing
+        .into_iter()
+        .filter_map(|(title, pageid)| {
+            if let Some(target) = redirects.get(&title) {
+                if let Some(&(pageid, qid)) = target_qids.get(target) {
+                    let source = format!("redirect to {target}");
+                    return Some(Suggestion {
+                        title,
+                        pageid,
+                        qid,
+                        source,
+                    });
+                }
+            }
+            if let Some(&qid) = page_items.get(&pageid) {
+                let source = format!("wikibase_item of page {pageid}");
+                return Some(Suggestion {
+                    title,
+                    pageid,
+                    qid,
+                    source,
+                });
+            }
+            let resolution = online.get(&title)?;
+            Some(Suggestion {
+                pageid: resolution.pageid,
+                qid: resolution.qid?,
+                source: "the Wikipedia API".to_owned(),
+                title,
+            })
+        })
+        .collect()
+}
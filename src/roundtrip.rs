@@ -0,0 +1,130 @@
+use crate::dataset::{read_split, split_path, Document, Mention, SPLITS};
+use crate::exit::{fail, Failure};
+use crate::export::{export, ExportFormat, ImportFormat};
+use hashbrown::HashMap;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// Runs the conversion of the TSV files `input_aida` and `input_conll` into `output_dir` with
+/// the other options `args`.
+fn convert(input_aida: &Path, input_conll: &Path, output_dir: &Path, args: &[String]) {
+    fs::create_dir_all(output_dir).unwrap();
+
+    let status = Command::new(std::env::current_exe().unwrap())
+        .arg("convert")
+        .arg("--input-aida")
+        .arg(input_aida)
+        .arg("--input-conll")
+        .arg(input_conll)
+        .arg("--output-dir")
+        .arg(output_dir)
+        .args(args)
+        .args(["--overwrite", "--quiet"])
+        .status()
+        .unwrap();
+    if !status.success() {
+        fail(
+            Failure::Other,
+            format!(
+                "The conversion into {} failed with the exit code {}.",
+                output_dir.display(),
+                status.code().unwrap_or(1)
+            ),
+        );
+    }
+}
+
+/// Returns the entities of `document` sorted by their spans.
+fn sorted_entities(document: &Document) -> Vec<&Mention> {
+    let mut entities = document.entities.iter().collect::<Vec<_>>();
+    entities.sort_by_key(|x| (x.start, x.end));
+    entities
+}
+
+/// Prints every document of the datasets in `dir_a` and `dir_b` whose text or entities,
+/// with all their fields, differ, and returns their number.
+fn compare_datasets(dir_a: &Path, dir_b: &Path) -> usize {
+    let mut differences = 0;
+
+    for name in SPLITS {
+        let documents =
+            |dir| split_path(dir, name).map_or_else(Vec::new, |x| read_split(&x).documents);
+        let (documents_a, documents_b) = (documents(dir_a), documents(dir_b));
+        let mut by_id = documents_b
+            .iter()
+            .map(|x| (x.document_id, x))
+            .collect::<HashMap<_, _>>();
+
+        for a in &documents_a {
+            let id = a.document_id;
+            let Some(b) = by_id.remove(&id) else {
+                println!("{name}\t{id}\tlost");
+                differences += 1;
+                continue;
+            };
+
+            if a.text != b.text {
+                println!("{name}\t{id}\ttext differs\t{:?}\t{:?}", a.text, b.text);
+                differences += 1;
+                continue;
+            }
+
+            let (entities_a, entities_b) = (sorted_entities(a), sorted_entities(b));
+            if entities_a != entities_b {
+                let format = |document: &Document, entities: &[&Mention]| {
+                    entities
+                        .iter()
+                        .map(|x| document.mention_json(x).to_string())
+                        .collect::<Vec<_>>()
+                        .join(",")
+                };
+                println!(
+                    "{name}\t{id}\tentities differ\t[{}]\t[{}]",
+                    format(a, &entities_a),
+                    format(b, &entities_b)
+                );
+                differences += 1;
+            }
+        }
+
+        let mut added = by_id.into_keys().collect::<Vec<_>>();
+        added.sort_unstable();
+        for id in added {
+            println!("{name}\t{id}\tadded");
+            differences += 1;
+        }
+    }
+
+    differences
+}
+
+/// Converts the TSV files into Parquet with the options `args`, exports the dataset back into
+/// the TSV format, converts that into Parquet again, and prints the documents that differ
+/// between the two datasets, which were lost or changed by the conversion or the export.
+///
+/// Returns whether the datasets are the same.
+pub fn verify_roundtrip(input_aida: &str, input_conll: &str, args: &[String]) -> bool {
+    let dir = std::env::temp_dir().join(format!("aida-roundtrip-{}", std::process::id()));
+    let (first, tsv, second) = (dir.join("first"), dir.join("tsv"), dir.join("second"));
+
+    convert(Path::new(input_aida), Path::new(input_conll), &first, args);
+    export(
+        &first.to_string_lossy(),
+        ImportFormat::Parquet,
+        ExportFormat::Aida,
+        &tsv.to_string_lossy(),
+        false,
+    );
+    convert(&tsv.join("aida.tsv"), &tsv.join("conll.tsv"), &second, args);
+
+    let differences = compare_datasets(&first, &second);
+    fs::remove_dir_all(&dir).ok();
+
+    if differences == 0 {
+        eprintln!("The round trip through the TSV format preserved every document.");
+    } else {
+        eprintln!("The round trip through the TSV format changed {differences} documents.");
+    }
+    differences == 0
+}
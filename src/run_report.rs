@@ -0,0 +1,257 @@
+use crate::coverage::Coverage;
+use crate::exit::{self, fail, Failure};
+use crate::manifest::ManifestEntry;
+use crate::mapping::{Resolution, Resolver};
+use crate::DataPoint;
+use clap::ValueEnum;
+use hashbrown::HashMap;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::BufWriter;
+use std::time::Instant;
+
+/// The categories of the data-quality warnings of the conversion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Warning {
+    /// Titles resolved to disambiguation or list pages.
+    SuspiciousTarget,
+    /// Titles resolved to pages without a QID, for which corrections were suggested.
+    SuggestedCorrection,
+    /// Titles resolved outside the mapping snapshot.
+    StaleTitle,
+    /// Titles resolved by a resolver after the corrections and the mappings, e.g. a redirect.
+    FallbackResolution,
+    /// Titles that couldn't be resolved.
+    UnmappedTitle,
+    /// QIDs that differ from the ones on Wikidata.
+    QidDiscrepancy,
+    /// Obsolete QIDs remapped through the Wikidata redirects.
+    ObsoleteQid,
+    /// QIDs that differ from the ones of the sitelinks.
+    SitelinkConflict,
+    /// Documents whose tokens couldn't be aligned with their raw texts.
+    MisalignedRawText,
+    /// Malformed lines of the inputs, skipped under `--lenient`.
+    MalformedLine,
+    /// Documents whose IDs or texts appear more than once, or that are in more than one split.
+    DuplicateDocument,
+    /// Titles whose pageids in the mappings differ from the ones in the TSV.
+    PageidDisagreement,
+    /// Tokens with whitespace, control characters, or zero-width characters.
+    ProblematicToken,
+    /// Caches that couldn't be written, or read back, so the work they'd have saved is repeated.
+    UnwritableCache,
+}
+
+impl Warning {
+    fn name(self) -> String {
+        self.to_possible_value().unwrap().get_name().to_owned()
+    }
+}
+
+/// Turns the warnings of a category, or of every category if it's `None`, into errors,
+/// given with `-W error` or `-W <category>=error`.
+#[derive(Debug, Clone, Copy)]
+pub struct WarningsAsErrors(Option<Warning>);
+
+impl WarningsAsErrors {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let category = match spec.strip_suffix("error") {
+            Some("") => return Ok(Self(None)),
+            Some(category) => category.strip_suffix('='),
+            None => None,
+        };
+        let Some(category) = category else {
+            return Err("expected `error` or `<category>=error`".to_owned());
+        };
+
+        Warning::from_str(category, false).map(|x| Self(Some(x)))
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct RecordedWarning {
+    category: Warning,
+    message: String,
+    error: bool,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct SplitCounts {
+    documents: usize,
+    mentions: usize,
+    linked_mentions: usize,
+    nil_mentions: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct Timing {
+    stage: String,
+    seconds: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct OutputFile {
+    sha256: String,
+    bytes: u64,
+    rows: Option<usize>,
+}
+
+/// A machine-readable summary of a conversion, written with `--report-json`.
+#[derive(Debug, Serialize)]
+pub struct RunReport {
+    status: &'static str,
+    splits: BTreeMap<String, SplitCounts>,
+    /// The unresolved titles, with the number of their mentions.
+    unresolved: BTreeMap<String, usize>,
+    /// The number of titles resolved by every resolver.
+    resolvers: BTreeMap<&'static str, usize>,
+    /// The titles resolved through the corrections.
+    corrections: Vec<String>,
+    warnings: Vec<RecordedWarning>,
+    timings: Vec<Timing>,
+    outputs: BTreeMap<String, OutputFile>,
+    #[serde(skip)]
+    stage_start: Instant,
+    #[serde(skip)]
+    errors: Vec<WarningsAsErrors>,
+}
+
+impl RunReport {
+    pub fn new(errors: &[WarningsAsErrors]) -> Self {
+        Self {
+            status: "succeeded",
+            splits: BTreeMap::new(),
+            unresolved: BTreeMap::new(),
+            resolvers: BTreeMap::new(),
+            corrections: vec![],
+            warnings: vec![],
+            timings: vec![],
+            outputs: BTreeMap::new(),
+            stage_start: Instant::now(),
+            errors: errors.to_vec(),
+        }
+    }
+
+    /// Prints a warning and records it in the report, as an error if its category
+    /// is turned into errors.
+    pub fn warn(&mut self, category: Warning, message: String) {
+        let error = self
+            .errors
+            .iter()
+            .any(|x| x.0.is_none_or(|x| x == category));
+
+        eprintln!("{message}");
+        self.warnings.push(RecordedWarning {
+            category,
+            message,
+            error,
+        });
+    }
+
+    /// Returns the number of warnings so far.
+    pub fn warning_count(&self) -> usize {
+        self.warnings.len()
+    }
+
+    /// Exits, after writing the report to `path`, if any warnings were turned into errors.
+    pub fn check_errors(&mut self, path: Option<&str>) {
+        let mut categories = self
+            .warnings
+            .iter()
+            .filter(|x| x.error)
+            .map(|x| x.category.name())
+            .collect::<Vec<_>>();
+        categories.sort_unstable();
+        categories.dedup();
+        if categories.is_empty() {
+            return;
+        }
+
+        if let Some(path) = path {
+            self.fail();
+            self.write(path);
+        }
+        fail(
+            Failure::Warnings,
+            format!(
+                "Failing because of warnings turned into errors with -W: {}.",
+                categories.join(", ")
+            ),
+        );
+    }
+
+    /// Records the time since the end of the previous stage as the duration of `stage`, or adds
+    /// it to the duration of `stage` if it already ended, since the splits are generated and
+    /// written in turns.
+    pub fn end_stage(&mut self, stage: &str) {
+        let seconds = self.stage_start.elapsed().as_secs_f64();
+        match self.timings.iter_mut().find(|x| x.stage == stage) {
+            Some(timing) => timing.seconds += seconds,
+            None => self.timings.push(Timing {
+                stage: stage.to_owned(),
+                seconds,
+            }),
+        }
+        self.stage_start = Instant::now();
+    }
+
+    pub fn add_resolutions(&mut self, mapping: &HashMap<String, Resolution>) {
+        for (title, resolution) in mapping {
+            *self
+                .resolvers
+                .entry(resolution.resolver.name())
+                .or_default() += 1;
+            if resolution.resolver == Resolver::Correction {
+                self.corrections.push(title.clone());
+            }
+        }
+        self.corrections.sort_unstable();
+    }
+
+    pub fn add_coverage(&mut self, coverage: &Coverage) {
+        self.unresolved = coverage
+            .unmapped
+            .iter()
+            .map(|(title, unmapped)| (title.clone(), unmapped.mentions))
+            .collect();
+    }
+
+    /// Adds the counts of `split`, which may be a part of the split `name`.
+    pub fn add_split(&mut self, name: &str, split: &[DataPoint]) {
+        let counts = self.splits.entry(name.to_owned()).or_default();
+        counts.documents += split.len();
+
+        for entity in split.iter().flat_map(|x| &x.entities) {
+            counts.mentions += 1;
+            if entity.qid.is_some() {
+                counts.linked_mentions += 1;
+            } else {
+                counts.nil_mentions += 1;
+            }
+        }
+    }
+
+    pub fn add_outputs(&mut self, entries: &[ManifestEntry]) {
+        for entry in entries {
+            self.outputs.insert(
+                entry.name.clone(),
+                OutputFile {
+                    sha256: entry.sha256.clone(),
+                    bytes: entry.bytes,
+                    rows: entry.rows,
+                },
+            );
+        }
+    }
+
+    pub fn fail(&mut self) {
+        self.status = "failed";
+    }
+
+    pub fn write(&self, path: &str) {
+        let writer = BufWriter::new(exit::create(path));
+        serde_json::to_writer_pretty(writer, self).unwrap();
+    }
+}
@@ -0,0 +1,65 @@
+use crate::dataset::read_dataset;
+use crate::exit::{fail, Failure};
+use crate::random::rng;
+use rand::seq::SliceRandom;
+use serde_json::json;
+
+/// Prints `n` random documents, or mentions if `mentions` is set, of the generated
+/// dataset as JSON, one per line.
+///
+/// The sample is drawn from every split unless `split` is given, and is the same
+/// across runs with the same `seed`.
+pub fn print_sample(input: &str, n: usize, split: Option<&str>, seed: Option<u64>, mentions: bool) {
+    let splits = read_dataset(input)
+        .into_iter()
+        .filter(|x| split.is_none_or(|split| x.name == split))
+        .collect::<Vec<_>>();
+    if splits.is_empty() {
+        fail(
+            Failure::InputNotFound,
+            format!("No split {} in {input}.", split.unwrap_or_default()),
+        );
+    }
+
+    let mut rng = rng(seed);
+
+    let documents = splits
+        .iter()
+        .flat_map(|split| split.documents.iter().map(|x| (split.name.as_str(), x)))
+        .collect::<Vec<_>>();
+
+    let values = if mentions {
+        let mentions = documents
+            .iter()
+            .flat_map(|(split, document)| {
+                document
+                    .entities
+                    .iter()
+                    .map(move |mention| (*split, *document, mention))
+            })
+            .collect::<Vec<_>>();
+
+        mentions
+            .choose_multiple(&mut rng, n)
+            .map(|(split, document, mention)| {
+                let mut value = document.mention_json(mention);
+                value["split"] = json!(split);
+                value["document_id"] = json!(document.document_id);
+                value
+            })
+            .collect::<Vec<_>>()
+    } else {
+        documents
+            .choose_multiple(&mut rng, n)
+            .map(|(split, document)| {
+                let mut value = document.to_json();
+                value["split"] = json!(split);
+                value
+            })
+            .collect()
+    };
+
+    for value in values {
+        println!("{value}");
+    }
+}
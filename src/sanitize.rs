@@ -0,0 +1,91 @@
+use crate::TokenRecord;
+
+/// Returns whether `c` is a zero-width character, which is invisible but still counts in the
+/// offsets.
+fn is_zero_width(c: char) -> bool {
+    matches!(
+        c,
+        '\u{200B}' | '\u{200C}' | '\u{200D}' | '\u{2060}' | '\u{FEFF}' | '\u{00AD}'
+    )
+}
+
+/// Returns whether `c` is a control character other than whitespace.
+fn is_control(c: char) -> bool {
+    c.is_control() && !c.is_whitespace()
+}
+
+/// Returns the problems with `token`: whitespace, which splits it when the text is split on
+/// spaces again, and control or zero-width characters, which are invisible.
+fn token_problems(token: &str) -> Vec<&'static str> {
+    let mut problems = vec![];
+    if token.chars().any(char::is_whitespace) {
+        problems.push("whitespace");
+    }
+    if token.chars().any(is_control) {
+        problems.push("control characters");
+    }
+    if token.chars().any(is_zero_width) {
+        problems.push("zero-width characters");
+    }
+    problems
+}
+
+/// Returns `token` without control and zero-width characters, and with its runs of whitespace
+/// replaced by underscores, or `token` itself if nothing would be left of it.
+fn sanitize_token(token: &str) -> String {
+    let mut sanitized = String::new();
+    let mut whitespace = false;
+    for c in token
+        .trim()
+        .chars()
+        .filter(|&c| !is_zero_width(c) && !is_control(c))
+    {
+        if c.is_whitespace() {
+            whitespace = true;
+            continue;
+        }
+        if whitespace && !sanitized.is_empty() {
+            sanitized.push('_');
+        }
+        whitespace = false;
+        sanitized.push(c);
+    }
+
+    if sanitized.is_empty() {
+        token.to_owned()
+    } else {
+        sanitized
+    }
+}
+
+/// Finds the tokens of the `splits` with whitespace, control characters, or zero-width
+/// characters, and sanitizes them if `sanitize` is set.
+///
+/// Returns the problematic tokens, as `split: document: "token" has problems`, followed by
+/// `-> "token"` if they were sanitized.
+pub fn check_tokens(splits: &mut [(&str, &mut Vec<TokenRecord>)], sanitize: bool) -> Vec<String> {
+    let mut reports = vec![];
+
+    for (name, split) in splits {
+        for record in split.iter_mut() {
+            let problems = token_problems(&record.token);
+            if problems.is_empty() {
+                continue;
+            }
+
+            let mut report = format!(
+                "{name}: document {}: {:?} has {}",
+                record.document_id,
+                record.token,
+                problems.join(" and ")
+            );
+            if sanitize {
+                record.token = sanitize_token(&record.token).into();
+                report.push_str(&format!(" -> {:?}", record.token));
+            }
+            reports.push(report);
+        }
+    }
+
+    reports
+}
@@ -0,0 +1,70 @@
+use std::fs;
+use std::ops::RangeInclusive;
+
+/// A selection of documents by their IDs, given as a comma-separated list of IDs and
+/// inclusive ranges of IDs (e.g. `1-200,946,1163`).
+#[derive(Debug, Clone, Default)]
+pub struct DocumentSelection {
+    ranges: Vec<RangeInclusive<u32>>,
+}
+
+impl DocumentSelection {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut selection = Self::default();
+        selection.extend(spec)?;
+        Ok(selection)
+    }
+
+    /// Reads a selection from a file with one ID or range of IDs per line (or several,
+    /// separated by commas); empty lines and lines starting with `#` are skipped.
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|error| format!("{path}: {error}"))?;
+
+        let mut selection = Self::default();
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            selection
+                .extend(line)
+                .map_err(|error| format!("{path}:{}: {error}", i + 1))?;
+        }
+
+        Ok(selection)
+    }
+
+    fn extend(&mut self, spec: &str) -> Result<(), String> {
+        let parse = |id: &str| {
+            id.trim()
+                .parse::<u32>()
+                .map_err(|_| format!("invalid document ID {:?}", id.trim()))
+        };
+
+        for part in spec.split(',').filter(|x| !x.trim().is_empty()) {
+            let range = match part.split_once('-') {
+                Some((start, end)) => parse(start)?..=parse(end)?,
+                None => {
+                    let id = parse(part)?;
+                    id..=id
+                }
+            };
+            if range.is_empty() {
+                return Err(format!("empty range of document IDs {:?}", part.trim()));
+            }
+            self.ranges.push(range);
+        }
+
+        Ok(())
+    }
+
+    /// Returns a selection of the documents in either of the selections.
+    pub fn union(mut self, other: Self) -> Self {
+        self.ranges.extend(other.ranges);
+        self
+    }
+
+    pub fn contains(&self, document_id: u32) -> bool {
+        self.ranges.iter().any(|x| x.contains(&document_id))
+    }
+}
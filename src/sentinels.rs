@@ -0,0 +1,10 @@
+/// The strings that mark a mention as NIL in the title or URL cells of the common releases of
+/// the AIDA CoNLL-YAGO dataset, besides an empty cell.
+pub const NIL_SENTINELS: [&str; 2] = ["--NME--", "NIL"];
+
+/// Returns whether the cell `cell` of a mention marks it as NIL, i.e. it's empty or one of the
+/// `sentinels`.
+pub fn is_nil<S: AsRef<str>>(cell: &str, sentinels: &[S]) -> bool {
+    let cell = cell.trim();
+    cell.is_empty() || sentinels.iter().any(|x| x.as_ref() == cell)
+}
@@ -0,0 +1,127 @@
+use crate::dataset::{read_dataset, StoredSplit};
+use crate::exit::{fail, Failure};
+use crate::redirects::parse_qid;
+use crate::stats::stats_json;
+use hashbrown::HashMap;
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Response, Server};
+
+/// The generated dataset with the indices the endpoints are answered from.
+struct Index {
+    splits: Vec<StoredSplit>,
+    /// The split and position of every document, by its ID.
+    documents: HashMap<u32, (usize, usize)>,
+    /// The split, document, and position of every mention, by the QID it's linked to.
+    mentions: HashMap<u32, Vec<(usize, usize, usize)>>,
+    stats: Value,
+}
+
+impl Index {
+    fn new(splits: Vec<StoredSplit>) -> Self {
+        let mut documents = HashMap::new();
+        let mut mentions = HashMap::<u32, Vec<_>>::new();
+
+        for (i, split) in splits.iter().enumerate() {
+            for (j, document) in split.documents.iter().enumerate() {
+                documents.insert(document.document_id, (i, j));
+                for (k, mention) in document.entities.iter().enumerate() {
+                    if let Some(qid) = mention.qid {
+                        mentions.entry(qid).or_default().push((i, j, k));
+                    }
+                }
+            }
+        }
+
+        let stats = stats_json(&splits);
+
+        Self {
+            splits,
+            documents,
+            mentions,
+            stats,
+        }
+    }
+
+    fn document(&self, id: &str) -> Option<Value> {
+        let (i, j) = self.documents.get(&id.parse::<u32>().ok()?)?;
+        let split = &self.splits[*i];
+
+        let mut value = split.documents[*j].to_json();
+        value["split"] = json!(split.name);
+        Some(value)
+    }
+
+    fn mentions(&self, qid: &str) -> Option<Value> {
+        let qid = parse_qid(qid)?;
+
+        let mentions = self
+            .mentions
+            .get(&qid)
+            .into_iter()
+            .flatten()
+            .map(|(i, j, k)| {
+                let split = &self.splits[*i];
+                let document = &split.documents[*j];
+
+                let mut value = document.mention_json(&document.entities[*k]);
+                value["split"] = json!(split.name);
+                value["document_id"] = json!(document.document_id);
+                value
+            })
+            .collect::<Vec<_>>();
+
+        Some(Value::Array(mentions))
+    }
+
+    /// Returns the status code and the body of the response to a GET request of `path`.
+    fn respond(&self, path: &str) -> (u16, Value) {
+        let path = path.split('?').next().unwrap_or_default();
+        let segments = path
+            .split('/')
+            .filter(|x| !x.is_empty())
+            .collect::<Vec<_>>();
+
+        let body = match segments.as_slice() {
+            ["documents", id] => self.document(id),
+            ["entities", qid, "mentions"] => self.mentions(qid),
+            ["stats"] => Some(self.stats.clone()),
+            _ => None,
+        };
+
+        match body {
+            Some(body) => (200, body),
+            None => (404, json!({ "error": format!("{path} not found") })),
+        }
+    }
+}
+
+/// Serves the generated dataset in `input` over HTTP at `address`, answering with JSON at
+/// `/documents/{id}`, `/entities/{qid}/mentions`, and `/stats`.
+pub fn serve(input: &str, address: &str) {
+    let index = Index::new(read_dataset(input));
+
+    let server = match Server::http(address) {
+        Ok(server) => server,
+        Err(error) => {
+            fail(
+                Failure::Other,
+                format!("Couldn't listen on {address}: {error}."),
+            );
+        }
+    };
+    eprintln!("Serving {input} at http://{address}.");
+
+    for request in server.incoming_requests() {
+        let (status, body) = if *request.method() == Method::Get {
+            index.respond(request.url())
+        } else {
+            (405, json!({ "error": "only GET requests are supported" }))
+        };
+
+        let response = Response::from_string(body.to_string())
+            .with_status_code(status)
+            .with_header(Header::from_bytes("Content-Type", "application/json").unwrap())
+            .with_header(Header::from_bytes("Access-Control-Allow-Origin", "*").unwrap());
+        request.respond(response).ok();
+    }
+}
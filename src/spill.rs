@@ -0,0 +1,244 @@
+use crate::exit::{fail, Failure};
+use crate::intern::Interner;
+use crate::records::{read_length, read_record, write_records};
+use crate::TokenRecord;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Seek, Write};
+use std::mem;
+use std::path::{Path, PathBuf};
+
+/// The records of a split, either in memory or spilled into a file until they're needed.
+pub enum SplitRecords {
+    Memory(Vec<TokenRecord>),
+    /// The file, which is removed as soon as it's created, so it's left behind by no run
+    /// on Unix, its path, which is removed once the records are dropped on other platforms, and
+    /// the number of chunks of records written into it.
+    Disk(File, PathBuf, usize),
+}
+
+impl SplitRecords {
+    /// Returns the empty records of the split `name`, which are spilled into a file in `dir` as
+    /// they're added if it's given, and kept in memory otherwise.
+    pub fn new(dir: Option<&Path>, name: &str) -> Self {
+        let Some(dir) = dir else {
+            return Self::Memory(vec![]);
+        };
+
+        let path = dir.join(format!("{}-{name}.records", std::process::id()));
+        let result = fs::create_dir_all(dir).and_then(|_| {
+            File::options()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(&path)
+        });
+
+        match result {
+            Ok(file) => {
+                let _ = fs::remove_file(&path);
+                Self::Disk(file, path, 0)
+            }
+            Err(error) => fail(
+                Failure::Write,
+                format!(
+                    "Couldn't spill the {name} split into {}: {error}.",
+                    path.display()
+                ),
+            ),
+        }
+    }
+
+    /// Adds `records` after the ones added before, writing them into the file right away if
+    /// they're spilled.
+    pub fn append(&mut self, mut records: Vec<TokenRecord>) {
+        match self {
+            Self::Memory(split) if split.is_empty() => *split = records,
+            Self::Memory(split) => split.append(&mut records),
+            Self::Disk(file, path, chunks) => {
+                let mut writer = BufWriter::new(file);
+                if let Err(error) =
+                    write_records(&mut writer, &records).and_then(|_| writer.flush())
+                {
+                    fail(
+                        Failure::Write,
+                        format!(
+                            "Couldn't spill the records into {}: {error}.",
+                            path.display()
+                        ),
+                    );
+                }
+                *chunks += 1;
+            }
+        }
+    }
+
+    /// Returns the records, if they're in memory.
+    pub fn in_memory(&self) -> Option<&[TokenRecord]> {
+        match self {
+            Self::Memory(split) => Some(split),
+            Self::Disk(..) => None,
+        }
+    }
+
+    /// Returns the records, `documents` whole documents at a time. The spilled records are read
+    /// back one at a time, from the start of the file every time they're returned, while the ones
+    /// in memory are moved out at once.
+    pub fn documents(&mut self, documents: usize) -> impl Iterator<Item = Vec<TokenRecord>> + '_ {
+        let (memory, spill) = match self {
+            Self::Memory(split) => (Some(mem::take(split)), None),
+            Self::Disk(file, path, chunks) => {
+                if let Err(error) = file.rewind() {
+                    read_failure(path, error);
+                }
+                let spill = SpillReader {
+                    reader: BufReader::new(file),
+                    path,
+                    chunks: *chunks,
+                    records: 0,
+                    titles: Interner::default(),
+                    words: Interner::default(),
+                };
+                (None, Some(spill))
+            }
+        };
+
+        Documents {
+            memory,
+            spill,
+            documents,
+            next: None,
+        }
+    }
+}
+
+impl Drop for SplitRecords {
+    fn drop(&mut self) {
+        if let Self::Disk(_, path, _) = self {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// The records of a split, returned by [`SplitRecords::documents`].
+struct Documents<'a> {
+    memory: Option<Vec<TokenRecord>>,
+    spill: Option<SpillReader<'a>>,
+    /// The number of documents returned at a time.
+    documents: usize,
+    /// The first record of the documents after the ones returned last.
+    next: Option<TokenRecord>,
+}
+
+impl Iterator for Documents<'_> {
+    type Item = Vec<TokenRecord>;
+
+    fn next(&mut self) -> Option<Vec<TokenRecord>> {
+        if let Some(split) = self.memory.take() {
+            return (!split.is_empty()).then_some(split);
+        }
+        let spill = self.spill.as_mut()?;
+
+        let mut records = Vec::<TokenRecord>::new();
+        let mut documents = 0;
+        while let Some(record) = self.next.take().or_else(|| spill.read()) {
+            if records.last().is_none_or(|x| x.document != record.document) {
+                if documents == self.documents {
+                    self.next = Some(record);
+                    break;
+                }
+                documents += 1;
+            }
+            records.push(record);
+        }
+
+        (!records.is_empty()).then_some(records)
+    }
+}
+
+/// Reads the spilled records one at a time, with the numbers of the chunks and of the records of
+/// the current chunk left to read, and the interners of the titles and the tokens read so far.
+struct SpillReader<'a> {
+    reader: BufReader<&'a File>,
+    path: &'a Path,
+    chunks: usize,
+    records: usize,
+    titles: Interner,
+    words: Interner,
+}
+
+impl SpillReader<'_> {
+    fn read(&mut self) -> Option<TokenRecord> {
+        let result = (|| {
+            while self.records == 0 {
+                if self.chunks == 0 {
+                    return Ok(None);
+                }
+                self.records = read_length(&mut self.reader)?;
+                self.chunks -= 1;
+            }
+            self.records -= 1;
+            read_record(&mut self.reader, &mut self.titles, &mut self.words).map(Some)
+        })();
+        result.unwrap_or_else(|error| read_failure(self.path, error))
+    }
+}
+
+fn read_failure(path: &Path, error: io::Error) -> ! {
+    fail(
+        Failure::Other,
+        format!(
+            "Couldn't read the spilled records from {}: {error}.",
+            path.display()
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EntityType;
+    use std::sync::Arc;
+
+    fn records(document: u32, tokens: &[&str]) -> Vec<TokenRecord> {
+        tokens
+            .iter()
+            .map(|token| TokenRecord {
+                document_id: document + 1,
+                document,
+                sentence: 0,
+                token: Arc::from(*token),
+                entity: EntityType::None,
+                begins: false,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn spilled_records_are_read_back_in_whole_documents() {
+        let dir = std::env::temp_dir().join(format!("spill-{}", std::process::id()));
+        let mut split = SplitRecords::new(Some(&dir), "train");
+        let mut first = records(0, &["EU", "rejects"]);
+        first.extend(records(1, &["Paris"]));
+        split.append(first);
+        // A document may be continued in the next chunk.
+        split.append(records(2, &["Independence"]));
+        split.append(records(2, &["Day"]));
+        assert!(split.in_memory().is_none());
+
+        // The records are read from the start every time.
+        for _ in 0..2 {
+            let documents = split
+                .documents(2)
+                .map(|x| x.iter().map(|x| x.token.to_string()).collect::<Vec<_>>())
+                .collect::<Vec<_>>();
+            assert_eq!(
+                documents,
+                [vec!["EU", "rejects", "Paris"], vec!["Independence", "Day"]]
+            );
+        }
+
+        drop(split);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
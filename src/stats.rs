@@ -0,0 +1,211 @@
+use crate::dataset::{read_dataset, StoredSplit, SPLITS};
+use crate::docstart::parse_docstart;
+use crate::exit::{self, fail, Failure};
+use crate::layout::{Fields, TsvLayout};
+use hashbrown::HashSet;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// The width of the buckets of the document length histogram, in tokens.
+const DOCUMENT_BUCKET: usize = 100;
+/// The mention lengths, in tokens, from which on the mentions are counted together.
+const MAX_MENTION_LENGTH: usize = 10;
+
+#[derive(Debug, Default)]
+struct SplitStats {
+    documents: usize,
+    mentions: usize,
+    nil: usize,
+    entities: HashSet<String>,
+    mention_lengths: BTreeMap<usize, usize>,
+    document_lengths: BTreeMap<usize, usize>,
+}
+
+impl SplitStats {
+    fn add_document(&mut self, tokens: usize) {
+        self.documents += 1;
+        *self
+            .document_lengths
+            .entry(tokens / DOCUMENT_BUCKET)
+            .or_default() += 1;
+    }
+
+    fn add_mention(&mut self, tokens: usize, entity: Option<String>) {
+        self.mentions += 1;
+        *self
+            .mention_lengths
+            .entry(tokens.min(MAX_MENTION_LENGTH))
+            .or_default() += 1;
+
+        match entity {
+            Some(entity) => {
+                self.entities.insert(entity);
+            }
+            None => self.nil += 1,
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "documents": self.documents,
+            "mentions": self.mentions,
+            "nil_mentions": self.nil,
+            "unique_entities": self.entities.len(),
+        })
+    }
+
+    fn print(&self, name: &str) {
+        println!("{name}");
+        println!("  documents: {}", self.documents);
+        println!("  mentions: {}", self.mentions);
+        println!("  NIL mentions: {}", self.nil);
+        println!("  unique entities: {}", self.entities.len());
+
+        println!("  mention lengths (tokens):");
+        for (length, count) in &self.mention_lengths {
+            let plus = if *length == MAX_MENTION_LENGTH {
+                "+"
+            } else {
+                ""
+            };
+            println!("    {length}{plus}: {count}");
+        }
+
+        println!("  document lengths (tokens):");
+        for (bucket, count) in &self.document_lengths {
+            println!(
+                "    {}-{}: {count}",
+                bucket * DOCUMENT_BUCKET,
+                (bucket + 1) * DOCUMENT_BUCKET - 1
+            );
+        }
+    }
+}
+
+fn end_mention(
+    splits: &mut BTreeMap<u8, SplitStats>,
+    split: u8,
+    mention: &mut Option<(usize, Option<String>)>,
+) {
+    if let Some((length, entity)) = mention.take() {
+        splits.entry(split).or_default().add_mention(length, entity);
+    }
+}
+
+/// Computes the statistics of the splits of the AIDA CoNLL-YAGO dataset in the TSV format.
+fn tsv_stats(path: &str) -> Vec<(String, SplitStats)> {
+    let mut splits = BTreeMap::<u8, SplitStats>::new();
+    let reader = BufReader::new(exit::open(path));
+
+    let mut split = 0;
+    let mut tokens = None;
+    let mut mention: Option<(usize, Option<String>)> = None;
+    let layout = TsvLayout::default();
+
+    for (i, line) in reader.lines().map(|x| x.unwrap()).enumerate() {
+        if let Some(docstart) = parse_docstart(line.trim_end()) {
+            let docstart = docstart.unwrap_or_else(|problem| {
+                fail(
+                    Failure::Parse,
+                    format!("{path}:{}: {problem}: {line:?}.", i + 1),
+                )
+            });
+
+            end_mention(&mut splits, split, &mut mention);
+            if let Some(tokens) = tokens.take() {
+                splits.entry(split).or_default().add_document(tokens);
+            }
+
+            split = docstart.split as u8;
+            tokens = Some(0);
+            continue;
+        }
+
+        if line.is_empty() {
+            continue;
+        }
+        *tokens.get_or_insert(0) += 1;
+
+        let fields = Fields::new(&line, b'\t');
+        match layout.mention(&fields) {
+            Some(false) => {
+                if let Some((length, _)) = &mut mention {
+                    *length += 1;
+                }
+            }
+            Some(true) => {
+                end_mention(&mut splits, split, &mut mention);
+                let entity = layout.title(&fields).map(|x| x.to_string());
+                mention = Some((1, entity));
+            }
+            _ => end_mention(&mut splits, split, &mut mention),
+        }
+    }
+
+    end_mention(&mut splits, split, &mut mention);
+    if let Some(tokens) = tokens {
+        splits.entry(split).or_default().add_document(tokens);
+    }
+
+    splits
+        .into_iter()
+        .map(|(split, stats)| (SPLITS[split as usize].to_owned(), stats))
+        .collect()
+}
+
+/// Computes the statistics of a split of the generated dataset in the Parquet format.
+fn parquet_stats(split: &StoredSplit) -> SplitStats {
+    let mut stats = SplitStats::default();
+
+    for document in &split.documents {
+        stats.add_document(document.text.split(' ').filter(|x| !x.is_empty()).count());
+
+        for mention in &document.entities {
+            let tokens = document.surface(mention).matches(' ').count() + 1;
+            let entity = match (mention.qid, &mention.title) {
+                (Some(qid), _) => Some(format!("Q{qid}")),
+                (None, Some(title)) => Some(title.clone()),
+                (None, None) => None,
+            };
+            stats.add_mention(tokens, entity);
+        }
+    }
+
+    stats
+}
+
+/// Returns the number of documents, mentions, NIL mentions, and unique entities of every
+/// split of the generated dataset as a JSON object keyed by the names of the splits.
+pub fn stats_json(splits: &[StoredSplit]) -> Value {
+    Value::Object(
+        splits
+            .iter()
+            .map(|split| (split.name.clone(), parquet_stats(split).to_json()))
+            .collect(),
+    )
+}
+
+/// Prints the statistics of every split of the dataset, either in the original
+/// TSV format, or the generated Parquet format if `input` is a directory or a
+/// Parquet file.
+pub fn print_stats(input: &str) {
+    let path = Path::new(input);
+
+    let splits = if path.is_dir() || path.extension().is_some_and(|x| x == "parquet") {
+        read_dataset(input)
+            .iter()
+            .map(|split| (split.name.clone(), parquet_stats(split)))
+            .collect()
+    } else {
+        tsv_stats(input)
+    };
+
+    for (i, (name, stats)) in splits.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        stats.print(name);
+    }
+}
@@ -0,0 +1,124 @@
+use crate::exit::{fail, Failure};
+use arrow::datatypes::SchemaRef;
+use arrow::record_batch::RecordBatch;
+use object_store::buffered::BufWriter;
+use object_store::path::Path;
+use object_store::{parse_url_opts, ObjectStore, ObjectStoreExt};
+use parquet::arrow::arrow_writer::ArrowWriterOptions;
+use parquet::arrow::async_writer::AsyncArrowWriter;
+use parquet::errors::ParquetError;
+use parquet::file::properties::WriterProperties;
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+use url::Url;
+
+/// An object store, e.g. an S3 bucket, that the splits are streamed into with `--output-url`.
+pub struct OutputStore {
+    url: String,
+    store: Arc<dyn ObjectStore>,
+    prefix: Path,
+    runtime: Runtime,
+}
+
+impl OutputStore {
+    /// Connects to the store at `url`, configured by the environment variables of its kind of
+    /// store (e.g. `AWS_ACCESS_KEY_ID` and `AWS_REGION` for `s3://` URLs).
+    pub fn new(url: &str) -> Self {
+        let (store, prefix) = Url::parse(url)
+            .map_err(|x| x.to_string())
+            .and_then(|x| parse_url_opts(&x, std::env::vars()).map_err(|x| x.to_string()))
+            .unwrap_or_else(|error| {
+                fail(
+                    Failure::Usage,
+                    format!("Invalid --output-url {url}: {error}."),
+                )
+            });
+
+        Self {
+            url: url.trim_end_matches('/').to_owned(),
+            store: Arc::from(store),
+            prefix,
+            runtime: tokio::runtime::Builder::new_multi_thread()
+                .enable_all()
+                .build()
+                .unwrap(),
+        }
+    }
+
+    /// Returns the URL of the store.
+    pub fn location(&self) -> &str {
+        &self.url
+    }
+
+    /// Returns the URL of the object `name`.
+    pub fn url(&self, name: &str) -> String {
+        format!("{}/{name}", self.url)
+    }
+
+    fn path(&self, name: &str) -> Path {
+        self.prefix.clone().join(name)
+    }
+
+    /// Returns whether the object `name` exists.
+    pub fn exists(&self, name: &str) -> bool {
+        match self.runtime.block_on(self.store.head(&self.path(name))) {
+            Ok(_) => true,
+            Err(object_store::Error::NotFound { .. }) => false,
+            Err(error) => fail(
+                Failure::Write,
+                format!("Couldn't look up {}: {error}.", self.url(name)),
+            ),
+        }
+    }
+
+    /// Streams `batches` into the Parquet file `name` with `properties`, uploading every batch
+    /// as its own row group as soon as it's encoded, so the file is never buffered in full.
+    ///
+    /// Unlike the files written into the output directory, an empty split has no row groups,
+    /// since empty batches aren't written.
+    pub fn write_parquet(
+        &self,
+        name: &str,
+        batches: impl Iterator<Item = RecordBatch>,
+        schema: SchemaRef,
+        properties: WriterProperties,
+    ) -> Result<(), ParquetError> {
+        let object = BufWriter::new(self.store.clone(), self.path(name));
+        // The root is named like in the files written into the output directory.
+        let options = ArrowWriterOptions::new()
+            .with_properties(properties)
+            .with_schema_root("root".to_owned());
+
+        self.runtime.block_on(async {
+            let mut writer = AsyncArrowWriter::try_new_with_options(object, schema, options)?;
+            for batch in batches {
+                writer.write(&batch).await?;
+                writer.flush().await?;
+            }
+            writer.close().await?;
+            Ok(())
+        })
+    }
+
+    /// Moves the object `from` to `to`, replacing it if it exists.
+    pub fn rename(&self, from: &str, to: &str) {
+        let result = self
+            .runtime
+            .block_on(self.store.rename(&self.path(from), &self.path(to)));
+        if let Err(error) = result {
+            fail(
+                Failure::Write,
+                format!(
+                    "Couldn't move {} to {}: {error}.",
+                    self.url(from),
+                    self.url(to)
+                ),
+            );
+        }
+    }
+
+    /// Removes the object `name`, if it exists.
+    pub fn delete(&self, name: &str) {
+        let _ = self.runtime.block_on(self.store.delete(&self.path(name)));
+    }
+}
@@ -0,0 +1,123 @@
+use crate::dataset::{read_split, split_path, Document, SPLITS};
+use crate::provenance::{ENTITY_ORDER, SCHEMA_VERSION};
+use hashbrown::HashMap;
+use std::path::Path;
+
+/// Returns the identifier documents are compared by, which is the document ID, followed by the
+/// index of the sentence if the files have one row per sentence.
+fn document_key(document: &Document) -> String {
+    match document.sentence {
+        Some(sentence) => format!("{}.{sentence}", document.document_id),
+        None => document.document_id.to_string(),
+    }
+}
+
+/// Returns the problems with the spans of the entities of `document`, including their order
+/// if the files are `ordered` by [`ENTITY_ORDER`].
+fn check_spans(document: &Document, ordered: bool) -> Vec<String> {
+    let length = document.text.chars().count() as u64;
+    let mut problems = vec![];
+
+    for (i, mention) in document.entities.iter().enumerate() {
+        if mention.start >= mention.end {
+            problems.push(format!(
+                "entity {i} starts at {} but ends at {}",
+                mention.start, mention.end
+            ));
+        }
+        if mention.end > length {
+            problems.push(format!(
+                "entity {i} ends at {} past the end of the text ({length} characters)",
+                mention.end
+            ));
+        }
+    }
+
+    if ordered {
+        for (i, pair) in document.entities.windows(2).enumerate() {
+            if (pair[0].start, pair[0].end) > (pair[1].start, pair[1].end) {
+                problems.push(format!(
+                    "entities {i} and {} aren't ordered by their start and end offsets",
+                    i + 1
+                ));
+            }
+        }
+    }
+
+    let mut spans = document
+        .entities
+        .iter()
+        .enumerate()
+        .map(|(i, x)| (x.start, x.end, i))
+        .collect::<Vec<_>>();
+    spans.sort_unstable();
+    for pair in spans.windows(2) {
+        let ((_, end, a), (start, _, b)) = (pair[0], pair[1]);
+        let nested = document.entities[a].nested || document.entities[b].nested;
+        if start < end && !nested {
+            problems.push(format!("entities {a} and {b} overlap"));
+        }
+    }
+
+    problems
+}
+
+/// Checks the generated Parquet files in `input_dir` and prints every violation: a missing or
+/// unsupported schema version, entity spans that are empty, reversed, out of the bounds of the text,
+/// overlapping, or out of the order recorded in the metadata, and documents that are duplicated within or shared between the splits.
+///
+/// Returns whether the files are valid.
+pub fn validate_dataset(input_dir: &str) -> bool {
+    let dir = Path::new(input_dir);
+    let mut violations = 0;
+    let mut report = |message: String| {
+        println!("{message}");
+        violations += 1;
+    };
+
+    let mut seen = HashMap::<String, &str>::new();
+
+    for name in SPLITS {
+        let Some(path) = split_path(dir, name) else {
+            report(format!("{name}: no {name} split in {}", dir.display()));
+            continue;
+        };
+
+        let split = read_split(&path);
+        match split.metadata.get("schema_version") {
+            Some(version) if version == SCHEMA_VERSION => {}
+            Some(version) => report(format!(
+                "{name}: schema version {version} differs from the supported version {SCHEMA_VERSION}"
+            )),
+            None => report(format!("{name}: no schema version in the metadata")),
+        }
+        let ordered = split.metadata.get("entity_order").map(String::as_str) == Some(ENTITY_ORDER);
+
+        for document in &split.documents {
+            let key = document_key(document);
+
+            for problem in check_spans(document, ordered) {
+                report(format!("{name}: document {key}: {problem}"));
+            }
+
+            match seen.try_insert(key.clone(), name) {
+                Ok(_) => {}
+                Err(error) if *error.entry.get() == name => {
+                    report(format!("{name}: document {key} is duplicated"))
+                }
+                Err(error) => report(format!(
+                    "{name}: document {key} is also in {}",
+                    error.entry.get()
+                )),
+            }
+        }
+    }
+
+    if violations > 0 {
+        eprintln!("Found {violations} violations in {input_dir}.");
+    } else {
+        eprintln!("No violations found in {input_dir}.");
+    }
+
+    violations == 0
+}
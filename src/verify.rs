@@ -0,0 +1,182 @@
+use crate::exit;
+use crate::http::HttpClient;
+use crate::mapping::{Resolution, Resolver};
+use crate::normalize::TitleNormalizer;
+use crate::online::{fetch_items, ItemStatus};
+use crate::redirects::parse_qid;
+use hashbrown::{HashMap, HashSet};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+
+/// Checks every QID assigned to one of `titles` against the live Wikidata and
+/// writes a TSV report of the discrepancies to `path`.
+///
+/// A QID is reported if its item doesn't exist, if it's a redirect to another
+/// item, or if its English Wikipedia sitelink doesn't point to the expected title.
+/// Only the titles found in the mappings are expected to be the sitelinks, once normalized
+/// with `normalizer`; the other resolvers resolve the titles to other pages by design.
+/// Returns the number of discrepancies found.
+pub fn verify_qids(
+    titles: &HashSet<String>,
+    mapping: &HashMap<String, Resolution>,
+    normalizer: &TitleNormalizer,
+    client: &mut HttpClient,
+    path: &str,
+) -> usize {
+    let mut pairs = titles
+        .iter()
+        .filter_map(|title| {
+            let resolution = mapping.get(title)?;
+            Some((title.as_str(), resolution.qid?, resolution.resolver))
+        })
+        .collect::<Vec<_>>();
+    pairs.sort_unstable_by_key(|(title, _, _)| *title);
+
+    let mut qids = pairs.iter().map(|(_, qid, _)| *qid).collect::<Vec<_>>();
+    qids.sort_unstable();
+    qids.dedup();
+
+    let items = fetch_items(client, &qids);
+
+    let mut writer = BufWriter::new(exit::create(path));
+    writeln!(writer, "title\tqid\tproblem\tdetails").unwrap();
+
+    let mut discrepancies = 0;
+    for (title, qid, resolver) in pairs {
+        let problem = match items.get(&qid) {
+            None | Some(ItemStatus::Missing) => Some(("missing", String::new())),
+            Some(ItemStatus::Found { qid: target, .. }) if *target != qid => {
+                Some(("redirect", format!("Q{target}")))
+            }
+            Some(ItemStatus::Found { enwiki: None, .. }) => Some(("no-sitelink", String::new())),
+            Some(ItemStatus::Found {
+                enwiki: Some(enwiki),
+                ..
+            }) if resolver == Resolver::Mapping
+                && normalizer.normalize(&enwiki.replace(' ', "_")) != title =>
+            {
+                Some(("sitelink-mismatch", enwiki.clone()))
+            }
+            Some(ItemStatus::Found { .. }) => None,
+        };
+
+        if let Some((problem, details)) = problem {
+            writeln!(writer, "{title}\tQ{qid}\t{problem}\t{details}").unwrap();
+            discrepancies += 1;
+        }
+    }
+
+    discrepancies
+}
+
+/// Checks every QID assigned to one of `titles` against a TSV file of sitelinks with one
+/// `qid<TAB>site<TAB>title` triple per line, and writes a TSV report of the conflicts to `path`.
+///
+/// A QID is reported if its item has no `enwiki` sitelink, if the sitelink points to a
+/// different title, or if the title is the `enwiki` sitelink of another item. If `site` is
+/// given, the items without a sitelink to it are reported as well, and the reports include
+/// the title in that language to help to tell apart, e.g., a film and its soundtrack.
+/// Returns the number of conflicts found.
+pub fn check_sitelinks(
+    titles: &HashSet<String>,
+    mapping: &HashMap<String, Resolution>,
+    sitelinks: &str,
+    site: Option<&str>,
+    normalizer: &TitleNormalizer,
+    path: &str,
+) -> usize {
+    let mut pairs = titles
+        .iter()
+        .filter_map(|title| Some((title.as_str(), mapping.get(title)?.qid?)))
+        .collect::<Vec<_>>();
+    pairs.sort_unstable();
+
+    let qids = pairs.iter().map(|(_, qid)| *qid).collect::<HashSet<_>>();
+
+    let mut enwiki = HashMap::<u32, String>::new();
+    let mut other = HashMap::<u32, String>::new();
+    let mut by_title = HashMap::<String, u32>::new();
+
+    let reader = BufReader::new(exit::open(sitelinks));
+    for line in reader.lines().map(|x| x.unwrap()) {
+        let mut fields = line.split('\t');
+        let (Some(qid), Some(link_site), Some(link_title)) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+        let Some(qid) = parse_qid(qid) else {
+            continue;
+        };
+
+        if link_site == "enwiki" {
+            let link_title = normalizer.normalize(&link_title.replace(' ', "_"));
+            if titles.contains(&link_title) {
+                by_title.insert(link_title.clone(), qid);
+            }
+            if qids.contains(&qid) {
+                enwiki.insert(qid, link_title);
+            }
+        } else if Some(link_site) == site && qids.contains(&qid) {
+            other.insert(qid, link_title.to_owned());
+        }
+    }
+
+    let mut writer = BufWriter::new(exit::create(path));
+    writeln!(writer, "title\tqid\tproblem\tdetails\tother_title").unwrap();
+
+    let mut conflicts = 0;
+    for (title, qid) in pairs {
+        let problem = match (enwiki.get(&qid), by_title.get(title)) {
+            (_, Some(other)) if *other != qid => Some(("title-conflict", format!("Q{other}"))),
+            (None, _) => Some(("no-enwiki", String::new())),
+            (Some(enwiki), _) if enwiki != title => Some(("enwiki-mismatch", enwiki.clone())),
+            _ => site
+                .filter(|_| !other.contains_key(&qid))
+                .map(|site| ("no-sitelink", site.to_owned())),
+        };
+
+        if let Some((problem, details)) = problem {
+            let other_title = other.get(&qid).map(|x| x.as_str()).unwrap_or_default();
+            writeln!(
+                writer,
+                "{title}\tQ{qid}\t{problem}\t{details}\t{other_title}"
+            )
+            .unwrap();
+            conflicts += 1;
+        }
+    }
+
+    conflicts
+}
+
+/// Writes a TSV report of the titles whose pageid in the mappings differs from the one given
+/// in the TSV, which likely point at stale or wrong links, and returns them with both pageids.
+pub fn write_pageid_report(
+    mapping: &HashMap<String, Resolution>,
+    pageids: &HashMap<String, u32>,
+    path: Option<&str>,
+) -> Vec<(String, u32, u32)> {
+    let mut disagreements = pageids
+        .iter()
+        .filter_map(|(title, &pageid)| {
+            let resolution = mapping.get(title)?;
+            (resolution.pageid != pageid).then(|| (title.clone(), pageid, resolution.pageid))
+        })
+        .collect::<Vec<_>>();
+    disagreements.sort_unstable();
+
+    if let Some(path) = path {
+        let mut writer = BufWriter::new(exit::create(path));
+        writeln!(writer, "title\ttsv_pageid\tmapping_pageid\tresolver").unwrap();
+        for (title, tsv, resolved) in &disagreements {
+            writeln!(
+                writer,
+                "{title}\t{tsv}\t{resolved}\t{}",
+                mapping[title].resolver.name()
+            )
+            .unwrap();
+        }
+    }
+
+    disagreements
+}
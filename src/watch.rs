@@ -0,0 +1,78 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// How often the watched files are checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Returns the modification times of the files, which are `None` for missing files.
+fn modification_times(paths: &[String]) -> BTreeMap<&str, Option<SystemTime>> {
+    paths
+        .iter()
+        .map(|path| {
+            let modified = fs::metadata(path).and_then(|x| x.modified()).ok();
+            (path.as_str(), modified)
+        })
+        .collect()
+}
+
+/// Reruns the conversion whenever any of the files at `paths` change, until interrupted.
+///
+/// Every conversion runs in a child process with the arguments of this one except `--watch`,
+/// so a failing conversion is reported with its exit code without ending the watch. Since
+/// every run replaces the outputs of the previous one, they're passed `--overwrite`.
+pub fn watch(paths: &[String]) {
+    let mut args = std::env::args_os()
+        .skip(1)
+        .filter(|x| x != "--watch")
+        .collect::<Vec<_>>();
+    if !args.iter().any(|x| x == "--overwrite") {
+        args.push("--overwrite".into());
+    }
+    let exe = std::env::current_exe().unwrap();
+
+    let run = || {
+        // The environment variable keeps a `watch` in a configuration file from applying to the child.
+        match Command::new(&exe)
+            .args(&args)
+            .env("AIDA_WATCH", "false")
+            .status()
+        {
+            Ok(status) if status.success() => eprintln!("The conversion succeeded."),
+            Ok(status) => match status.code() {
+                Some(code) => eprintln!("The conversion failed with the exit code {code}."),
+                None => eprintln!("The conversion was terminated."),
+            },
+            Err(error) => eprintln!("Couldn't run the conversion: {error}."),
+        }
+        eprintln!(
+            "Watching {} files for changes; press Ctrl-C to stop.",
+            paths.len()
+        );
+    };
+
+    let mut times = modification_times(paths);
+    run();
+
+    loop {
+        thread::sleep(POLL_INTERVAL);
+
+        let current = modification_times(paths);
+        if current == times {
+            continue;
+        }
+
+        let changed = paths
+            .iter()
+            .filter(|x| current[x.as_str()] != times[x.as_str()])
+            .cloned()
+            .collect::<Vec<_>>();
+        eprintln!("Changed: {}; rerunning the conversion.", changed.join(", "));
+
+        times = current;
+        run();
+        // Changes made while converting trigger another run.
+    }
+}